@@ -1,15 +1,95 @@
 
 
 use std::cell::{Ref, RefCell};
-use std::{cell::Cell, io};
-use ratatui::{layout::{Constraint, Direction, Layout}, Frame};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::cell::Cell;
+use std::error::Error;
+use std::io;
+use std::process::ExitStatus;
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, ModifierKeyCode, MouseButton, MouseEvent, MouseEventKind},
+    layout::{Constraint, Direction, Layout, Position, Rect}, text::{Line, Span}, widgets::LineGauge, Frame
+};
 
-use crate::fms::{PathKind, list_files_in_directory};
+use crate::archive::{self, Archive, ArchiveFormat};
+use crate::column::Column;
+use crate::config::{Config, DirSizeMode, LayoutMode, ListColumns, ScrollMode};
+use crate::dialog::{
+    AttributeDialog, AttributeResult, ConflictDialog, ConflictResult, Dialog, ExecDialog, ExecResult, InputDialog, InputResult,
+    MenuDialog, MenuResult, MessageDialog, PackDialog, PackResult
+};
+use crate::dirsize::{DirSizeState, DirSizer};
+use crate::fms::{self, FmsError, PathKind};
+use crate::format;
+use crate::fsinfo::{self, FsInfo};
+use crate::i18n::{tr, Msg};
+use crate::job::Job;
+use crate::keymap::{Action, KeyMatch, KeyPress, Keymap};
+use crate::loader::DirLoader;
+use crate::opener::{self, OpenRule};
+use crate::operation::{FileOperation, OperationKind, OperationStatus};
+use crate::permission;
+use crate::sftp::{ConnectStep, Connecting, SftpFs, SftpUrl};
+use crate::shell::{self, CapturedCommand, ExternalCommand, History, Placeholders};
+use crate::theme::Theme;
+use crate::usage::{UsageResult, UsageScan, UsageView};
+use crate::vfs::{LocalFs, Vfs};
+use crate::viewer::Viewer;
+use crate::watch::DirWatcher;
 
 use crate::header::HeaderWidget;
 use crate::body::BodyWidget;
 use crate::footer::FooterWidget;
 
+/// マウスでクリックできる領域の種類
+///
+/// # バリアント
+/// * `Action` - ファンクションキー（クリックで割り当てられた操作を実行する）
+/// * `Path` - ヘッダーのパスの階層（クリックでそのディレクトリに移動する）
+/// * `Key` - ダイアログ・ビューアのキー（クリックでそのキーを入力する）
+#[derive(Clone, Debug)]
+pub enum ClickTarget {
+    Action(Action),
+    Path(String),
+    Key(KeyCode),
+}
+
+/// ローカル以外のファイルシステム（アーカイブ・SFTPの接続先など）の閲覧状態
+///
+/// # フィールド
+/// * `vfs` - ファイルシステム
+/// * `origin` - ファイルシステムの場所（アーカイブファイルのパスやSFTPの接続先など、ヘッダーのパスの先頭に表示する）
+/// * `dir` - ファイルシステム内の現在のディレクトリ（空はルート）
+/// * `remote` - SFTPの接続先かどうか（コピーはダウンロードになる）
+struct Mount {
+    vfs: Rc<dyn Vfs>,
+    origin: String,
+    dir: PathBuf,
+    remote: bool,
+}
+
+/// SFTPの接続後に行う処理
+///
+/// # バリアント
+/// * `Browse` - URLのパスのディレクトリを一覧に表示する
+/// * `Upload` - ローカルのファイルをURLのパスのディレクトリにコピーする
+enum AfterConnect {
+    Browse(Option<String>),
+    Upload(Vec<PathBuf>, Option<String>),
+}
+
+/// ダブルクリックと判定するクリックの間隔
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// ヘッダーに表示するファイルシステムの情報を取得し直す間隔
+const FS_INFO_INTERVAL: Duration = Duration::from_secs(2);
+
+/// マウスホイール1回でフォーカスを移動する行数
+const WHEEL_SCROLL_LINES: isize = 3;
+
 /// アプリケーション状態管理構造体
 /// 
 /// # フィールド
@@ -17,25 +97,132 @@ use crate::footer::FooterWidget;
 /// * `files` - ディレクトリ内のファイル一覧
 /// * `focus_file_no` - フォーカスされているファイル番号
 /// * `max_file_count_in_page` - 1ページに表示できる最大のファイル数
+/// * `marked_files` - マークされているファイル名
+/// * `dialog` - 表示中のダイアログ
+/// * `operation` - 実行中のコピー・移動操作
+/// * `config` - 設定ファイルの内容
+/// * `layout_mode` - ファイル一覧のレイアウト
+/// * `list_columns` - ファイル一覧の列数の指定
+/// * `column_count` - ファイル一覧の実際の列数
+/// * `scroll_mode` - ファイル一覧のスクロール方法
+/// * `scroll_offset` - 1行ずつスクロールする場合の、先頭に表示しているファイル番号
+/// * `list_areas` - ファイル一覧の各列の描画エリア
+/// * `click_areas` - ヘッダー・フッターのクリックできる領域
+/// * `last_click` - ダブルクリック判定用の、最後にクリックした時刻とファイル番号
+/// * `keymap` - キー割り当て
+/// * `pending_keys` - 複数キーの組み合わせの入力途中のキー
+/// * `held_modifiers` - 押されている修飾キー（キーボード拡張に対応した端末のみ）
+/// * `viewer` - 表示中のビューア
+/// * `theme` - 配色
+/// * `open_rules` - ファイルを開くプログラムの規則
+/// * `external_command` - 端末を一時停止して実行を待っている外部コマンド
+/// * `captured_command` - 出力をビューアに表示するために、バックグラウンドで実行中の外部コマンド
+/// * `history` - 実行したコマンドラインの履歴
+/// * `previous_dir` - 1つ前にいたディレクトリ（コマンドラインの `%o`）
+/// * `mount` - 閲覧中のローカル以外のファイルシステム（`dir_path` はアーカイブなどのあるディレクトリのまま）
+/// * `jobs` - バックグラウンドで実行中の処理と、エラーが発生した場合のメッセージダイアログのタイトル
+/// * `remotes` - 接続済みのSFTPの接続先（同じ接続先には接続を使い回す）
+/// * `connecting` - ホスト鍵の確認やパスワードの入力を待っている接続と、接続後に行う処理
+/// * `connect_job` - バックグラウンドで接続・認証中の処理と、接続後に行う処理
+/// * `watcher` - 現在のローカルのディレクトリの変更の監視
+/// * `loader` - バックグラウンドで読み込み中の、現在のディレクトリのファイル一覧
+/// * `reloading` - 読み直し中のファイル一覧（読み込みが終わるまでは、表示中の一覧をそのままにする）
+/// * `dir_sizer` - ディレクトリのサイズを計算するバックグラウンドの処理
+/// * `dir_sizes` - ディレクトリのサイズの計算状況（パスごとのキャッシュ）
+/// * `usage` - 表示中の使用量ビュー
+/// * `usage_scan` - バックグラウンドで実行中の、使用量ビューのスキャン
+/// * `fs_info` - 最後に取得したファイルシステムの情報（ディレクトリパスと取得した時刻）
 /// 
 pub struct App {
     dir_path: RefCell<String>,
     files: RefCell<Vec<PathKind>>,
     focused_file_index: Cell<usize>,
     max_files_per_page: Cell<usize>,
+    marked_files: RefCell<HashSet<String>>,
+    dialog: RefCell<Option<Dialog>>,
+    operation: RefCell<Option<FileOperation>>,
+    config: Config,
+    layout_mode: Cell<LayoutMode>,
+    list_columns: Cell<ListColumns>,
+    column_count: Cell<usize>,
+    scroll_mode: Cell<ScrollMode>,
+    scroll_offset: Cell<usize>,
+    list_areas: RefCell<Vec<Rect>>,
+    click_areas: RefCell<Vec<(Rect, ClickTarget)>>,
+    last_click: Cell<Option<(Instant, usize)>>,
+    keymap: Keymap,
+    pending_keys: RefCell<Vec<KeyPress>>,
+    held_modifiers: Cell<KeyModifiers>,
+    viewer: RefCell<Option<Viewer>>,
+    theme: Theme,
+    open_rules: Vec<OpenRule>,
+    external_command: RefCell<Option<ExternalCommand>>,
+    captured_command: RefCell<Option<CapturedCommand>>,
+    history: RefCell<History>,
+    previous_dir: RefCell<Option<String>>,
+    mount: RefCell<Option<Mount>>,
+    jobs: RefCell<Vec<(Job, String)>>,
+    remotes: RefCell<Vec<Rc<SftpFs>>>,
+    connecting: RefCell<Option<(Connecting, AfterConnect)>>,
+    connect_job: RefCell<Option<(Job<fms::Result<ConnectStep>>, AfterConnect)>>,
+    watcher: RefCell<Option<DirWatcher>>,
+    loader: RefCell<Option<DirLoader>>,
+    reloading: RefCell<Option<Vec<PathKind>>>,
+    dir_sizer: DirSizer,
+    dir_sizes: RefCell<HashMap<PathBuf, DirSizeState>>,
+    usage: RefCell<Option<UsageView>>,
+    usage_scan: RefCell<Option<UsageScan>>,
+    fs_info: RefCell<Option<(String, Instant, Option<FsInfo>)>>,
 }
 
 // App構造体の実装
 impl App {
     /// 新しいAppインスタンスを返します。
-    pub fn new(dir_path: &str) ->io::Result<App> {
-        // 初期読み込み
-        let files = list_files_in_directory(dir_path)?;
+    pub fn new(dir_path: &str, config: Config) -> Result<App, Box<dyn Error>> {
+        // 初期読み込み（ファイルは読み込んだ分から一覧に追加する）
+        let loader = DirLoader::start(Path::new(dir_path))?;
+        let keymap = Keymap::new(&config.keys)?;
+        let theme = Theme::load(&config.theme)?;
         Ok(App {
             dir_path: RefCell::new(dir_path.to_string()),
-            files: RefCell::new(files),
+            files: RefCell::new(vec![fms::parent_dir_entry()]),
             focused_file_index: Cell::new(0),      
             max_files_per_page: Cell::new(0),      
+            marked_files: RefCell::new(HashSet::new()),
+            dialog: RefCell::new(None),
+            operation: RefCell::new(None),
+            layout_mode: Cell::new(config.layout.mode),
+            list_columns: Cell::new(config.layout.list_columns),
+            column_count: Cell::new(1),
+            scroll_mode: Cell::new(config.layout.scroll),
+            scroll_offset: Cell::new(0),
+            list_areas: RefCell::new(Vec::new()),
+            click_areas: RefCell::new(Vec::new()),
+            last_click: Cell::new(None),
+            keymap,
+            pending_keys: RefCell::new(Vec::new()),
+            held_modifiers: Cell::new(KeyModifiers::NONE),
+            viewer: RefCell::new(None),
+            theme,
+            open_rules: config.open_rules(),
+            external_command: RefCell::new(None),
+            captured_command: RefCell::new(None),
+            history: RefCell::new(History::load()),
+            previous_dir: RefCell::new(None),
+            mount: RefCell::new(None),
+            jobs: RefCell::new(Vec::new()),
+            remotes: RefCell::new(Vec::new()),
+            connecting: RefCell::new(None),
+            connect_job: RefCell::new(None),
+            watcher: RefCell::new(DirWatcher::new(Path::new(dir_path))),
+            loader: RefCell::new(Some(loader)),
+            reloading: RefCell::new(None),
+            dir_sizer: DirSizer::new(),
+            dir_sizes: RefCell::new(HashMap::new()),
+            usage: RefCell::new(None),
+            usage_scan: RefCell::new(None),
+            fs_info: RefCell::new(None),
+            config,
         })
     }
 
@@ -44,17 +231,734 @@ impl App {
         self.dir_path.borrow().to_owned()
     }
 
+    /// ヘッダーに表示するパスを取得する
+    /// アーカイブなどの閲覧中は、その場所にファイルシステム内のディレクトリを付け加えたパスです
+    pub fn display_path(&self) -> String {
+        match self.mount.borrow().as_ref() {
+            Some(mount) if mount.dir.as_os_str().is_empty() => mount.origin.clone(),
+            Some(mount) => format!("{}/{}", mount.origin, mount.dir.display()),
+            None => self.dir_path(),
+        }
+    }
+
+    /// アーカイブなどのローカル以外のファイルシステムを閲覧中かどうかを取得する
+    pub fn is_mounted(&self) -> bool {
+        self.mount.borrow().is_some()
+    }
+
+    /// 現在のディレクトリのファイルシステムとディレクトリで処理を行う
+    /// ローカル以外のファイルシステムを閲覧中でなければ、ローカルの `dir_path` です
+    fn with_vfs<T>(&self, f: impl FnOnce(&dyn Vfs, &Path) -> T) -> T {
+        match self.mount.borrow().as_ref() {
+            Some(mount) => f(mount.vfs.as_ref(), &mount.dir),
+            None => f(&LocalFs, Path::new(&self.dir_path())),
+        }
+    }
+
+    /// ヘッダーに表示したパスの階層に移動する
+    /// 閲覧中のファイルシステム内のパスの場合は、そのファイルシステム内のディレクトリに移動します
+    /// ローカルのディレクトリではない階層（`sftp://` の `sftp:/` など）の場合は何もしません
+    pub fn navigate_to(&self, path: &str) {
+        let inner = self.mount.borrow().as_ref().and_then(|mount| {
+            let rest = path.strip_prefix(mount.origin.as_str())?;
+            (rest.is_empty() || rest.starts_with('/')).then(|| PathBuf::from(rest.trim_start_matches('/')))
+        });
+        match inner {
+            Some(inner) => self.set_mount_dir(inner),
+            None if Path::new(path).is_dir() => self.set_dir_path(path),
+            None => {}
+        }
+    }
+
     /// ディレクトリパスを設定する
-    /// ディレクトリ内のファイル一覧を取得し、フォーカスファイル番号を0に設定します
+    /// ディレクトリ内のファイル一覧のバックグラウンドでの読み込みを開始し、フォーカスファイル番号を0に設定します
+    /// アーカイブなどの閲覧中の場合は、閉じてローカルのディレクトリに戻ります
+    /// ディレクトリを開けない場合はメッセージダイアログを表示し、移動しません
     pub fn set_dir_path(&self, dir_path: &str) {
-        self.dir_path.replace(dir_path.into());
-        let files = list_files_in_directory(dir_path).unwrap();
+        let loader = match DirLoader::start(Path::new(dir_path)) {
+            Ok(loader) => loader,
+            Err(e) => {
+                self.show_message(tr(Msg::Error), vec![error_line(Path::new(dir_path), &e)]);
+                return;
+            }
+        };
+        self.mount.replace(None);
+        let previous_dir = self.dir_path.replace(dir_path.into());
+        if previous_dir != dir_path {
+            self.previous_dir.replace(Some(previous_dir));
+        }
+        self.set_files(vec![fms::parent_dir_entry()]);
+        self.cancel_dir_sizes();
+        self.usage_scan.replace(None);
+        self.loader.replace(Some(loader));
+        self.reloading.replace(None);
+        self.watcher.replace(DirWatcher::new(Path::new(dir_path)));
+    }
+
+    /// バックグラウンドで読み込んだファイルを一覧に追加する
+    /// フォーカスは同じ名前のファイルに残します
+    /// 読み直しの場合は、読み込みが終わってから一覧を入れ替えます（フォーカスとマークは同じ名前のファイルに残します）
+    /// 読み込めなかったファイルがある場合は、読み込みの終了後にメッセージダイアログを表示します（ダイアログの表示中は閉じるまで待ちます）
+    pub fn poll_loading(&self) {
+        let mut loader = self.loader.borrow_mut();
+        let Some(active) = loader.as_mut() else {
+            return;
+        };
+        if let Some(reloaded) = self.reloading.borrow_mut().as_mut() {
+            active.merge_into(reloaded);
+        } else {
+            let focused = self.focused_file();
+            let mut files = self.files.borrow_mut();
+            if active.merge_into(&mut files) {
+                // 一覧は並べ替え済みのため、フォーカスしていたファイルの位置は二分探索で求められる
+                let index = files.partition_point(|file| fms::compare_file_order(file, &focused).is_lt());
+                self.focused_file_index.set(index.min(files.len().saturating_sub(1)));
+            }
+        }
+        if self.has_dialog() {
+            return;
+        }
+        let Some(errors) = active.take_finished() else {
+            return;
+        };
+        loader.take();
+        drop(loader);
+        if let Some(files) = self.reloading.take() {
+            self.update_files(files);
+            // 操作でディレクトリの中身が変わった可能性があるため、計算済みのサイズを破棄する
+            self.forget_dir_sizes(&[PathBuf::from(self.dir_path())]);
+        }
+        self.auto_measure_dir_sizes();
+        if !errors.is_empty() {
+            let errors = errors.iter().map(|(path, e)| error_line(path, e)).collect();
+            self.show_message(tr(Msg::LoadError), errors);
+        }
+    }
+
+    /// ファイル一覧をバックグラウンドで読み込み中かどうかを取得する
+    pub fn is_loading(&self) -> bool {
+        self.loader.borrow().is_some()
+    }
+
+    /// ファイル一覧の読み込みを中止する
+    /// 読み込み済みのファイルは一覧に残します（読み直しの場合は、読み直す前の一覧のままにします）
+    ///
+    /// # 戻り値
+    /// `bool` - 読み込み中だったかどうか
+    pub fn cancel_loading(&self) -> bool {
+        self.reloading.replace(None);
+        let cancelled = self.loader.take().is_some();
+        if cancelled {
+            self.auto_measure_dir_sizes();
+        }
+        cancelled
+    }
+
+    /// ディレクトリのサイズの計算状況を取得する
+    /// ローカルのディレクトリで、計算を依頼したものだけです
+    pub fn dir_size(&self, file: &PathKind) -> Option<DirSizeState> {
+        let PathKind::DIR(dir) = file else {
+            return None;
+        };
+        if dir.file_name == ".." || self.is_mounted() {
+            return None;
+        }
+        self.dir_sizes.borrow().get(&Path::new(&self.dir_path()).join(&dir.file_name)).copied()
+    }
+
+    /// 操作対象のディレクトリのサイズを計算する
+    /// 親ディレクトリ（..）にフォーカスしていてマークがない場合は、一覧のすべてのディレクトリを計算します
+    /// 計算済みのディレクトリも計算し直します
+    pub fn measure_dir_sizes(&self) {
+        if self.is_mounted() {
+            return;
+        }
+        let all = self.marked_files.borrow().is_empty() && self.focused_file().info().file_name == "..";
+        let targets = self.target_names();
+        let names: Vec<String> = self.files.borrow().iter()
+            .filter(|file| is_measurable_dir(file))
+            .map(|file| file.info().file_name.clone())
+            .filter(|name| all || targets.contains(name))
+            .collect();
+        let dir_path = self.dir_path();
+        for name in names {
+            self.request_dir_size(Path::new(&dir_path).join(name));
+        }
+    }
+
+    /// 自動で計算する設定の場合に、一覧のまだ計算していないディレクトリのサイズを計算する
+    fn auto_measure_dir_sizes(&self) {
+        if self.config.layout.dir_sizes != DirSizeMode::Auto || self.is_mounted() || self.is_loading() {
+            return;
+        }
+        let dir_path = self.dir_path();
+        let paths: Vec<PathBuf> = self.files.borrow().iter()
+            .filter(|file| is_measurable_dir(file))
+            .map(|file| Path::new(&dir_path).join(&file.info().file_name))
+            .filter(|path| !self.dir_sizes.borrow().contains_key(path))
+            .collect();
+        for path in paths {
+            self.request_dir_size(path);
+        }
+    }
+
+    /// ディレクトリのサイズの計算を依頼する
+    fn request_dir_size(&self, path: PathBuf) {
+        self.dir_sizes.borrow_mut().insert(path.clone(), DirSizeState::Measuring);
+        self.dir_sizer.request(path);
+    }
+
+    /// 計算待ち・計算中のディレクトリのサイズの計算を取り消す
+    /// 計算済みのサイズはキャッシュに残します
+    fn cancel_dir_sizes(&self) {
+        self.dir_sizer.clear();
+        self.dir_sizes.borrow_mut().retain(|_, state| matches!(state, DirSizeState::Measured(_)));
+    }
+
+    /// 変更されたディレクトリ以下の、計算済みのサイズを破棄する
+    /// 自動で計算する設定の場合は、一覧のディレクトリを計算し直します
+    ///
+    /// # 引数
+    /// * `paths` - 変更されたパス
+    fn forget_dir_sizes(&self, paths: &[PathBuf]) {
+        self.dir_sizes.borrow_mut().retain(|path, _| !paths.iter().any(|changed| path.starts_with(changed)));
+        self.auto_measure_dir_sizes();
+    }
+
+    /// 計算が終わったディレクトリのサイズをキャッシュに反映する
+    pub fn poll_dir_sizes(&self) {
+        let results = self.dir_sizer.take_results();
+        let mut dir_sizes = self.dir_sizes.borrow_mut();
+        for (path, size) in results {
+            dir_sizes.insert(path, DirSizeState::Measured(size));
+        }
+    }
+
+    /// ディレクトリのサイズを計算中かどうかを取得する
+    pub fn is_measuring(&self) -> bool {
+        self.dir_sizes.borrow().values().any(|state| *state == DirSizeState::Measuring)
+    }
+
+    /// 新しいディレクトリのファイル一覧を表示する
+    /// フォーカス・スクロール位置を先頭に戻し、マークを消去します
+    fn set_files(&self, files: Vec<PathKind>) {
         self.files.replace(files);
         self.focused_file_index.set(0);
+        self.scroll_offset.set(0);
+        self.marked_files.borrow_mut().clear();
+    }
+
+    /// フォーカスされているディレクトリに移動する
+    /// 親ディレクトリ（..）の場合は1つ上の階層に移動します
+    pub fn enter_focused_dir(&self) {
+        if let PathKind::DIR(dir) = self.focused_file() {
+            if self.is_mounted() {
+                self.enter_mount_dir(&dir.file_name);
+                return;
+            }
+            let dir_path = self.dir_path();
+            let new_dir_path = if dir.file_name == ".." {
+                match Path::new(&dir_path).parent() {
+                    Some(parent) => parent.to_path_buf(),
+                    None => return,
+                }
+            } else {
+                Path::new(&dir_path).join(&dir.file_name)
+            };
+            if let Some(new_dir_path) = new_dir_path.to_str() {
+                self.set_dir_path(new_dir_path);
+            }
+        }
+    }
+
+    /// アーカイブを開いて、アーカイブのルートを一覧に表示する
+    /// 開けない場合はメッセージダイアログを表示します
+    fn open_archive(&self, path: &Path, format: ArchiveFormat) {
+        let result = Archive::open(path, format)
+            .and_then(|archive| self.mount_vfs(Rc::new(archive), path.display().to_string(), PathBuf::new(), false));
+        if let Err(e) = result {
+            self.show_message(tr(Msg::ArchiveError), vec![error_line(path, &e)]);
+        }
+    }
+
+    /// ローカル以外のファイルシステムに切り替えて、そのディレクトリを一覧に表示する
+    ///
+    /// # 引数
+    /// * `vfs` - ファイルシステム
+    /// * `origin` - ヘッダーのパスの先頭に表示する、ファイルシステムの場所
+    /// * `dir` - 表示するファイルシステム内のディレクトリ
+    /// * `remote` - SFTPの接続先かどうか
+    ///
+    /// # 戻り値
+    /// `fms::Result<()>` - ディレクトリの一覧を取得できない場合はエラー（切り替えない）
+    fn mount_vfs(&self, vfs: Rc<dyn Vfs>, origin: String, dir: PathBuf, remote: bool) -> fms::Result<()> {
+        let files = vfs.list(&dir)?;
+        self.loader.replace(None);
+        self.reloading.replace(None);
+        self.cancel_dir_sizes();
+        self.usage_scan.replace(None);
+        self.mount.replace(Some(Mount { vfs, origin, dir, remote }));
+        self.set_files(files);
+        Ok(())
+    }
+
+    /// SFTPのURLのディレクトリを開いて、一覧に表示する
+    /// 未知のホストの場合はホスト鍵を受け入れるかを確認し、パスワードが必要な場合は入力するダイアログを表示します
+    ///
+    /// # 引数
+    /// * `url` - `sftp://[user@]host[:port][/path]` 形式のURL
+    pub fn open_remote(&self, url: &str) {
+        match SftpUrl::parse(url) {
+            Some(url) => {
+                let path = url.path.clone();
+                self.connect(url, AfterConnect::Browse(path));
+            }
+            None => self.show_message(tr(Msg::Error), vec![tr(Msg::InvalidUrl(url.to_string()))]),
+        }
+    }
+
+    /// SFTPの接続先に接続して、接続後の処理を行う
+    /// 接続済みの接続先の場合は、その接続を使います
+    fn connect(&self, url: SftpUrl, after: AfterConnect) {
+        let origin = url.origin();
+        let connected = self.remotes.borrow().iter().find(|remote| remote.url().origin() == origin).cloned();
+        match connected {
+            Some(remote) => self.after_connect(remote, after),
+            None => self.spawn_connect_step(origin, after, move || Connecting::start(url)),
+        }
+    }
+
+    /// 接続・認証の処理をバックグラウンドで開始する
+    /// 終了すると `poll_connecting` で、確認・入力のダイアログを表示するか接続後の処理を行います
+    ///
+    /// # 引数
+    /// * `origin` - 接続先（実行中の表示に使う）
+    /// * `after` - 接続後に行う処理
+    /// * `step` - 接続・認証の処理
+    fn spawn_connect_step<F>(&self, origin: String, after: AfterConnect, step: F)
+    where
+        F: FnOnce() -> fms::Result<ConnectStep> + Send + 'static,
+    {
+        let job = Job::spawn(origin, move |_| step());
+        self.connect_job.replace(Some((job, after)));
+    }
+
+    /// バックグラウンドでの接続・認証が終わったら、その結果を反映する
+    /// ダイアログの表示中は、閉じるまで待ちます
+    pub fn poll_connecting(&self) {
+        if self.has_dialog() || !self.connect_job.borrow().as_ref().is_some_and(|(job, _)| job.is_finished()) {
+            return;
+        }
+        let Some((job, after)) = self.connect_job.take() else {
+            return;
+        };
+        let origin = job.title().to_string();
+        let step = job.result()
+            .unwrap_or_else(|| Err(FmsError::Io(io::Error::other(format!("{}: the connection task panicked", origin)))));
+        self.on_connect_step(step, after);
+    }
+
+    /// バックグラウンドで接続・認証中かどうかを取得する
+    pub fn is_connecting(&self) -> bool {
+        self.connect_job.borrow().is_some()
+    }
+
+    /// バックグラウンドでの接続・認証を中止する
+    /// 処理中のスレッドは終了を待たずに切り離し、結果は破棄します
+    ///
+    /// # 戻り値
+    /// `bool` - 接続・認証中だったかどうか
+    pub fn cancel_connecting(&self) -> bool {
+        self.connect_job.take().is_some()
+    }
+
+    /// 接続の進行状況に応じて、確認・入力のダイアログを表示するか接続後の処理を行う
+    fn on_connect_step(&self, step: fms::Result<ConnectStep>, after: AfterConnect) {
+        match step {
+            Ok(ConnectStep::Connected(remote)) => {
+                let remote = Rc::new(remote);
+                self.remotes.borrow_mut().push(Rc::clone(&remote));
+                self.after_connect(remote, after);
+            }
+            Ok(ConnectStep::UnknownHost(connecting, fingerprint)) => {
+                let title = tr(Msg::UnknownHostTitle(connecting.url().host.clone()));
+                let menu = MenuDialog::new(&title, vec![tr(Msg::TrustHostKey(fingerprint)), tr(Msg::Cancel)]);
+                self.connecting.replace(Some((connecting, after)));
+                self.dialog.replace(Some(Dialog::HostKey(menu)));
+            }
+            Ok(ConnectStep::Password(connecting)) => {
+                let origin = connecting.url().origin();
+                let title = match connecting.attempts() {
+                    0 => tr(Msg::PasswordPrompt(origin)),
+                    _ => tr(Msg::PasswordRetry(origin)),
+                };
+                self.connecting.replace(Some((connecting, after)));
+                self.dialog.replace(Some(Dialog::Password(InputDialog::new(&title, "").masked())));
+            }
+            Err(e) => self.show_message(tr(Msg::ConnectError), vec![e.to_string()]),
+        }
+    }
+
+    /// 接続したSFTPの接続先で、接続後の処理を行う
+    /// エラーが発生した場合は、接続が切れている場合に備えて接続を破棄し、メッセージダイアログを表示します
+    fn after_connect(&self, remote: Rc<SftpFs>, after: AfterConnect) {
+        let origin = remote.url().origin();
+        let result = match after {
+            AfterConnect::Browse(path) => {
+                let dir = remote.resolve(path.as_deref());
+                let title = Path::new(&origin).join(&dir);
+                self.mount_vfs(Rc::clone(&remote) as Rc<dyn Vfs>, origin.clone(), dir, true)
+                    .map_err(|e| (tr(Msg::ConnectError), vec![error_line(&title, &e)]))
+            }
+            AfterConnect::Upload(sources, path) => {
+                let dest = remote.resolve(path.as_deref());
+                let title = Path::new(&origin).join(&dest);
+                let error = match remote.stat(&dest) {
+                    Ok(PathKind::DIR(_)) => {
+                        self.spawn_remote_copy(&remote, sources, dest, title.display().to_string(), true);
+                        return;
+                    }
+                    Ok(PathKind::FILE(_)) => FmsError::NotADirectory(dest),
+                    Err(e) => e,
+                };
+                Err((tr(Msg::OperationError(OperationKind::Copy)), vec![error_line(&title, &error)]))
+            }
+        };
+        if let Err((title, errors)) = result {
+            let mounted = self.mount.borrow().as_ref().is_some_and(|mount| mount.origin == origin);
+            if !mounted {
+                self.remotes.borrow_mut().retain(|connected| !Rc::ptr_eq(connected, &remote));
+            }
+            self.show_message(title, errors);
+        }
+    }
+
+    /// 移動先のディレクトリ（またはSFTPのURL）を入力するダイアログを表示する
+    pub fn start_goto(&self) {
+        self.dialog.replace(Some(Dialog::Goto(InputDialog::new(&tr(Msg::GotoPrompt), &self.display_path()))));
+    }
+
+    /// 入力されたディレクトリ（またはSFTPのURL）に移動する
+    /// ローカルの相対パスは現在のディレクトリからのパスです
+    fn goto(&self, target: &str) {
+        let target = target.trim();
+        if SftpUrl::is_url(target) {
+            self.open_remote(target);
+            return;
+        }
+        let path = Path::new(&self.dir_path()).join(target);
+        match path.canonicalize().ok().filter(|path| path.is_dir()).as_ref().and_then(|path| path.to_str()) {
+            Some(dir_path) => self.set_dir_path(dir_path),
+            None => self.show_message(tr(Msg::Error), vec![FmsError::NotADirectory(path).to_string()]),
+        }
+    }
+
+    /// 閲覧中のファイルシステム内のディレクトリに移動する
+    /// 親ディレクトリ（..）の場合は1つ上の階層に移動し、ルートの場合はローカルのディレクトリに戻ります
+    /// 上の階層に移動した場合は、移動元のディレクトリ（またはアーカイブ）にフォーカスを当てます
+    fn enter_mount_dir(&self, name: &str) {
+        let Some(dir) = self.mount.borrow().as_ref().map(|mount| mount.dir.clone()) else {
+            return;
+        };
+        if name != ".." {
+            self.set_mount_dir(dir.join(name));
+            return;
+        }
+        match (dir.parent(), dir.file_name()) {
+            (Some(parent), Some(child)) => {
+                self.set_mount_dir(parent.to_path_buf());
+                self.focus_file_named(&child.to_string_lossy());
+            }
+            _ => {
+                let mount = self.mount.take();
+                // 読み直しが終わるまでは、アーカイブだけを一覧に表示してフォーカスを当てておく
+                // （ファイルシステム内の一覧のマークは、ローカルのディレクトリに引き継がない）
+                let mut files = vec![fms::parent_dir_entry()];
+                files.extend(mount.and_then(|mount| fms::entry_information(Path::new(&mount.origin), 1).ok()));
+                let last_index = files.len() - 1;
+                self.set_files(files);
+                self.focused_file_index.set(last_index);
+                self.refresh();
+            }
+        }
+    }
+
+    /// 閲覧中のファイルシステム内のディレクトリの一覧を表示する
+    /// 一覧を取得できない場合はメッセージダイアログを表示し、移動しません
+    fn set_mount_dir(&self, dir: PathBuf) {
+        let mut mount = self.mount.borrow_mut();
+        let Some(mount) = mount.as_mut() else {
+            return;
+        };
+        match mount.vfs.list(&dir) {
+            Ok(files) => {
+                mount.dir = dir;
+                self.set_files(files);
+            }
+            Err(e) => {
+                let path = Path::new(&mount.origin).join(&dir);
+                self.show_message(tr(Msg::Error), vec![error_line(&path, &e)]);
+            }
+        }
+    }
+
+    /// 指定した名前のファイルにフォーカスを当てる
+    fn focus_file_named(&self, name: &str) {
+        if let Some(index) = self.files.borrow().iter().position(|file| file.info().file_name == name) {
+            self.focused_file_index.set(index);
+        }
+    }
+
+    /// フォーカスされているファイルを開く
+    /// ディレクトリ・アーカイブの場合は移動し、ファイルの場合は最初に一致した規則のプログラムで開きます
+    /// アーカイブ内のファイルはビューアで表示します
+    pub fn open_focused(&self) {
+        let PathKind::FILE(file) = self.focused_file() else {
+            self.enter_focused_dir();
+            return;
+        };
+        if self.is_mounted() {
+            self.open_viewer();
+            return;
+        }
+        let path = Path::new(&self.dir_path()).join(&file.file_name);
+        if let Some(format) = ArchiveFormat::detect(&file.file_name) {
+            self.open_archive(&path, format);
+            return;
+        }
+        match opener::matching_rules(&self.open_rules, &path).first() {
+            Some(rule) => self.run_open_rule(rule, &path),
+            None => self.show_message(tr(Msg::OpenError), vec![format!("{}: {}", path.display(), tr(Msg::NoOpener))]),
+        }
+    }
+
+    /// フォーカスされているファイルを開くプログラムを選択するメニューを表示する
+    /// 一致するすべての規則を優先順に表示します
+    pub fn start_open_with(&self) {
+        let PathKind::FILE(file) = self.focused_file() else {
+            return;
+        };
+        if self.is_mounted() {
+            return;
+        }
+        let path = Path::new(&self.dir_path()).join(&file.file_name);
+        let items: Vec<String> = opener::matching_rules(&self.open_rules, &path).iter().map(|rule| rule.label()).collect();
+        if items.is_empty() {
+            self.show_message(tr(Msg::OpenError), vec![format!("{}: {}", path.display(), tr(Msg::NoOpener))]);
+            return;
+        }
+        let menu = MenuDialog::new(&tr(Msg::OpenWithTitle(file.file_name.clone())), items);
+        self.dialog.replace(Some(Dialog::OpenWith(path, menu)));
+    }
+
+    /// 規則のプログラムでファイルを開く
+    /// GUIのプログラムは端末から切り離して実行し、それ以外は端末を一時停止して実行するように予約します
+    fn run_open_rule(&self, rule: &OpenRule, path: &Path) {
+        let command_line = rule.command_line(path);
+        let cwd = PathBuf::from(self.dir_path());
+        if rule.background {
+            if let Err(e) = shell::spawn_detached(&command_line, &cwd) {
+                self.show_message(tr(Msg::OpenError), vec![format!("{}: {}", command_line, e)]);
+            }
+        } else {
+            self.external_command.replace(Some(ExternalCommand { command_line, cwd, pause: false, subshell: false }));
+        }
+    }
+
+    /// シェルのコマンドラインを入力するダイアログを表示する
+    pub fn start_exec(&self) {
+        let history = self.history.borrow().entries().to_vec();
+        self.dialog.replace(Some(Dialog::Exec(ExecDialog::new(history))));
+    }
+
+    /// 現在のディレクトリで対話シェルを起動するように予約する
+    /// シェルの終了後は一覧を再読み込みします
+    pub fn start_subshell(&self) {
+        self.external_command.replace(Some(ExternalCommand::subshell(PathBuf::from(self.dir_path()))));
+    }
+
+    /// プレースホルダーを置き換えて、シェルのコマンドラインを現在のディレクトリで実行する
+    /// 出力をビューアに表示する場合はバックグラウンドで実行して終了後にビューアを開き、それ以外は端末を一時停止して実行するように予約します
+    ///
+    /// # 引数
+    /// * `template` - 入力されたコマンドライン
+    /// * `capture` - 出力をビューアに表示するかどうか
+    fn run_shell_command(&self, template: &str, capture: bool) {
+        self.history.borrow_mut().add(template);
+
+        let dir_path = self.dir_path();
+        let focused = self.focused_file().info().file_name.clone();
+        let marked = self.target_paths().iter()
+            .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .collect();
+        let other_dir = self.previous_dir.borrow().clone().unwrap_or_else(|| dir_path.clone());
+        let command_line = shell::expand(template, &Placeholders { focused, marked, dir: dir_path.clone(), other_dir });
+        let cwd = PathBuf::from(dir_path);
+
+        if !capture {
+            self.external_command.replace(Some(ExternalCommand { command_line, cwd, pause: true, subshell: false }));
+            return;
+        }
+        match CapturedCommand::start(command_line.clone(), &cwd) {
+            Ok(command) => {
+                self.captured_command.replace(Some(command));
+            }
+            Err(e) => self.show_message(tr(Msg::Error), vec![command_line, e.to_string()]),
+        }
+    }
+
+    /// 出力をビューアに表示する外部コマンドが終了したら、一覧を再読み込みして出力をビューアに表示する
+    /// 異常終了した場合は、出力の後に終了ステータスを表示します
+    /// ダイアログの表示中は、閉じるまで待ちます
+    pub fn poll_captured_command(&self) {
+        if self.has_dialog() {
+            return;
+        }
+        let Some((mut text, status)) = self.captured_command.borrow_mut().as_mut().and_then(CapturedCommand::take_output) else {
+            return;
+        };
+        let Some(command) = self.captured_command.take() else {
+            return;
+        };
+        if let Some(status) = status.filter(|status| !status.success()) {
+            text.push_str(&format!("\n{}\n", tr(Msg::CommandFailed(status.to_string()))));
+        }
+        self.refresh();
+        let viewer = Viewer::new(command.command_line(), &text).border_style(self.theme.viewer_border);
+        self.viewer.replace(Some(viewer));
+    }
+
+    /// 出力をビューアに表示する外部コマンドを実行中かどうかを取得する
+    pub fn is_running_command(&self) -> bool {
+        self.captured_command.borrow().is_some()
+    }
+
+    /// 出力をビューアに表示する外部コマンドを終了させる
+    /// 終了までの出力は、終了ステータスとともにビューアに表示します
+    ///
+    /// # 戻り値
+    /// `bool` - 実行中だったかどうか
+    pub fn cancel_captured_command(&self) -> bool {
+        match self.captured_command.borrow_mut().as_mut() {
+            Some(command) => {
+                command.kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 端末を一時停止して実行を待っている外部コマンドを取り出す
+    pub fn take_external_command(&self) -> Option<ExternalCommand> {
+        self.external_command.take()
+    }
+
+    /// 外部コマンドの終了後に、一覧を再読み込みして結果を表示する
+    /// 起動できなかった場合や異常終了した場合はメッセージダイアログを表示します
+    ///
+    /// # 引数
+    /// * `command` - 実行した外部コマンド
+    /// * `result` - 終了ステータス
+    pub fn finish_external_command(&self, command: &ExternalCommand, result: io::Result<ExitStatus>) {
+        // 実行中に離された修飾キーのイベントは受け取れないので、記録を消去する
+        self.held_modifiers.set(KeyModifiers::NONE);
+        self.refresh();
+        let error = match result {
+            // 出力を表示したまま待つコマンドとサブシェルは、終了ステータスも端末で確認済み
+            Ok(status) if status.success() || command.pause || command.subshell => return,
+            Ok(status) => tr(Msg::CommandFailed(status.to_string())),
+            Err(e) => e.to_string(),
+        };
+        self.show_message(tr(Msg::Error), vec![command.command_line.clone(), error]);
+    }
+
+    /// 監視しているディレクトリの変更を一覧に反映する
+    /// 変更されたファイルだけを読み直し、フォーカスとマークは同じ名前のファイルに残します
+    /// アーカイブなどの閲覧中は反映せず、ダイアログの表示中と一覧の読み込み中は終わるまで待ちます
+    pub fn poll_changes(&self) {
+        if self.has_dialog() || self.is_loading() {
+            return;
+        }
+        let Some(changes) = self.watcher.borrow().as_ref().and_then(DirWatcher::take_changes) else {
+            return;
+        };
+        if self.is_mounted() {
+            return;
+        }
+        let dir_path = self.dir_path();
+        if changes.rescan {
+            // ディレクトリが削除された場合などは、一覧をそのままにする
+            let _ = self.start_reloading(&dir_path);
+            return;
+        }
+        let mut files = self.files.borrow().clone();
+        for name in &changes.names {
+            let Some(file_name) = name.to_str() else {
+                continue;
+            };
+            let index = files.iter().position(|file| file.info().file_name == file_name);
+            let file_no = files.len();
+            match (fms::entry_information(&Path::new(&dir_path).join(name), file_no), index) {
+                (Ok(file), Some(index)) => files[index] = file,
+                (Ok(file), None) => files.push(file),
+                (Err(_), Some(index)) => { files.remove(index); }
+                (Err(_), None) => {}
+            }
+        }
+        fms::sort_file_list(&mut files);
+        self.update_files(files);
+        let changed: Vec<PathBuf> = changes.names.iter().map(|name| Path::new(&dir_path).join(name)).collect();
+        self.forget_dir_sizes(&changed);
+    }
+
+    /// 現在のディレクトリのファイル一覧を読み直す
+    /// ローカルのディレクトリはバックグラウンドで読み込み、読み込みが終わってから一覧を入れ替えます
+    /// フォーカスとマークは同じ名前のファイルに残します
+    /// 読み込めない場合はメッセージダイアログを表示し、一覧はそのままにします
+    pub fn refresh(&self) {
+        if self.is_mounted() {
+            match self.with_vfs(|vfs, dir| vfs.list(dir)) {
+                Ok(files) => self.update_files(files),
+                Err(e) => self.show_message(tr(Msg::Error), vec![error_line(Path::new(&self.display_path()), &e)]),
+            }
+            return;
+        }
+        let dir_path = self.dir_path();
+        if let Err(e) = self.start_reloading(&dir_path) {
+            self.show_message(tr(Msg::Error), vec![error_line(Path::new(&dir_path), &e)]);
+        }
+    }
+
+    /// ローカルのディレクトリの読み直しをバックグラウンドで開始する
+    /// 読み込んだファイルは `poll_loading` で読み込みが終わってから一覧に反映します
+    ///
+    /// # 引数
+    /// * `dir_path` - 読み直すディレクトリ（現在のディレクトリ）
+    fn start_reloading(&self, dir_path: &str) -> fms::Result<()> {
+        let loader = DirLoader::start(Path::new(dir_path))?;
+        self.loader.replace(Some(loader));
+        self.reloading.replace(Some(vec![fms::parent_dir_entry()]));
+        Ok(())
+    }
+
+    /// 同じディレクトリの新しいファイル一覧を表示する
+    /// フォーカスは同じ名前のファイルに残し、なくなった場合はその名前が並ぶはずだった位置の次のファイルに移します
+    /// なくなったファイルのマークは消去します
+    fn update_files(&self, files: Vec<PathKind>) {
+        let focused = self.focused_file();
+        let focused_name = &focused.info().file_name;
+        let index = files.iter().position(|file| file.info().file_name == *focused_name)
+            .unwrap_or_else(|| {
+                let next = files.partition_point(|file| fms::compare_file_order(file, &focused).is_lt());
+                next.min(files.len().saturating_sub(1))
+            });
+        self.marked_files.borrow_mut().retain(|name| files.iter().any(|file| file.info().file_name == *name));
+        self.files.replace(files);
+        self.focused_file_index.set(index);
     }
 
     /// ファイル一覧を取得する
-    pub fn file_list(&self) -> Ref<Vec<PathKind>> {
+    pub fn file_list(&self) -> Ref<'_, Vec<PathKind>> {
         self.files.borrow()
     }
 
@@ -77,6 +981,571 @@ impl App {
     pub fn set_focus_file_no(&self, no: usize) {
         self.focused_file_index.set(no);
     }
+
+    /// フォーカスされているファイルのマークを切り替えて、次のファイルにフォーカスを移す
+    /// 親ディレクトリ（..）はマークできません
+    pub fn toggle_mark(&self) {
+        let file_name = self.focused_file().info().file_name.clone();
+        if file_name != ".." {
+            let mut marked_files = self.marked_files.borrow_mut();
+            if !marked_files.remove(&file_name) {
+                marked_files.insert(file_name);
+            }
+        }
+        let next = self.focused_file_index.get() + 1;
+        if next < self.count_file_list() {
+            self.focused_file_index.set(next);
+        }
+    }
+
+    /// ファイルがマークされているかどうかを取得する
+    pub fn is_marked(&self, file_name: &str) -> bool {
+        self.marked_files.borrow().contains(file_name)
+    }
+
+    /// 操作対象のファイルのパスを取得する
+    /// マークされたファイルがある場合はそのファイル、ない場合はフォーカスされているファイルが対象です
+    pub fn target_paths(&self) -> Vec<PathBuf> {
+        let dir_path = self.dir_path();
+        self.target_names().into_iter()
+            .map(|name| Path::new(&dir_path).join(name))
+            .collect()
+    }
+
+    /// 操作対象のファイル名を取得する（一覧の順）
+    /// 親ディレクトリ（..）は含みません
+    fn target_names(&self) -> Vec<String> {
+        let marked_files = self.marked_files.borrow();
+        let files = self.files.borrow();
+        let names: Vec<&str> = if marked_files.is_empty() {
+            vec![files[self.focused_file_index.get()].info().file_name.as_str()]
+        } else {
+            files.iter()
+                .map(|file| file.info().file_name.as_str())
+                .filter(|name| marked_files.contains(*name))
+                .collect()
+        };
+        names.into_iter()
+            .filter(|name| *name != "..")
+            .map(String::from)
+            .collect()
+    }
+
+    /// ファイル一覧の表示列と幅を取得する
+    pub fn columns(&self) -> Vec<(Column, usize)> {
+        self.config.layout.columns(self.layout_mode.get())
+    }
+
+    /// ファイル一覧のレイアウトを標準と詳細（パーミッション・所有者など）で切り替える
+    pub fn toggle_layout(&self) {
+        let layout_mode = match self.layout_mode.get() {
+            LayoutMode::Compact => LayoutMode::Detailed,
+            LayoutMode::Detailed => LayoutMode::Compact,
+        };
+        self.layout_mode.set(layout_mode);
+    }
+
+    /// ダイアログが表示されているかどうかを取得する
+    pub fn has_dialog(&self) -> bool {
+        self.dialog.borrow().is_some()
+    }
+
+    /// フッターに表示する、ダイアログで使えるキーと操作の名前を取得する
+    /// ダイアログが表示されていない場合は `None` を返します
+    pub fn dialog_function_keys(&self) -> Option<Vec<(KeyCode, String)>> {
+        self.dialog.borrow().as_ref().map(|dialog| dialog.function_keys())
+    }
+
+    /// ビューアが表示されているかどうかを取得する
+    pub fn has_viewer(&self) -> bool {
+        self.viewer.borrow().is_some()
+    }
+
+    /// フォーカスされているファイルの内容をビューアで表示する
+    /// 読み込めない場合はメッセージダイアログを表示します
+    pub fn open_viewer(&self) {
+        let PathKind::FILE(file) = self.focused_file() else {
+            return;
+        };
+        let title = Path::new(&self.display_path()).join(&file.file_name).display().to_string();
+        match self.with_vfs(|vfs, dir| Viewer::open(vfs, &dir.join(&file.file_name), &title)) {
+            Ok(viewer) => { self.viewer.replace(Some(viewer.border_style(self.theme.viewer_border))); }
+            Err(e) => self.show_message(tr(Msg::ViewError), vec![error_line(Path::new(&title), &e)]),
+        }
+    }
+
+    /// ビューア表示中のキー入力を処理する
+    pub fn handle_viewer_key(&self, key: KeyEvent) {
+        let close = self.viewer.borrow_mut().as_mut().is_some_and(|viewer| viewer.handle_key(key));
+        if close {
+            self.viewer.replace(None);
+        }
+    }
+
+    /// 使用量ビューが表示されているかどうかを取得する
+    pub fn has_usage(&self) -> bool {
+        self.usage.borrow().is_some()
+    }
+
+    /// フッターに表示する、ビューア・使用量ビューのファンクションキーの番号と操作の名前を取得する
+    /// どちらも表示されていない場合は `None` を返します
+    pub fn view_function_keys(&self) -> Option<&'static [(u8, Msg)]> {
+        if self.has_viewer() {
+            Some(&Viewer::FUNCTION_KEYS)
+        } else if self.has_usage() {
+            Some(&UsageView::FUNCTION_KEYS)
+        } else {
+            None
+        }
+    }
+
+    /// 現在のディレクトリ以下の使用量のスキャンを開始する
+    /// スキャンが終わると使用量ビューを表示します（ローカルのディレクトリのみ）
+    pub fn start_usage_scan(&self) {
+        if self.is_mounted() {
+            return;
+        }
+        let dir_path = self.dir_path();
+        match UsageScan::start(Path::new(&dir_path)) {
+            Ok(scan) => { self.usage_scan.replace(Some(scan)); }
+            Err(e) => self.show_message(tr(Msg::Error), vec![error_line(Path::new(&dir_path), &e)]),
+        }
+    }
+
+    /// 使用量のスキャンが終わっていれば、使用量ビューを表示する
+    pub fn poll_usage_scan(&self) {
+        let result = self.usage_scan.borrow().as_ref()
+            .and_then(|scan| scan.take_result().map(|root| (scan.dir().to_path_buf(), root)));
+        if let Some((dir, root)) = result {
+            self.usage_scan.replace(None);
+            let view = UsageView::new(&dir, root)
+                .border_style(self.theme.viewer_border)
+                .focus_style(self.theme.focus);
+            self.usage.replace(Some(view));
+        }
+    }
+
+    /// 使用量をスキャン中かどうかを取得する
+    pub fn is_scanning(&self) -> bool {
+        self.usage_scan.borrow().is_some()
+    }
+
+    /// 使用量のスキャンを中止する
+    ///
+    /// # 戻り値
+    /// `bool` - スキャン中だった場合は `true`
+    pub fn cancel_usage_scan(&self) -> bool {
+        self.usage_scan.take().is_some()
+    }
+
+    /// 使用量ビュー表示中のキー入力を処理する
+    /// 削除する場合は、確認のダイアログを表示します
+    pub fn handle_usage_key(&self, key: KeyEvent) {
+        let result = self.usage.borrow_mut().as_mut().map(|usage| usage.handle_key(key));
+        match result {
+            Some(UsageResult::Close) => { self.usage.replace(None); }
+            Some(UsageResult::Delete(path)) => {
+                let menu = MenuDialog::new(&tr(Msg::DeleteTitle(path.display().to_string())), vec![tr(Msg::Delete), tr(Msg::Cancel)]);
+                self.dialog.replace(Some(Dialog::Delete(path, menu)));
+            }
+            Some(UsageResult::Pending) | None => {}
+        }
+    }
+
+    /// 使用量ビューで選択したファイルを削除する
+    /// 削除できた場合は使用量ビューから取り除き、ファイル一覧を読み直します
+    fn delete_usage_entry(&self, path: &Path) {
+        if let Err(e) = fms::remove_path(path) {
+            self.show_message(tr(Msg::DeleteError), vec![error_line(path, &e)]);
+            return;
+        }
+        if let Some(usage) = self.usage.borrow_mut().as_mut() {
+            usage.remove_selected();
+        }
+        self.refresh();
+    }
+
+    /// 押されている修飾キーを取得する
+    pub fn held_modifiers(&self) -> KeyModifiers {
+        self.held_modifiers.get()
+    }
+
+    /// 修飾キーの押下・解放を記録する
+    /// フッターのファンクションキーの表示を、修飾キーとの組み合わせに切り替えるために使います
+    ///
+    /// # 引数
+    /// * `key` - 修飾キー
+    /// * `pressed` - 押された場合は `true`、離された場合は `false`
+    pub fn set_modifier_held(&self, key: ModifierKeyCode, pressed: bool) {
+        let modifier = match key {
+            ModifierKeyCode::LeftShift | ModifierKeyCode::RightShift => KeyModifiers::SHIFT,
+            ModifierKeyCode::LeftControl | ModifierKeyCode::RightControl => KeyModifiers::CONTROL,
+            ModifierKeyCode::LeftAlt | ModifierKeyCode::RightAlt => KeyModifiers::ALT,
+            _ => return,
+        };
+        let mut modifiers = self.held_modifiers.get();
+        modifiers.set(modifier, pressed);
+        self.held_modifiers.set(modifiers);
+    }
+
+    /// 修飾キーなしのキー入力があった場合は、修飾キーの記録を消去する
+    /// （修飾キーを離したイベントを受け取れなかった場合に、表示が戻らなくなるのを防ぐ）
+    pub fn sync_modifiers(&self, key: KeyEvent) {
+        if key.modifiers.is_empty() {
+            self.held_modifiers.set(KeyModifiers::NONE);
+        }
+    }
+
+    /// コピー・移動操作を開始する
+    /// 操作先のディレクトリを入力するダイアログを表示します
+    /// アーカイブ・SFTPの接続先の閲覧中は、コピーはローカルへの展開・ダウンロードになり、移動はできません
+    /// ローカルからのコピー先に `sftp://` のURLを入力すると、SFTPの接続先にアップロードします
+    pub fn start_file_operation(&self, kind: OperationKind) {
+        if self.target_paths().is_empty() {
+            return;
+        }
+        if let Some(remote) = self.mount.borrow().as_ref().map(|mount| mount.remote) {
+            if kind == OperationKind::Copy {
+                let title = if remote { tr(Msg::DestinationPrompt(kind)) } else { tr(Msg::ExtractPrompt) };
+                self.dialog.replace(Some(Dialog::Extract(InputDialog::new(&title, &self.dir_path()))));
+            }
+            return;
+        }
+        let dialog = InputDialog::new(&tr(Msg::DestinationPrompt(kind)), &self.dir_path());
+        self.dialog.replace(Some(Dialog::Destination(kind, dialog)));
+    }
+
+    /// パーミッション・所有者の変更を開始する
+    /// 変更内容を入力するダイアログを表示します
+    pub fn start_attribute_change(&self) {
+        if self.is_mounted() {
+            return;
+        }
+        let targets = self.target_paths();
+        let target = match targets.as_slice() {
+            [] => return,
+            [path] => path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+            paths => tr(Msg::FileCount(paths.len())),
+        };
+        let Ok(file) = crate::fms::path_information(&targets[0]) else {
+            return;
+        };
+        self.dialog.replace(Some(Dialog::Attribute(AttributeDialog::new(&target, &file, targets.len() > 1))));
+    }
+
+    /// 閲覧中のアーカイブ・SFTPの接続先などの操作対象のファイルを、ローカルのディレクトリに展開する（ダウンロードする）
+    /// SFTPの接続先からのダウンロードは、バックグラウンドで行います
+    /// エラーが発生した場合はメッセージダイアログを表示します
+    fn extract_targets(&self, dest_dir: &Path) {
+        let names = self.target_names();
+        let Some((targets, origin)) = self.mount.borrow().as_ref().map(|mount| {
+            let targets: Vec<PathBuf> = names.iter().map(|name| mount.dir.join(name)).collect();
+            (targets, mount.remote.then(|| mount.origin.clone()))
+        }) else {
+            return;
+        };
+        if let Some(origin) = origin {
+            let remote = self.remotes.borrow().iter().find(|remote| remote.url().origin() == origin).cloned();
+            if let Some(remote) = remote {
+                self.spawn_remote_copy(&remote, targets, dest_dir.to_path_buf(), dest_dir.display().to_string(), false);
+            }
+            return;
+        }
+        let errors = self.with_vfs(|vfs, _| vfs.copy_out(&targets, dest_dir));
+        if !errors.is_empty() {
+            let errors = errors.iter().map(|(path, e)| error_line(path, e)).collect();
+            self.show_message(tr(Msg::ExtractError), errors);
+        }
+    }
+
+    /// SFTPの接続先とのコピーをバックグラウンドで開始する
+    /// 対象ごとにコピーし、コピーした対象の数を進行状況にします
+    ///
+    /// # 引数
+    /// * `remote` - 接続先（同じ接続を複製して使う）
+    /// * `targets` - コピーするパス（アップロードはローカルのパス、ダウンロードは接続先のパス）
+    /// * `dest` - コピー先のディレクトリ（アップロードは接続先のパス、ダウンロードはローカルのパス）
+    /// * `dest_name` - 進行状況に表示するコピー先
+    /// * `upload` - ローカルから接続先へのコピーかどうか
+    fn spawn_remote_copy(&self, remote: &SftpFs, targets: Vec<PathBuf>, dest: PathBuf, dest_name: String, upload: bool) {
+        let remote = remote.clone();
+        let job = Job::spawn(tr(Msg::CopyingTo(dest_name)), move |progress| {
+            progress.set_total(targets.len() as u64);
+            let mut errors = Vec::new();
+            for target in &targets {
+                progress.set_current(&target.to_string_lossy());
+                let target = std::slice::from_ref(target);
+                errors.extend(if upload { remote.copy_in(target, &dest) } else { remote.copy_out(target, &dest) });
+                progress.add_done(1);
+            }
+            errors
+        });
+        self.jobs.borrow_mut().push((job, tr(Msg::OperationError(OperationKind::Copy))));
+    }
+
+    /// 圧縮を開始する
+    /// 作成するアーカイブの名前と形式を入力するダイアログを表示します
+    /// 名前の初期値は、対象が1つの場合はその名前、複数の場合は現在のディレクトリの名前です
+    pub fn start_pack(&self) {
+        if self.is_mounted() {
+            return;
+        }
+        let targets = self.target_paths();
+        let (target, name) = match targets.as_slice() {
+            [] => return,
+            [path] => {
+                let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                (name.clone(), name)
+            }
+            paths => {
+                let dir_path = self.dir_path();
+                let name = Path::new(&dir_path).file_name().map(|name| name.to_string_lossy().into_owned());
+                (tr(Msg::FileCount(paths.len())), name.unwrap_or_else(|| "archive".to_string()))
+            }
+        };
+        self.dialog.replace(Some(Dialog::Pack(PackDialog::new(&target, &name))));
+    }
+
+    /// 操作対象のファイルを、バックグラウンドでアーカイブに圧縮する
+    /// 既に同じ名前のファイルが存在する場合はメッセージダイアログを表示します
+    fn pack_targets(&self, file_name: &str, format: ArchiveFormat) {
+        let dest = Path::new(&self.dir_path()).join(file_name);
+        if dest.exists() {
+            self.show_message(tr(Msg::Error), vec![FmsError::AlreadyExists(dest).to_string()]);
+            return;
+        }
+        let sources = self.target_paths();
+        let title = tr(Msg::Packing(file_name.to_string()));
+        let job = Job::spawn(title, move |progress| archive::pack(&sources, &dest, format, progress));
+        self.jobs.borrow_mut().push((job, tr(Msg::PackError)));
+    }
+
+    /// バックグラウンドで実行中の処理があるかどうかを取得する
+    pub fn has_jobs(&self) -> bool {
+        !self.jobs.borrow().is_empty()
+    }
+
+    /// 終了したバックグラウンドの処理を片付けて、一覧を再読み込みする
+    /// エラーが発生した場合はメッセージダイアログを表示します（複数の処理が終了した場合は、1つずつ表示します）
+    /// ダイアログの表示中は、入力中の内容を消さないように閉じるまで待ちます
+    pub fn poll_jobs(&self) {
+        if self.has_dialog() {
+            return;
+        }
+        let Some(index) = self.jobs.borrow().iter().position(|(job, _)| job.is_finished()) else {
+            return;
+        };
+        let (job, title) = self.jobs.borrow_mut().remove(index);
+        let errors: Vec<String> = job.join().iter().map(|(path, e)| error_line(path, e)).collect();
+        self.refresh();
+        if !errors.is_empty() {
+            self.show_message(title, errors);
+        }
+    }
+
+    /// 実行中のバックグラウンドの処理がすべて終了するのを待つ
+    ///
+    /// # 戻り値
+    /// `Vec<String>` - 発生したエラーの文言
+    pub fn wait_jobs(&self) -> Vec<String> {
+        self.jobs.take().into_iter()
+            .flat_map(|(job, _)| job.join())
+            .map(|(path, e)| error_line(&path, &e))
+            .collect()
+    }
+
+    /// 操作対象のファイルのパーミッション・所有者を変更する
+    /// エラーが発生した場合はメッセージダイアログを表示します
+    fn change_attributes(&self, mode: Option<String>, owner: Option<String>, recursive: bool) {
+        let mut errors = Vec::new();
+        for path in self.target_paths() {
+            // 所有者を変更するとsetuid・setgidが解除されるため、所有者を先に変更する
+            let mut result = Ok(());
+            if let Some(owner) = &owner {
+                result = permission::change_owner(&path, owner, recursive);
+            }
+            if let (Ok(()), Some(mode)) = (&result, &mode) {
+                result = permission::change_mode(&path, mode, recursive);
+            }
+            if let Err(e) = result {
+                errors.push(error_line(&path, &e));
+            }
+        }
+        self.refresh();
+        if !errors.is_empty() {
+            self.show_message(tr(Msg::AttributeError), errors);
+        }
+    }
+
+    /// ダイアログ表示中のキー入力を処理する
+    pub fn handle_dialog_key(&self, key: KeyEvent) {
+        let Some(mut dialog) = self.dialog.take() else {
+            return;
+        };
+
+        match &mut dialog {
+            Dialog::Destination(kind, input) => match input.handle_key(key) {
+                InputResult::Submit(dest_dir) if *kind == OperationKind::Copy && SftpUrl::is_url(&dest_dir) => {
+                    match SftpUrl::parse(&dest_dir) {
+                        Some(url) => {
+                            let path = url.path.clone();
+                            self.connect(url, AfterConnect::Upload(self.target_paths(), path));
+                        }
+                        None => self.show_message(tr(Msg::Error), vec![tr(Msg::InvalidUrl(dest_dir))]),
+                    }
+                    return;
+                }
+                InputResult::Submit(dest_dir) => {
+                    let dest_dir = Path::new(&self.dir_path()).join(dest_dir);
+                    if !dest_dir.is_dir() {
+                        self.show_message(tr(Msg::Error), vec![FmsError::NotADirectory(dest_dir).to_string()]);
+                        return;
+                    }
+                    let mut operation = FileOperation::new(*kind, self.target_paths(), dest_dir);
+                    let status = operation.run();
+                    self.operation.replace(Some(operation));
+                    self.on_operation_status(status);
+                    return;
+                }
+                InputResult::Cancel => return,
+                InputResult::Pending => {}
+            },
+            Dialog::Extract(input) => match input.handle_key(key) {
+                InputResult::Submit(dest_dir) => {
+                    let dest_dir = Path::new(&self.dir_path()).join(dest_dir);
+                    if !dest_dir.is_dir() {
+                        self.show_message(tr(Msg::Error), vec![FmsError::NotADirectory(dest_dir).to_string()]);
+                        return;
+                    }
+                    self.extract_targets(&dest_dir);
+                    return;
+                }
+                InputResult::Cancel => return,
+                InputResult::Pending => {}
+            },
+            Dialog::Conflict(conflict) => match conflict.handle_key(key) {
+                ConflictResult::Resolve(action, apply_to_all) => {
+                    let status = self.operation.borrow_mut().as_mut()
+                        .map_or(OperationStatus::Finished, |operation| operation.resolve(action, apply_to_all));
+                    self.on_operation_status(status);
+                    return;
+                }
+                ConflictResult::Cancel => {
+                    self.on_operation_status(OperationStatus::Finished);
+                    return;
+                }
+                ConflictResult::Pending => {}
+            },
+            Dialog::Attribute(attribute) => match attribute.handle_key(key) {
+                AttributeResult::Submit { mode, owner, recursive } => {
+                    self.change_attributes(mode, owner, recursive);
+                    return;
+                }
+                AttributeResult::Cancel => return,
+                AttributeResult::Pending => {}
+            },
+            Dialog::OpenWith(path, menu) => match menu.handle_key(key) {
+                MenuResult::Select(index) => {
+                    if let Some(rule) = opener::matching_rules(&self.open_rules, path).get(index) {
+                        self.run_open_rule(rule, path);
+                    }
+                    return;
+                }
+                MenuResult::Cancel => return,
+                MenuResult::Pending => {}
+            },
+            Dialog::Exec(exec) => match exec.handle_key(key) {
+                ExecResult::Submit { command_line, capture } => {
+                    self.run_shell_command(&command_line, capture);
+                    return;
+                }
+                ExecResult::Cancel => return,
+                ExecResult::Pending => {}
+            },
+            Dialog::Pack(pack) => match pack.handle_key(key) {
+                PackResult::Submit { file_name, format } => {
+                    self.pack_targets(&file_name, format);
+                    return;
+                }
+                PackResult::Cancel => return,
+                PackResult::Pending => {}
+            },
+            Dialog::Goto(input) => match input.handle_key(key) {
+                InputResult::Submit(target) => {
+                    self.goto(&target);
+                    return;
+                }
+                InputResult::Cancel => return,
+                InputResult::Pending => {}
+            },
+            Dialog::Password(input) => match input.handle_key(key) {
+                InputResult::Submit(password) => {
+                    if let Some((connecting, after)) = self.connecting.take() {
+                        let origin = connecting.url().origin();
+                        self.spawn_connect_step(origin, after, move || connecting.authenticate(&password));
+                    }
+                    return;
+                }
+                InputResult::Cancel => {
+                    self.connecting.replace(None);
+                    return;
+                }
+                InputResult::Pending => {}
+            },
+            Dialog::HostKey(menu) => match menu.handle_key(key) {
+                MenuResult::Select(0) => {
+                    if let Some((connecting, after)) = self.connecting.take() {
+                        let origin = connecting.url().origin();
+                        self.spawn_connect_step(origin, after, move || connecting.accept_host_key());
+                    }
+                    return;
+                }
+                MenuResult::Select(_) | MenuResult::Cancel => {
+                    self.connecting.replace(None);
+                    return;
+                }
+                MenuResult::Pending => {}
+            },
+            Dialog::Delete(path, menu) => match menu.handle_key(key) {
+                MenuResult::Select(0) => {
+                    self.delete_usage_entry(path);
+                    return;
+                }
+                MenuResult::Select(_) | MenuResult::Cancel => return,
+                MenuResult::Pending => {}
+            },
+            Dialog::Message(_) => return,
+        }
+        self.dialog.replace(Some(dialog));
+    }
+
+    /// コピー・移動操作の進行状況に応じて、ダイアログの表示や一覧の再読み込みを行う
+    fn on_operation_status(&self, status: OperationStatus) {
+        match status {
+            OperationStatus::Conflict { src, dest } => {
+                let Some(kind) = self.operation.borrow().as_ref().map(|operation| operation.kind()) else {
+                    return;
+                };
+                self.dialog.replace(Some(Dialog::Conflict(Box::new(ConflictDialog::new(kind, *src, *dest)))));
+            }
+            OperationStatus::Finished => {
+                let operation = self.operation.take();
+                self.refresh();
+                if let Some(operation) = operation {
+                    if !operation.errors().is_empty() {
+                        let errors = operation.errors().iter().map(|(path, e)| error_line(path, e)).collect();
+                        self.show_message(tr(Msg::OperationError(operation.kind())), errors);
+                    }
+                }
+            }
+        }
+    }
+
+    /// メッセージダイアログを表示する
+    fn show_message(&self, title: String, lines: Vec<String>) {
+        self.dialog.replace(Some(Dialog::Message(MessageDialog::new(&title, lines))));
+    }
+
     /// ファイル一覧のページ内最大表示数を取得する
     pub fn max_files_per_page(&self) -> usize {
         self.max_files_per_page.get()
@@ -87,9 +1556,299 @@ impl App {
         self.max_files_per_page.set(count);
     }
 
+    /// ファイル一覧の列数の指定を取得する
+    pub fn list_columns(&self) -> ListColumns {
+        self.list_columns.get()
+    }
+
+    /// ファイル一覧の列数の指定を設定する
+    pub fn set_list_columns(&self, list_columns: ListColumns) {
+        self.list_columns.set(list_columns);
+    }
+
+    /// ファイル一覧の実際の列数を設定する
+    pub fn set_column_count(&self, count: usize) {
+        self.column_count.set(count);
+    }
+
+    /// ファイル一覧の1列あたりの行数を取得する
+    pub fn rows_per_column(&self) -> usize {
+        self.max_files_per_page.get() / self.column_count.get().max(1)
+    }
+
+    /// ファイル一覧のスクロール方法を、ページ単位と1行ずつで切り替える
+    pub fn toggle_scroll_mode(&self) {
+        let scroll_mode = match self.scroll_mode.get() {
+            ScrollMode::Page => ScrollMode::Smooth,
+            ScrollMode::Smooth => ScrollMode::Page,
+        };
+        self.scroll_mode.set(scroll_mode);
+    }
+
+    /// フォーカスを指定した数だけ移動する
+    /// 先頭・末尾を超える場合は、先頭・末尾で止めます
+    ///
+    /// # 引数
+    /// * `delta` - 移動する数（負の場合は上に移動）
+    pub fn move_focus(&self, delta: isize) {
+        let last_index = self.count_file_list().saturating_sub(1);
+        let next = self.focused_file_index.get().saturating_add_signed(delta).min(last_index);
+        self.focused_file_index.set(next);
+    }
+
+    /// 先頭に表示するファイル番号を取得する
+    /// ページ単位の場合は現在のページの先頭、1行ずつの場合はフォーカスの前後に
+    /// 余白が残るようにスクロール位置を調整してから返します
+    pub fn list_offset(&self) -> usize {
+        let page_size = self.max_files_per_page.get();
+        if page_size == 0 {
+            return 0;
+        }
+
+        match self.scroll_mode.get() {
+            ScrollMode::Page => (self.current_page_no() - 1) * page_size,
+            ScrollMode::Smooth => {
+                let focus = self.focused_file_index.get();
+                let margin = self.config.layout.scroll_margin.min(page_size.saturating_sub(1) / 2);
+                let mut offset = self.scroll_offset.get();
+
+                if focus < offset + margin {
+                    offset = focus.saturating_sub(margin);
+                } else if focus + margin >= offset + page_size {
+                    offset = focus + margin + 1 - page_size;
+                }
+                // 末尾より後ろに空白ができないようにする
+                offset = offset.min(self.count_file_list().saturating_sub(page_size));
+
+                self.scroll_offset.set(offset);
+                offset
+            }
+        }
+    }
+
+    /// 配色を取得する
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// キー割り当てを取得する
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// キー入力を操作に変換する
+    /// 複数キーの組み合わせの途中の場合は、入力されたキーを保持して `None` を返します
+    ///
+    /// # 引数
+    /// * `key` - キー入力
+    ///
+    /// # 戻り値
+    /// `Option<Action>` - 確定した操作
+    pub fn resolve_key(&self, key: KeyEvent) -> Option<Action> {
+        let mut pending_keys = self.pending_keys.borrow_mut();
+        pending_keys.push(KeyPress::from(key));
+        match self.keymap.lookup(&pending_keys) {
+            KeyMatch::Action(action) => {
+                pending_keys.clear();
+                Some(action)
+            }
+            KeyMatch::Pending => None,
+            KeyMatch::Unbound => {
+                // 組み合わせの途中で割り当てのないキーが押された場合は、そのキーだけで照合し直す
+                let retry = pending_keys.len() > 1;
+                pending_keys.clear();
+                drop(pending_keys);
+                if retry { self.resolve_key(key) } else { None }
+            }
+        }
+    }
+
+    /// ファイル一覧の各列の描画エリアを設定する
+    pub fn set_list_areas(&self, areas: Vec<Rect>) {
+        self.list_areas.replace(areas);
+    }
+
+    /// クリックできる領域を追加する
+    pub fn add_click_area(&self, area: Rect, target: ClickTarget) {
+        self.click_areas.borrow_mut().push((area, target));
+    }
+
+    /// マウス操作を処理する
+    /// ファイルのクリックでフォーカス、ダブルクリックで開く、ホイールでフォーカスを移動します
+    /// ダイアログ・ビューア表示中は、フッターのキーのクリックとビューアのスクロールのみ受け付けます
+    ///
+    /// # 引数
+    /// * `mouse` - マウスイベント
+    ///
+    /// # 戻り値
+    /// `Option<Action>` - ファンクションキーがクリックされた場合は、割り当てられた操作
+    pub fn handle_mouse(&self, mouse: MouseEvent) -> Option<Action> {
+        let position = Position::new(mouse.column, mouse.row);
+        let target = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.click_areas.borrow().iter()
+                .find(|(area, _)| area.contains(position))
+                .map(|(_, target)| target.clone()),
+            _ => None,
+        };
+        if let Some(ClickTarget::Key(code)) = target {
+            let key = KeyEvent::from(code);
+            if self.has_dialog() {
+                self.handle_dialog_key(key);
+            } else if self.has_usage() {
+                self.handle_usage_key(key);
+            } else {
+                self.handle_viewer_key(key);
+            }
+            return None;
+        }
+        if self.has_dialog() {
+            return None;
+        }
+        if let Some(viewer) = self.viewer.borrow_mut().as_mut() {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => viewer.scroll_by(WHEEL_SCROLL_LINES),
+                MouseEventKind::ScrollUp => viewer.scroll_by(-WHEEL_SCROLL_LINES),
+                _ => {}
+            }
+            return None;
+        }
+        if let Some(usage) = self.usage.borrow_mut().as_mut() {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => usage.move_by(WHEEL_SCROLL_LINES),
+                MouseEventKind::ScrollUp => usage.move_by(-WHEEL_SCROLL_LINES),
+                _ => {}
+            }
+            return None;
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.move_focus(WHEEL_SCROLL_LINES),
+            MouseEventKind::ScrollUp => self.move_focus(-WHEEL_SCROLL_LINES),
+            MouseEventKind::Down(MouseButton::Left) => match target {
+                Some(ClickTarget::Action(action)) => return Some(action),
+                Some(ClickTarget::Path(dir_path)) => self.navigate_to(&dir_path),
+                Some(ClickTarget::Key(_)) | None => self.click_file(position),
+            },
+            _ => {}
+        }
+        None
+    }
+
+    /// クリックされた位置のファイルにフォーカスを当てる
+    /// 同じファイルをダブルクリックした場合は、そのディレクトリに移動するか、ファイルを開きます
+    fn click_file(&self, position: Position) {
+        let index = {
+            let list_areas = self.list_areas.borrow();
+            let Some(column) = list_areas.iter().position(|area| area.contains(position)) else {
+                return;
+            };
+            let row = (position.y - list_areas[column].y) as usize;
+            self.list_offset() + column * self.rows_per_column() + row
+        };
+        if index >= self.count_file_list() {
+            return;
+        }
+
+        let now = Instant::now();
+        let double_click = matches!(self.last_click.get(),
+            Some((time, last_index)) if last_index == index && now.duration_since(time) <= DOUBLE_CLICK_INTERVAL);
+
+        self.focused_file_index.set(index);
+        if double_click {
+            self.last_click.set(None);
+            self.open_focused();
+        } else {
+            self.last_click.set(Some((now, index)));
+        }
+    }
+
+    /// 現在のディレクトリのあるファイルシステムの情報を取得する
+    /// 描画のたびに問い合わせないよう、同じディレクトリでは一定の間隔でのみ取得し直します
+    ///
+    /// # 戻り値
+    /// `Option<FsInfo>` - ファイルシステムの情報（アーカイブ・SFTPの閲覧中や、取得できない場合は `None`）
+    pub fn fs_info(&self) -> Option<FsInfo> {
+        if self.is_mounted() {
+            return None;
+        }
+        let dir_path = self.dir_path();
+        let mut cache = self.fs_info.borrow_mut();
+        match cache.as_ref() {
+            Some((dir, time, info)) if *dir == dir_path && time.elapsed() < FS_INFO_INTERVAL => info.clone(),
+            _ => {
+                let info = fsinfo::filesystem(Path::new(&dir_path));
+                cache.replace((dir_path, Instant::now(), info.clone()));
+                info
+            }
+        }
+    }
+
+    /// ファイル一覧のファイル数と、ファイルの合計サイズを取得する
+    /// 親ディレクトリ（..）は数えず、ディレクトリのサイズは含めません
+    pub fn listing_totals(&self) -> (usize, u64) {
+        let files = self.files.borrow();
+        let count = files.iter().filter(|file| file.info().file_name != "..").count();
+        let size = files.iter()
+            .filter_map(|file| match file {
+                PathKind::FILE(info) => Some(info.file_size),
+                PathKind::DIR(_) => None,
+            })
+            .sum();
+        (count, size)
+    }
+
     /// 現在のページNoを取得する
     pub fn current_page_no(&self) -> usize {
-        (self.focused_file_index.get() / self.max_files_per_page.get()) + 1
+        // 一覧をまだ描画していない場合は、1ページ目とする
+        (self.focused_file_index.get() / self.max_files_per_page.get().max(1)) + 1
+    }
+
+    /// バックグラウンドの処理の進行状況を描画する
+    /// 処理の名前・割合・処理中の対象をラベルにしたゲージを1行ずつ表示します
+    fn draw_jobs(&self, frame: &mut Frame, area: Rect) {
+        let theme = self.theme();
+        let jobs = self.jobs.borrow();
+        // 接続・外部コマンドの実行・一覧の読み込み・使用量のスキャンの状況は、処理の下に1行ずつ表示する
+        let mut status: Vec<Line> = Vec::new();
+        if let Some((job, _)) = self.connect_job.borrow().as_ref() {
+            status.push(Line::styled(tr(Msg::Connecting(job.title().to_string())), theme.label));
+        }
+        if let Some(command) = self.captured_command.borrow().as_ref() {
+            status.push(Line::styled(tr(Msg::RunningCommand(command.command_line().to_string())), theme.label));
+        }
+        if let Some(loaded) = self.loader.borrow().as_ref().map(DirLoader::loaded) {
+            status.push(Line::styled(tr(Msg::Loading(loaded)), theme.label));
+        }
+        if let Some((files, bytes, current)) = self.usage_scan.borrow().as_ref().map(UsageScan::progress) {
+            let size = format::file_size(bytes).trim_start().to_string();
+            status.push(Line::from(vec![
+                Span::styled(tr(Msg::Scanning(format::number(files), size)), theme.label),
+                Span::styled(current.display().to_string(), theme.value),
+            ]));
+        }
+        let rows = Layout::vertical(vec![Constraint::Length(1); jobs.len() + status.len()]).split(area);
+        for (line, row) in status.into_iter().zip(rows.iter().skip(jobs.len())) {
+            frame.render_widget(line, *row);
+        }
+        for ((job, _), row) in jobs.iter().zip(rows.iter()) {
+            let progress = job.progress();
+            let ratio = progress.ratio();
+            // ゲージの幅を残すため、処理中の対象は幅の1/3に収まるように末尾を表示する
+            let current = progress.current();
+            let max_chars = (row.width / 3) as usize;
+            let current: String = current.chars().skip(current.chars().count().saturating_sub(max_chars)).collect();
+            let label = Line::from(vec![
+                Span::styled(format!("{} {:>3}% ", job.title(), (ratio * 100.0) as u32), theme.label),
+                Span::styled(current, theme.value),
+                Span::raw(" "),
+            ]);
+            let gauge = LineGauge::default()
+                .label(label)
+                .ratio(ratio)
+                .filled_style(theme.focus)
+                .unfilled_style(theme.border);
+            frame.render_widget(gauge, *row);
+        }
     }
 
     /// ターミナルに描画する
@@ -100,11 +1859,29 @@ impl App {
             .split(frame.area());
 
 
+        // クリックできる領域は描画のたびに登録し直す
+        self.click_areas.borrow_mut().clear();
+
         if let [header, body, footer] = chunks[..] {
 
-            // ボディ描画
-            let body_widget = BodyWidget::new(self);
-            frame.render_widget(body_widget, body);
+            // バックグラウンドの処理の進行状況と接続・外部コマンドの実行・一覧の読み込み・使用量のスキャンの状況は、ボディの下に1行ずつ描画する
+            let job_count = self.jobs.borrow().len()
+                + usize::from(self.is_connecting())
+                + usize::from(self.is_running_command())
+                + usize::from(self.is_loading())
+                + usize::from(self.is_scanning());
+            let job_count = job_count as u16;
+            let [body, progress] = Layout::vertical([Constraint::Min(1), Constraint::Length(job_count)]).areas(body);
+            self.draw_jobs(frame, progress);
+
+            // ボディ描画（ビューア・使用量ビュー表示中はファイル一覧の代わりに描画する）
+            if let Some(viewer) = self.viewer.borrow_mut().as_mut() {
+                frame.render_widget(viewer, body);
+            } else if let Some(usage) = self.usage.borrow_mut().as_mut() {
+                frame.render_widget(usage, body);
+            } else {
+                frame.render_widget(BodyWidget::new(self), body);
+            }
 
             // ヘッダー描画
             // ボディ描画で、最大のファイル表示数を取得して、ヘッダー描画で使用する
@@ -112,11 +1889,32 @@ impl App {
             frame.render_widget(header_widget, header);
 
             // フッター描画
-            let footer_widget = FooterWidget;
+            let footer_widget = FooterWidget::new(self);
             frame.render_widget(footer_widget, footer);
 
+            // ダイアログ描画
+            if let Some(dialog) = self.dialog.borrow().as_ref() {
+                frame.render_widget(dialog.styled(self.theme.dialog_style()), frame.area());
+            }
         }
 
     }
 
 }
+
+/// サイズを計算するディレクトリかどうかを判定する
+/// 親ディレクトリ（..）と、ディレクトリへのシンボリックリンク（リンク先を二重に数えてしまう）は除きます
+fn is_measurable_dir(file: &PathKind) -> bool {
+    match file {
+        PathKind::DIR(dir) => dir.file_name != ".." && dir.mode.map(|mode| mode & 0o170000) != Some(0o120000),
+        PathKind::FILE(_) => false,
+    }
+}
+
+/// エラーの対象のパスと、画面の言語に変換したエラーの文言を1行にする
+fn error_line(path: &Path, error: &FmsError) -> String {
+    match error.message() {
+        Some(msg) => format!("{}: {}", path.display(), tr(msg)),
+        None => format!("{}: {}", path.display(), error),
+    }
+}
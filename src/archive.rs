@@ -0,0 +1,803 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+
+use crate::fms::{self, FileInformation, FmsError, PathKind};
+use crate::job::Progress;
+use crate::vfs::Vfs;
+
+/// ファイル種別のモードビット（ディレクトリ）
+const MODE_DIR: u32 = 0o040000;
+
+/// ファイル種別のモードビット（通常のファイル）
+const MODE_FILE: u32 = 0o100000;
+
+/// アーカイブの形式
+///
+/// # バリアント
+/// * `Zip` - zip（.zip）
+/// * `Tar` - 圧縮なしのtar（.tar）
+/// * `TarGz` - gzipで圧縮したtar（.tar.gz、.tgz）
+/// * `TarXz` - xzで圧縮したtar（.tar.xz、.txz）
+/// * `TarZst` - zstdで圧縮したtar（.tar.zst、.tzst）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// ファイル名の拡張子と形式の対応（長い拡張子から照合する）
+    const EXTENSIONS: [(&'static str, ArchiveFormat); 8] = [
+        (".tar.gz", ArchiveFormat::TarGz),
+        (".tar.xz", ArchiveFormat::TarXz),
+        (".tar.zst", ArchiveFormat::TarZst),
+        (".tgz", ArchiveFormat::TarGz),
+        (".txz", ArchiveFormat::TarXz),
+        (".tzst", ArchiveFormat::TarZst),
+        (".tar", ArchiveFormat::Tar),
+        (".zip", ArchiveFormat::Zip),
+    ];
+
+    /// ファイル名の拡張子からアーカイブの形式を判定する
+    ///
+    /// # 引数
+    /// * `file_name` - ファイル名
+    ///
+    /// # 戻り値
+    /// `Option<ArchiveFormat>` - アーカイブの形式（アーカイブではない場合は `None`）
+    pub fn detect(file_name: &str) -> Option<ArchiveFormat> {
+        let lower = file_name.to_ascii_lowercase();
+        ArchiveFormat::EXTENSIONS.iter()
+            .find(|(extension, _)| lower.len() > extension.len() && lower.ends_with(extension))
+            .map(|(_, format)| *format)
+    }
+
+    /// 圧縮で作成できる形式（ダイアログで切り替える順）
+    pub const PACK: [ArchiveFormat; 4] = [ArchiveFormat::Zip, ArchiveFormat::TarGz, ArchiveFormat::TarXz, ArchiveFormat::TarZst];
+
+    /// 作成するアーカイブファイルの拡張子を取得する
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::Tar => ".tar",
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::TarXz => ".tar.xz",
+            ArchiveFormat::TarZst => ".tar.zst",
+        }
+    }
+}
+
+/// アーカイブ内のエントリー
+///
+/// # フィールド
+/// * `path` - アーカイブ内のパス（`/` 区切り、末尾の `/` なし）
+/// * `index` - zipのエントリー番号（tarでは先頭からの順番）
+/// * `data_offset` - tarを展開したストリームでの、ファイルの内容の開始位置（zipでは使わない）
+/// * `is_dir` - ディレクトリかどうか
+/// * `is_file` - 通常のファイルかどうか（シンボリックリンクなどは展開しない）
+/// * `info` - 一覧に表示するファイル情報
+struct ArchiveEntry {
+    path: String,
+    index: usize,
+    data_offset: u64,
+    is_dir: bool,
+    is_file: bool,
+    info: FileInformation,
+}
+
+/// ディレクトリとして閲覧するアーカイブ
+/// 開くときに全エントリーの一覧を読み込み、ファイルの内容は必要なときに読み込む
+/// 読み込み専用のファイルシステムとして扱い、変更する操作は `FmsError::ReadOnly` になります
+///
+/// # フィールド
+/// * `path` - アーカイブファイルのパス
+/// * `format` - アーカイブの形式
+/// * `entries` - エントリーの一覧
+pub struct Archive {
+    path: PathBuf,
+    format: ArchiveFormat,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl Archive {
+    /// アーカイブを開いて、エントリーの一覧を読み込む
+    ///
+    /// # 引数
+    /// * `path` - アーカイブファイルのパス
+    /// * `format` - アーカイブの形式
+    pub fn open(path: &Path, format: ArchiveFormat) -> fms::Result<Archive> {
+        let mut archive = Archive {
+            path: path.to_path_buf(),
+            format,
+            entries: Vec::new(),
+        };
+        archive.entries = match format {
+            ArchiveFormat::Zip => archive.read_zip_entries()?,
+            _ => archive.read_tar_entries()?,
+        };
+        Ok(archive)
+    }
+
+    /// zipのエントリーの一覧を読み込む
+    fn read_zip_entries(&self) -> fms::Result<Vec<ArchiveEntry>> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(&self.path)?)).map_err(zip_error)?;
+        let mut entries = Vec::with_capacity(zip.len());
+        for index in 0..zip.len() {
+            let file = zip.by_index_raw(index).map_err(zip_error)?;
+            // アーカイブの外を指すパス（../ や絶対パス）のエントリーは無視する
+            let Some(path) = file.enclosed_name().and_then(|path| normalize(&path)) else {
+                continue;
+            };
+            let is_dir = file.is_dir();
+            let modified = file.last_modified().and_then(|time| {
+                Local.with_ymd_and_hms(time.year().into(), time.month().into(), time.day().into(),
+                    time.hour().into(), time.minute().into(), time.second().into()).single()
+            });
+            let mode = file.unix_mode().map(|mode| (mode & 0o7777) | if is_dir { MODE_DIR } else { MODE_FILE });
+            entries.push(ArchiveEntry {
+                info: entry_info(&path, index, file.size(), modified, mode),
+                path,
+                index,
+                data_offset: 0,
+                is_dir,
+                is_file: file.is_file(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// tarのエントリーの一覧を読み込む
+    fn read_tar_entries(&self) -> fms::Result<Vec<ArchiveEntry>> {
+        let mut tar = tar::Archive::new(self.tar_stream()?);
+        let mut entries = Vec::new();
+        for (index, entry) in tar.entries()?.enumerate() {
+            let entry = entry?;
+            let Some(path) = normalize(&entry.path()?) else {
+                continue;
+            };
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let is_dir = entry_type.is_dir();
+            let modified = header.mtime().ok()
+                .and_then(|mtime| DateTime::from_timestamp(mtime as i64, 0))
+                .map(|datetime| datetime.with_timezone(&Local));
+            let mode = header.mode().ok().map(|mode| (mode & 0o7777) | if is_dir { MODE_DIR } else { MODE_FILE });
+            let mut info = entry_info(&path, index, header.size().unwrap_or(0), modified, mode);
+            info.owner = header.username().ok().flatten().map(String::from);
+            info.group = header.groupname().ok().flatten().map(String::from);
+            entries.push(ArchiveEntry {
+                info,
+                path,
+                index,
+                data_offset: entry.raw_file_position(),
+                is_dir,
+                is_file: entry_type.is_file() || entry_type.is_contiguous(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// 圧縮形式に応じて展開しながら読み込む、tarのストリームを作成する
+    fn tar_stream(&self) -> io::Result<Box<dyn Read>> {
+        let file = BufReader::new(File::open(&self.path)?);
+        Ok(match self.format {
+            ArchiveFormat::TarGz => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new(file)),
+            ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+            ArchiveFormat::Tar | ArchiveFormat::Zip => Box::new(file),
+        })
+    }
+
+    /// アーカイブ内のパスがディレクトリかどうかを判定する
+    /// ディレクトリのエントリーがなくても、その下にエントリーがあればディレクトリとして扱います
+    ///
+    /// # 引数
+    /// * `inner` - アーカイブ内のパス（空文字はアーカイブのルート）
+    fn is_dir(&self, inner: &str) -> bool {
+        if inner.is_empty() {
+            return true;
+        }
+        let prefix = format!("{}/", inner);
+        self.entries.iter().any(|entry| (entry.is_dir && entry.path == inner) || entry.path.starts_with(&prefix))
+    }
+
+    /// アーカイブ内のファイルのエントリーを探す
+    fn find_file(&self, inner: &str) -> fms::Result<&ArchiveEntry> {
+        self.entries.iter()
+            .find(|entry| entry.path == inner && entry.is_file)
+            .ok_or_else(|| FmsError::Io(io::Error::from(io::ErrorKind::NotFound)))
+    }
+
+    /// zipのエントリーの内容を、展開しながら読み込むリーダーを作成する
+    /// 圧縮されたデータの位置から直接読み込むため、zipのリーダーを借用し続けずに済みます
+    fn open_zip_entry(&self, index: usize) -> fms::Result<Box<dyn Read>> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(&self.path)?)).map_err(zip_error)?;
+        let file = zip.by_index_raw(index).map_err(zip_error)?;
+        let (start, size, method) = (file.data_start(), file.compressed_size(), file.compression());
+        if file.encrypted() {
+            return Err(zip_error(zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED)));
+        }
+        drop(file);
+        let mut reader = zip.into_inner();
+        reader.seek(SeekFrom::Start(start))?;
+        let raw = reader.take(size);
+        match method {
+            zip::CompressionMethod::Stored => Ok(Box::new(raw)),
+            zip::CompressionMethod::Deflated => Ok(Box::new(flate2::read::DeflateDecoder::new(raw))),
+            method => Err(FmsError::Io(io::Error::new(io::ErrorKind::Unsupported, format!("{} compression", method)))),
+        }
+    }
+
+    /// アーカイブ内のファイル・ディレクトリを展開する
+    /// ディレクトリはその中身もすべて展開し、展開先に既に存在するファイルは上書きします
+    /// 通常のファイル・ディレクトリ以外（シンボリックリンクなど）は展開しません
+    ///
+    /// # 引数
+    /// * `targets` - 展開するアーカイブ内のパス（それぞれの名前で展開先に作成する）
+    /// * `dest` - 展開先のディレクトリ
+    ///
+    /// # 戻り値
+    /// `Vec<(PathBuf, FmsError)>` - 展開できなかったパスとエラー
+    fn extract(&self, targets: &[String], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        // 展開先は、対象の名前にその下のパスを付け加えたパス（`dir/a` の下の `dir/a/b` は `dest/a/b`）
+        let dest_path = |path: &str| targets.iter().find_map(|target| {
+            let rest = path.strip_prefix(target.as_str())?;
+            let name = target.rsplit('/').next()?;
+            (rest.is_empty() || rest.starts_with('/')).then(|| dest.join(format!("{}{}", name, rest)))
+        });
+
+        let mut errors = Vec::new();
+        // 展開するエントリーのアーカイブ内の順番と展開先
+        let mut files: HashMap<usize, PathBuf> = HashMap::new();
+        for entry in &self.entries {
+            let Some(path) = dest_path(&entry.path) else {
+                continue;
+            };
+            if entry.is_dir {
+                if let Err(e) = fs::create_dir_all(&path) {
+                    errors.push((path, e.into()));
+                }
+            } else if entry.is_file {
+                files.insert(entry.index, path);
+            }
+        }
+        if files.is_empty() {
+            return errors;
+        }
+
+        let result = match self.format {
+            ArchiveFormat::Zip => self.extract_zip(&files, &mut errors),
+            _ => self.extract_tar(&files, &mut errors),
+        };
+        if let Err(e) = result {
+            errors.push((self.path.clone(), e));
+        }
+        errors
+    }
+
+    /// zipのエントリーを展開する
+    fn extract_zip(&self, files: &HashMap<usize, PathBuf>, errors: &mut Vec<(PathBuf, FmsError)>) -> fms::Result<()> {
+        let mut zip = zip::ZipArchive::new(BufReader::new(File::open(&self.path)?)).map_err(zip_error)?;
+        for (index, path) in files {
+            let result = (|| -> fms::Result<()> {
+                let mut file = zip.by_index(*index).map_err(zip_error)?;
+                create_parent(path)?;
+                io::copy(&mut file, &mut File::create(path)?)?;
+                #[cfg(unix)]
+                if let Some(mode) = file.unix_mode() {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                errors.push((path.clone(), e));
+            }
+        }
+        Ok(())
+    }
+
+    /// tarのエントリーを展開する
+    /// 圧縮されたtarは先頭から順にしか読めないため、アーカイブを1度だけ読み通して展開します
+    fn extract_tar(&self, files: &HashMap<usize, PathBuf>, errors: &mut Vec<(PathBuf, FmsError)>) -> fms::Result<()> {
+        let mut tar = tar::Archive::new(self.tar_stream()?);
+        for (index, entry) in tar.entries()?.enumerate() {
+            let mut entry = entry?;
+            let Some(path) = files.get(&index) else {
+                continue;
+            };
+            let result = create_parent(path).and_then(|_| entry.unpack(path).map(|_| ()));
+            if let Err(e) = result {
+                errors.push((path.clone(), e.into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Vfs for Archive {
+    fn list(&self, dir: &Path) -> fms::Result<Vec<PathKind>> {
+        let inner = inner_path(dir)?;
+        if !self.is_dir(&inner) {
+            return Err(FmsError::NotADirectory(self.path.join(&inner)));
+        }
+        let prefix = if inner.is_empty() { String::new() } else { format!("{}/", inner) };
+        let mut children: HashMap<&str, PathKind> = HashMap::new();
+        for entry in &self.entries {
+            let Some(rest) = entry.path.strip_prefix(&prefix) else {
+                continue;
+            };
+            match rest.split_once('/') {
+                // さらに下の階層のエントリーは、直下のディレクトリとして表示する
+                Some((name, _)) => {
+                    children.entry(name).or_insert_with(|| PathKind::DIR(FileInformation {
+                        file_name: name.to_string(),
+                        ..Default::default()
+                    }));
+                }
+                None if rest.is_empty() => {}
+                None if entry.is_dir => { children.insert(rest, PathKind::DIR(entry.info.clone())); }
+                None => { children.entry(rest).or_insert_with(|| PathKind::FILE(entry.info.clone())); }
+            }
+        }
+
+        let mut file_list = vec![PathKind::DIR(FileInformation { file_name: "..".to_string(), ..Default::default() })];
+        file_list.extend(children.into_values());
+        fms::sort_file_list(&mut file_list);
+        Ok(file_list)
+    }
+
+    fn stat(&self, path: &Path) -> fms::Result<PathKind> {
+        let inner = inner_path(path)?;
+        if let Some(entry) = self.entries.iter().find(|entry| entry.path == inner) {
+            return Ok(if entry.is_dir { PathKind::DIR(entry.info.clone()) } else { PathKind::FILE(entry.info.clone()) });
+        }
+        if self.is_dir(&inner) {
+            let file_name = inner.rsplit('/').next().unwrap_or_default().to_string();
+            return Ok(PathKind::DIR(FileInformation { file_name, ..Default::default() }));
+        }
+        Err(FmsError::Io(io::Error::from(io::ErrorKind::NotFound)))
+    }
+
+    fn open_read(&self, path: &Path) -> fms::Result<Box<dyn Read>> {
+        let entry = self.find_file(&inner_path(path)?)?;
+        if self.format == ArchiveFormat::Zip {
+            return self.open_zip_entry(entry.index);
+        }
+        // 圧縮されたtarは先頭から順にしか読めないため、ファイルの内容の位置まで読み飛ばす
+        let mut stream = self.tar_stream()?;
+        io::copy(&mut (&mut stream).take(entry.data_offset), &mut io::sink())?;
+        Ok(Box::new(stream.take(entry.info.file_size)))
+    }
+
+    fn open_write(&self, _path: &Path) -> fms::Result<Box<dyn Write>> {
+        Err(FmsError::ReadOnly)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> fms::Result<()> {
+        Err(FmsError::ReadOnly)
+    }
+
+    fn remove(&self, _path: &Path) -> fms::Result<()> {
+        Err(FmsError::ReadOnly)
+    }
+
+    fn mkdir(&self, _path: &Path) -> fms::Result<()> {
+        Err(FmsError::ReadOnly)
+    }
+
+    fn copy_out(&self, targets: &[PathBuf], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        let mut errors = Vec::new();
+        let mut inner_targets = Vec::with_capacity(targets.len());
+        for target in targets {
+            match inner_path(target) {
+                Ok(inner) => inner_targets.push(inner),
+                Err(e) => errors.push((target.clone(), e)),
+            }
+        }
+        errors.extend(self.extract(&inner_targets, dest));
+        errors
+    }
+}
+
+/// 圧縮するファイル・ディレクトリ
+///
+/// # フィールド
+/// * `path` - 圧縮するパス
+/// * `name` - アーカイブ内のパス（`/` 区切り）
+/// * `metadata` - シンボリックリンクをたどらずに取得したメタデータ
+struct PackEntry {
+    path: PathBuf,
+    name: String,
+    metadata: fs::Metadata,
+}
+
+/// 読み込んだバイト数を進行状況に加算するリーダー
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.progress.add_done(count as u64);
+        Ok(count)
+    }
+}
+
+/// ファイル・ディレクトリをアーカイブに圧縮する
+/// ディレクトリはその中身もすべて圧縮し、シンボリックリンクはリンクのまま格納します
+/// パーミッションと更新日時は、形式が対応している範囲で保存します
+/// 読み込めないファイルは飛ばし、アーカイブに書き込めない場合は作成途中のアーカイブを削除します
+///
+/// # 引数
+/// * `sources` - 圧縮するパス（それぞれの名前でアーカイブのルートに格納する）
+/// * `dest` - 作成するアーカイブファイルのパス（既に存在する場合はエラー）
+/// * `format` - アーカイブの形式
+/// * `progress` - 進行状況（圧縮したファイルのバイト数）
+///
+/// # 戻り値
+/// `Vec<(PathBuf, FmsError)>` - 圧縮できなかったパスとエラー
+pub fn pack(sources: &[PathBuf], dest: &Path, format: ArchiveFormat, progress: &Progress) -> Vec<(PathBuf, FmsError)> {
+    let mut errors = Vec::new();
+    let mut entries = Vec::new();
+    for source in sources {
+        match source.file_name().map(|name| (name, name.to_str())) {
+            Some((_, Some(name))) => collect_entries(source, name.to_string(), &mut entries, &mut errors),
+            Some((name, None)) => errors.push((source.clone(), FmsError::InvalidFileName(name.to_os_string()))),
+            None => {}
+        }
+    }
+    progress.set_total(entries.iter().filter(|entry| entry.metadata.is_file()).map(|entry| entry.metadata.len()).sum());
+
+    let file = match fs::OpenOptions::new().write(true).create_new(true).open(dest) {
+        Ok(file) => file,
+        Err(e) => {
+            let e = match e.kind() {
+                io::ErrorKind::AlreadyExists => FmsError::AlreadyExists(dest.to_path_buf()),
+                _ => e.into(),
+            };
+            errors.push((dest.to_path_buf(), e));
+            return errors;
+        }
+    };
+    let writer = BufWriter::new(file);
+    let result = (|| -> fms::Result<()> {
+        let mut writer = match format {
+            ArchiveFormat::Zip => pack_zip(writer, &entries, progress, &mut errors)?,
+            ArchiveFormat::Tar => pack_tar(writer, &entries, progress, &mut errors)?,
+            ArchiveFormat::TarGz => {
+                let encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                pack_tar(encoder, &entries, progress, &mut errors)?.finish()?
+            }
+            ArchiveFormat::TarXz => pack_tar(xz2::write::XzEncoder::new(writer, 6), &entries, progress, &mut errors)?.finish()?,
+            ArchiveFormat::TarZst => pack_tar(zstd::stream::write::Encoder::new(writer, 0)?, &entries, progress, &mut errors)?.finish()?,
+        };
+        writer.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(dest);
+        errors.push((dest.to_path_buf(), e));
+    }
+    errors
+}
+
+/// 圧縮するファイル・ディレクトリを、ディレクトリの中身までたどって一覧に追加する
+/// ディレクトリの中身は名前順に並べます
+///
+/// # 引数
+/// * `path` - 圧縮するパス
+/// * `name` - アーカイブ内のパス
+/// * `entries` - 圧縮するファイル・ディレクトリの一覧
+/// * `errors` - 読み込めなかったパスとエラー
+fn collect_entries(path: &Path, name: String, entries: &mut Vec<PackEntry>, errors: &mut Vec<(PathBuf, FmsError)>) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return errors.push((path.to_path_buf(), e.into())),
+    };
+    let is_dir = metadata.is_dir();
+    entries.push(PackEntry { path: path.to_path_buf(), name: name.clone(), metadata });
+    if !is_dir {
+        return;
+    }
+
+    let mut children = match fs::read_dir(path).and_then(|dir| dir.collect::<io::Result<Vec<_>>>()) {
+        Ok(children) => children,
+        Err(e) => return errors.push((path.to_path_buf(), e.into())),
+    };
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let child_name = child.file_name();
+        match child_name.to_str() {
+            Some(child_name) => collect_entries(&child.path(), format!("{}/{}", name, child_name), entries, errors),
+            None => errors.push((child.path(), FmsError::InvalidFileName(child_name))),
+        }
+    }
+}
+
+/// ファイル・ディレクトリをzipに書き込む
+fn pack_zip<W: Write + Seek>(writer: W, entries: &[PackEntry], progress: &Progress, errors: &mut Vec<(PathBuf, FmsError)>)
+    -> fms::Result<W> {
+    let mut zip = zip::ZipWriter::new(writer);
+    for entry in entries {
+        progress.set_current(&entry.name);
+        let options = zip_options(&entry.metadata);
+        let file_type = entry.metadata.file_type();
+        if file_type.is_dir() {
+            zip.add_directory(entry.name.as_str(), options).map_err(zip_error)?;
+        } else if file_type.is_symlink() {
+            match fs::read_link(&entry.path) {
+                Ok(target) => zip.add_symlink(entry.name.as_str(), target.to_string_lossy(), options).map_err(zip_error)?,
+                Err(e) => errors.push((entry.path.clone(), e.into())),
+            }
+        } else if file_type.is_file() {
+            match File::open(&entry.path) {
+                Ok(file) => {
+                    zip.start_file(entry.name.as_str(), options).map_err(zip_error)?;
+                    io::copy(&mut ProgressReader { inner: file, progress }, &mut zip)?;
+                }
+                Err(e) => errors.push((entry.path.clone(), e.into())),
+            }
+        }
+    }
+    zip.finish().map_err(zip_error)
+}
+
+/// zipのエントリーのオプション（圧縮方法・更新日時・パーミッション）を作成する
+fn zip_options(metadata: &fs::Metadata) -> zip::write::SimpleFileOptions {
+    let mut options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(metadata.len() >= u32::MAX as u64);
+    if let Some(time) = metadata.modified().ok().and_then(zip_time) {
+        options = options.last_modified_time(time);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        options = options.unix_permissions(metadata.permissions().mode() & 0o7777);
+    }
+    options
+}
+
+/// 更新日時をzipの日時（ローカル時刻、1980～2107年）に変換する
+fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    let local = DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        local.year().try_into().ok()?, local.month() as u8, local.day() as u8,
+        local.hour() as u8, local.minute() as u8, local.second() as u8,
+    ).ok()
+}
+
+/// ファイル・ディレクトリをtarに書き込む
+/// ヘッダーにはパーミッション・所有者・更新日時をそのまま記録します
+fn pack_tar<W: Write>(writer: W, entries: &[PackEntry], progress: &Progress, errors: &mut Vec<(PathBuf, FmsError)>)
+    -> fms::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    tar.mode(tar::HeaderMode::Complete);
+    tar.follow_symlinks(false);
+    for entry in entries {
+        progress.set_current(&entry.name);
+        if !entry.metadata.is_file() {
+            tar.append_path_with_name(&entry.path, &entry.name)?;
+            continue;
+        }
+        match File::open(&entry.path) {
+            Ok(file) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata_in_mode(&entry.metadata, tar::HeaderMode::Complete);
+                tar.append_data(&mut header, &entry.name, ProgressReader { inner: file, progress })?;
+            }
+            Err(e) => errors.push((entry.path.clone(), e.into())),
+        }
+    }
+    Ok(tar.into_inner()?)
+}
+
+/// ファイルシステムとしてのパスを、アーカイブ内のパス（空文字はルート）にする
+fn inner_path(path: &Path) -> fms::Result<String> {
+    if path.as_os_str().is_empty() {
+        return Ok(String::new());
+    }
+    normalize(path).ok_or_else(|| FmsError::InvalidFileName(path.as_os_str().to_os_string()))
+}
+
+/// アーカイブ内のパスを `/` 区切りの文字列にする
+/// アーカイブの外を指すパス（`..` や絶対パスを含む）の場合は `None` を返します
+fn normalize(path: &Path) -> Option<String> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!components.is_empty()).then(|| components.join("/"))
+}
+
+/// エントリーのファイル情報を作成する
+fn entry_info(path: &str, index: usize, size: u64, modified: Option<DateTime<Local>>, mode: Option<u32>) -> FileInformation {
+    FileInformation {
+        file_no: index,
+        file_name: path.rsplit('/').next().unwrap_or(path).to_string(),
+        file_size: size,
+        last_modified: modified,
+        mode,
+        ..Default::default()
+    }
+}
+
+/// 親ディレクトリがなければ作成する
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+/// zipのエラーをファイル操作のエラーに変換する
+fn zip_error(e: zip::result::ZipError) -> FmsError {
+    match e {
+        zip::result::ZipError::Io(e) => FmsError::Io(e),
+        e => FmsError::Io(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ファイル一覧の名前を取得する
+    fn names(files: &[PathKind]) -> Vec<String> {
+        files.iter().map(|file| file.info().file_name.clone()).collect()
+    }
+
+    /// 展開先のディレクトリ以下のファイルを、相対パスの一覧にする（名前順）
+    fn extracted(dir: &Path) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in fs::read_dir(&current).unwrap().map(Result::unwrap) {
+                let relative = entry.path().strip_prefix(dir).unwrap().to_string_lossy().replace('\\', "/");
+                if entry.file_type().unwrap().is_dir() {
+                    pending.push(entry.path());
+                    paths.push(format!("{}/", relative));
+                } else {
+                    paths.push(relative);
+                }
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    /// アーカイブ内のパスをそのまま格納したtarを作成する（tarの作成時の `..` や絶対パスの検査を通さない）
+    fn write_raw_tar(path: &Path, entries: &[(&str, &str)]) {
+        let mut tar = tar::Builder::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            tar.append(&header, content.as_bytes()).unwrap();
+        }
+        tar.finish().unwrap();
+    }
+
+    /// アーカイブ内のパスをそのまま格納したzipを作成する
+    fn write_raw_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn detect_by_extension() {
+        assert_eq!(ArchiveFormat::detect("a.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect("a.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::detect("a.tar.gz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::detect("A.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::detect("a.tar.xz"), Some(ArchiveFormat::TarXz));
+        assert_eq!(ArchiveFormat::detect("a.txz"), Some(ArchiveFormat::TarXz));
+        assert_eq!(ArchiveFormat::detect("a.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(ArchiveFormat::detect("a.tzst"), Some(ArchiveFormat::TarZst));
+        // 拡張子だけの名前や、途中に拡張子を含む名前はアーカイブではない
+        assert_eq!(ArchiveFormat::detect(".zip"), None);
+        assert_eq!(ArchiveFormat::detect("a.gz"), None);
+        assert_eq!(ArchiveFormat::detect("a.zip.txt"), None);
+        for format in ArchiveFormat::PACK {
+            assert_eq!(ArchiveFormat::detect(&format!("a{}", format.extension())), Some(format));
+        }
+    }
+
+    #[test]
+    fn entries_outside_the_archive_are_skipped() {
+        let temp = tempfile::tempdir().unwrap();
+        let entries = [("../evil.txt", "x"), ("/abs.txt", "x"), ("dir/../../up.txt", "x"), ("./ok.txt", "ok")];
+        let tar_path = temp.path().join("raw.tar");
+        write_raw_tar(&tar_path, &entries);
+        let zip_path = temp.path().join("raw.zip");
+        write_raw_zip(&zip_path, &entries);
+
+        for (path, format) in [(tar_path, ArchiveFormat::Tar), (zip_path, ArchiveFormat::Zip)] {
+            let archive = Archive::open(&path, format).unwrap();
+            assert_eq!(names(&archive.list(Path::new("")).unwrap()), ["..", "ok.txt"], "{:?}", format);
+
+            let dest = temp.path().join(format!("out-{:?}", format));
+            fs::create_dir(&dest).unwrap();
+            let errors = archive.copy_out(&[PathBuf::from("ok.txt")], &dest);
+            assert!(errors.is_empty(), "{:?}", errors);
+            assert_eq!(extracted(&dest), ["ok.txt"]);
+            assert!(!temp.path().join("evil.txt").exists() && !temp.path().join("up.txt").exists());
+        }
+    }
+
+    #[test]
+    fn extract_does_not_confuse_names_sharing_a_prefix() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("prefix.zip");
+        write_raw_zip(&path, &[("a/x.txt", "x"), ("ab/y.txt", "y"), ("abc", "z")]);
+        let archive = Archive::open(&path, ArchiveFormat::Zip).unwrap();
+
+        let dest = temp.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        let errors = archive.copy_out(&[PathBuf::from("a")], &dest);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(extracted(&dest), ["a/", "a/x.txt"]);
+    }
+
+    #[test]
+    fn pack_browse_and_extract_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir_all(source.join("dir/sub")).unwrap();
+        fs::write(source.join("dir/f1.txt"), "one").unwrap();
+        fs::write(source.join("dir/sub/f2.txt"), "two").unwrap();
+        fs::write(source.join("top.txt"), "top").unwrap();
+        let sources = [source.join("dir"), source.join("top.txt")];
+
+        for format in [ArchiveFormat::Zip, ArchiveFormat::TarGz] {
+            let path = temp.path().join(format!("packed{}", format.extension()));
+            let progress = Progress::default();
+            let errors = pack(&sources, &path, format, &progress);
+            assert!(errors.is_empty(), "{:?}: {:?}", format, errors);
+            assert_eq!(progress.ratio(), 1.0);
+
+            let archive = Archive::open(&path, ArchiveFormat::detect(&path.to_string_lossy()).unwrap()).unwrap();
+            assert_eq!(names(&archive.list(Path::new("")).unwrap()), ["..", "dir", "top.txt"]);
+            assert_eq!(names(&archive.list(Path::new("dir")).unwrap()), ["..", "sub", "f1.txt"]);
+            assert!(matches!(archive.stat(Path::new("dir/sub")), Ok(PathKind::DIR(_))));
+            match archive.stat(Path::new("dir/sub/f2.txt")) {
+                Ok(PathKind::FILE(info)) => assert_eq!(info.file_size, 3),
+                _ => panic!("{:?}: dir/sub/f2.txt should be a file", format),
+            }
+            let mut text = String::new();
+            archive.open_read(Path::new("dir/sub/f2.txt")).unwrap().read_to_string(&mut text).unwrap();
+            assert_eq!(text, "two");
+            assert!(matches!(archive.mkdir(Path::new("new")), Err(FmsError::ReadOnly)));
+
+            let dest = temp.path().join(format!("out-{:?}", format));
+            fs::create_dir(&dest).unwrap();
+            let errors = archive.copy_out(&[PathBuf::from("dir/sub"), PathBuf::from("top.txt")], &dest);
+            assert!(errors.is_empty(), "{:?}: {:?}", format, errors);
+            assert_eq!(extracted(&dest), ["sub/", "sub/f2.txt", "top.txt"]);
+            assert_eq!(fs::read_to_string(dest.join("sub/f2.txt")).unwrap(), "two");
+            assert_eq!(fs::read_to_string(dest.join("top.txt")).unwrap(), "top");
+        }
+    }
+}
@@ -1,54 +1,57 @@
-use std::vec;
-
-use ratatui::{buffer::Buffer, layout::{Constraint, Direction, Layout, Rect}, style::{Color, Style}, text::{Line, Span}, widgets::{Block, List, ListItem, Widget}
+use ratatui::{buffer::Buffer, layout::{Constraint, Direction, Layout, Rect}, style::Style, text::{Line, Span}, widgets::{List, ListItem, Widget}
 };
 
-use crate::{app::App, fms::PathKind};
+use crate::{app::App, column::{fit_columns, required_width, Column}, fms::PathKind};
 
 pub struct BodyWidget<'a> {
     app: &'a App,
 }
 
 impl BodyWidget<'_> {
-    pub fn new<'a>(app: &'a App) -> BodyWidget<'a> {
+    pub fn new(app: &App) -> BodyWidget<'_> {
         BodyWidget { app }
     }
 }
 
 impl Widget for BodyWidget<'_> {
     fn render (self, area: Rect, buf: &mut Buffer) {
-        // 描画エリアを2分割
+        // 列数を決めて、描画エリアを等分割
+        let columns = self.app.columns();
+        let column_count = self.app.list_columns()
+            .count(area.width as usize, required_width(&columns))
+            .min(area.width as usize)
+            .max(1);
+        self.app.set_column_count(column_count);
+
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .constraints(vec![Constraint::Ratio(1, column_count as u32); column_count])
             .split(area);
-        
-        let  area_with = chunks[0].width;
 
         // ファイルリスト
-        let draw_item_max_count = area.height * 2;
-        self.app.set_max_files_per_page(draw_item_max_count as usize);
+        let rows_per_column = area.height as usize;
+        let draw_item_max_count = rows_per_column * column_count;
+        self.app.set_max_files_per_page(draw_item_max_count);
 
-        // 現在のページ番号に基づいてオフセットを計算
-        let current_page_no = self.app.current_page_no();
-        let offset = (current_page_no - 1) * draw_item_max_count as usize;
+        // スクロール方法に応じて、先頭に表示するファイル番号を計算
+        let offset = self.app.list_offset();
 
-        // 現在のページ番号に基づいてファイルリストを描画
-        let mut left_items: Vec<ListItem> = Vec::new();
-        let mut right_items: Vec<ListItem> = Vec::new();
+        // 先頭に表示するファイル番号からファイルリストを描画
+        // 上から下、左から右の順に並べる
+        let mut column_items: Vec<Vec<ListItem>> = (0..column_count).map(|_| Vec::new()).collect();
         let file_list = self.app.file_list();
-        for (i, file) in file_list.iter().skip(offset).take(draw_item_max_count as usize).enumerate() {
+        for (i, file) in file_list.iter().skip(offset).take(draw_item_max_count).enumerate() {
             // ファイル情報の描画ラインインスタンスを取得
-            let file_info_line = get_file_info_line(&self.app, i, offset, file, area_with);   
-            if i < area.height as usize {
-                left_items.push(file_info_line);
-            } else {
-                right_items.push(file_info_line);
-            }
+            let chunk = &chunks[i / rows_per_column];
+            let file_info_line = get_file_info_line(self.app, i, offset, file, &columns, chunk.width);
+            column_items[i / rows_per_column].push(file_info_line);
+        }
+        for (items, chunk) in column_items.into_iter().zip(chunks.iter()) {
+            render_file_list(items, chunk, buf);
         }
-        render_file_list(left_items, &chunks[0], buf);
-        render_file_list(right_items, &chunks[1], buf);
 
+        // マウスでクリックされた位置を判定するため、各列の描画エリアを記録する
+        self.app.set_list_areas(chunks.to_vec());
     }
 
 }
@@ -61,42 +64,45 @@ impl Widget for BodyWidget<'_> {
 /// * `index` - ファイルリストのインデックス
 /// * `offset` - ファイルリストのオフセット
 /// * `file_info` - ファイル情報
-/// * `area_with` - 描画エリアの幅
+/// * `columns` - 表示列と幅
+/// * `area_width` - 描画エリアの幅
 /// 
 /// # 戻り値
 /// `ListItem` - ファイル情報のListItem
 /// 
-fn get_file_info_line<'a>(app: &App, index: usize, offset: usize, file_info: &'a PathKind, area_width: u16) -> ListItem<'a> {
-    // 背景色
-    let bg = if  (index+offset) == app.focus_file_no() {
-        Color::LightBlue
+fn get_file_info_line<'a>(app: &App, index: usize, offset: usize, file_info: &'a PathKind, columns: &[(Column, usize)], area_width: u16) -> ListItem<'a> {
+    // 行の配色（フォーカスされている行はテーマのフォーカスの配色を重ねる）
+    let theme = app.theme();
+    let row_style = if (index+offset) == app.focus_file_no() {
+        theme.focus
     } else {
-        Color::Reset
+        Style::default()
     };
 
-    // 名称の最大長さ
-    // 16+2 : 日付(YYYY/MM/DD HH:MM) + スペース
-    // 8+2 : サイズ(9,999.9G) + スペース
-    // 1 : スペース
-    let name_max_len = area_width - ((16+2) + (8+2) + 3) ;
-    let file_info_spans = match file_info {
-        PathKind::FILE(file_info) => 
-            vec!(
-                Span::styled(file_info.format_file_name(name_max_len.into()), Style::default().bg(bg)), 
-                Span::styled("  ", Style::default().bg(bg)),
-                Span::styled(file_info.formatted_modified(), Style::default().bg(bg)), 
-                Span::styled("  ", Style::default().bg(bg)),
-                Span::styled(file_info.format_file_size(), Style::default().bg(bg))
-            ),
-        PathKind::DIR(file_info) => 
-            vec!(
-                Span::styled(file_info.format_file_name(name_max_len.into()), Style::default().fg(Color::Yellow).bg(bg)),
-                Span::styled("  ", Style::default().bg(bg)),
-                Span::styled(file_info.formatted_modified(), Style::default().bg(bg)),
-                Span::styled("  ", Style::default().bg(bg)),
-                Span::styled(format!("{:>8}", "<DIR>"), Style::default().fg(Color::Yellow).bg(bg))
-            ),
+    // ファイルの種別・拡張子に応じた配色と、マークされているファイル名の配色
+    let file_style = theme.file_style(file_info);
+    let name_style = if app.is_marked(&file_info.info().file_name) {
+        file_style.patch(theme.marked)
+    } else {
+        file_style
     };
+
+    // 計算済みのディレクトリは、<DIR> の代わりに合計サイズを表示する
+    let dir_size = app.dir_size(file_info);
+
+    // 描画エリアの幅に収まる列を、列の間にスペースを入れて並べる
+    let mut file_info_spans = Vec::new();
+    for (i, (column, width)) in fit_columns(columns, area_width.into()).into_iter().enumerate() {
+        if i > 0 {
+            file_info_spans.push(Span::styled("  ", row_style));
+        }
+        let style = match column {
+            Column::Name => name_style.patch(row_style),
+            Column::Size => file_style.patch(row_style),
+            _ => row_style,
+        };
+        file_info_spans.push(Span::styled(column.format(file_info, dir_size, width), style));
+    }
     ListItem::from(Line::from(file_info_spans))   
 }
 
@@ -113,7 +119,7 @@ fn get_file_info_line<'a>(app: &App, index: usize, offset: usize, file_info: &'a
 /// なし
 ///  
 fn render_file_list(list_items: Vec<ListItem>, area: &Rect, buf: &mut Buffer) {
-    let file_list = List::new(Vec::from(list_items));
+    let file_list = List::new(list_items);
     file_list.render(*area, buf);
 }
 
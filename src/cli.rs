@@ -0,0 +1,159 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{self, Component, PathBuf};
+
+use crate::sftp::SftpUrl;
+
+/// 使い方
+pub const USAGE: &str = "\
+Usage: one [OPTIONS] [DIR]
+
+Arguments:
+  [DIR]                Directory to start in (default: the current directory),
+                       or a remote directory as sftp://[user@]host[:port][/path]
+
+Options:
+  --cd-file <FILE>     Write the last directory to FILE on exit
+  --print-dir          Print the last directory to stdout on exit
+  --init <SHELL>       Print a wrapper function that changes the shell's directory on exit
+                       (bash, zsh, fish)
+  -h, --help           Print help
+  -V, --version        Print version
+";
+
+/// bash・zsh用のラッパー関数
+const POSIX_WRAPPER: &str = r#"one() {
+    local dir_file dir ret
+    dir_file="$(mktemp)" || return
+    command one --cd-file "$dir_file" "$@"
+    ret=$?
+    dir="$(cat -- "$dir_file")"
+    rm -f -- "$dir_file"
+    if [ -n "$dir" ] && [ -d "$dir" ] && [ "$dir" != "$PWD" ]; then
+        cd -- "$dir" || return
+    fi
+    return $ret
+}
+"#;
+
+/// fish用のラッパー関数
+const FISH_WRAPPER: &str = r#"function one
+    set -l dir_file (mktemp); or return
+    command one --cd-file $dir_file $argv
+    set -l ret $status
+    set -l dir (cat -- $dir_file)
+    rm -f -- $dir_file
+    if test -n "$dir"; and test -d "$dir"; and test "$dir" != "$PWD"
+        cd -- $dir
+    end
+    return $ret
+end
+"#;
+
+/// コマンドライン引数で指定された動作
+///
+/// # バリアント
+/// * `Run` - ファイルマネージャーを起動する
+/// * `Help` - 使い方を表示する
+/// * `Version` - バージョンを表示する
+/// * `Init` - シェルのラッパー関数を表示する
+pub enum Cli {
+    Run(RunOptions),
+    Help,
+    Version,
+    Init(&'static str),
+}
+
+/// ファイルマネージャーの起動オプション
+///
+/// # フィールド
+/// * `dir` - 起動時のディレクトリ（絶対パス）
+/// * `cd_file` - 終了時に最後のディレクトリを書き込むファイル
+/// * `print_dir` - 終了時に最後のディレクトリを標準出力に書き込むかどうか
+/// * `remote` - 起動時に開くSFTPのURL（`dir` は現在のディレクトリ）
+pub struct RunOptions {
+    pub dir: PathBuf,
+    pub cd_file: Option<PathBuf>,
+    pub print_dir: bool,
+    pub remote: Option<String>,
+}
+
+impl Cli {
+    /// コマンドライン引数を解析する
+    ///
+    /// # 戻り値
+    /// `Result<Cli, String>` - 指定された動作（引数が不正な場合はエラーの文言）
+    pub fn parse() -> Result<Cli, String> {
+        let mut args = env::args_os().skip(1);
+        let mut dir = None;
+        let mut cd_file = None;
+        let mut print_dir = false;
+        let mut options_done = false;
+
+        while let Some(arg) = args.next() {
+            let text = arg.to_string_lossy();
+            if options_done || !text.starts_with('-') || text == "-" {
+                if dir.replace(PathBuf::from(&arg)).is_some() {
+                    return Err(format!("unexpected argument `{}`", text));
+                }
+                continue;
+            }
+            match text.as_ref() {
+                "--" => options_done = true,
+                "-h" | "--help" => return Ok(Cli::Help),
+                "-V" | "--version" => return Ok(Cli::Version),
+                "--print-dir" => print_dir = true,
+                "--cd-file" => {
+                    let file = args.next().ok_or("`--cd-file` requires a file")?;
+                    cd_file = Some(PathBuf::from(file));
+                }
+                "--init" => {
+                    let shell = args.next().ok_or("`--init` requires a shell")?;
+                    return match shell.to_string_lossy().as_ref() {
+                        "bash" | "zsh" => Ok(Cli::Init(POSIX_WRAPPER)),
+                        "fish" => Ok(Cli::Init(FISH_WRAPPER)),
+                        other => Err(format!("unsupported shell `{}` (bash, zsh, fish)", other)),
+                    };
+                }
+                _ => match text.strip_prefix("--cd-file=") {
+                    Some(file) => cd_file = Some(PathBuf::from(file)),
+                    None => return Err(format!("unknown option `{}`", text)),
+                },
+            }
+        }
+
+        // SFTPのURLは起動後に接続し、ローカルのディレクトリは現在のディレクトリにする
+        let remote = dir.take_if(|dir| SftpUrl::is_url(&dir.to_string_lossy()))
+            .map(|url| url.to_string_lossy().into_owned());
+
+        // 親ディレクトリへの移動やヘッダーの表示のため、`.` と `..` を含まない絶対パスにしておく
+        let absolute = path::absolute(dir.unwrap_or_else(|| PathBuf::from(".")))
+            .map_err(|e| e.to_string())?;
+        let mut dir = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => { dir.pop(); }
+                component => dir.push(component),
+            }
+        }
+        Ok(Cli::Run(RunOptions { dir, cd_file, print_dir, remote }))
+    }
+}
+
+impl RunOptions {
+    /// 終了時の最後のディレクトリを、指定されたファイル・標準出力に書き込む
+    ///
+    /// # 引数
+    /// * `dir_path` - 最後のディレクトリ
+    pub fn write_last_dir(&self, dir_path: &str) -> io::Result<()> {
+        if let Some(cd_file) = &self.cd_file {
+            fs::write(cd_file, dir_path)?;
+        }
+        if self.print_dir {
+            println!("{}", dir_path);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use serde::Deserialize;
+
+use crate::dirsize::DirSizeState;
+use crate::format;
+use crate::fms::PathKind;
+
+/// ファイル一覧の表示列
+/// 設定ファイルでは括弧内の名前で指定する
+///
+/// # バリアント
+/// * `Name` - ファイル名（name、残りの幅をすべて使用する）
+/// * `Modified` - 更新日時（mtime）
+/// * `Changed` - 属性変更日時（ctime）
+/// * `Accessed` - アクセス日時（atime）
+/// * `Size` - ファイルサイズ（size、計算済みのディレクトリは合計サイズ）
+/// * `Files` - ディレクトリ以下のファイル数（files、計算済みのディレクトリのみ）
+/// * `Permissions` - パーミッション（permissions）
+/// * `Owner` - 所有者（owner）
+/// * `Group` - グループ（group）
+/// * `Inode` - iノード番号（inode）
+/// * `Links` - ハードリンク数（links）
+/// * `Extension` - 拡張子（extension）
+/// * `Type` - ファイル種別（type）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Name,
+    #[serde(rename = "mtime")]
+    Modified,
+    #[serde(rename = "ctime")]
+    Changed,
+    #[serde(rename = "atime")]
+    Accessed,
+    Size,
+    Files,
+    Permissions,
+    Owner,
+    Group,
+    Inode,
+    Links,
+    Extension,
+    Type,
+}
+
+impl Column {
+    /// 標準の表示列
+    pub const STANDARD: [Column; 3] = [Column::Name, Column::Modified, Column::Size];
+
+    /// 詳細の表示列
+    pub const DETAILED: [Column; 8] = [
+        Column::Permissions, Column::Links, Column::Owner, Column::Group,
+        Column::Name, Column::Modified, Column::Size, Column::Inode,
+    ];
+
+    /// ファイル名の最小幅
+    pub const MIN_NAME_WIDTH: usize = 12;
+
+    /// 列の既定の幅を取得する
+    /// ファイル名は可変幅のため最小幅を返す
+    /// サイズと日時は設定された表示形式に合わせた幅を返す
+    pub fn default_width(&self) -> usize {
+        match self {
+            Column::Name => Column::MIN_NAME_WIDTH,
+            Column::Modified | Column::Changed | Column::Accessed => format::date_width(),
+            Column::Size => format::size_width(),
+            Column::Files => 7,
+            Column::Permissions => 10,
+            Column::Owner | Column::Group => 8,
+            Column::Inode => 10,
+            Column::Links => 3,
+            Column::Extension => 5,
+            Column::Type => 5,
+        }
+    }
+
+    /// ファイル情報を列の幅でフォーマットする
+    ///
+    /// # 引数
+    /// * `file` - ファイル情報
+    /// * `dir_size` - ディレクトリのサイズの計算状況（計算していない場合やファイルの場合は `None`）
+    /// * `width` - 列の幅
+    ///
+    /// # 戻り値
+    /// `String` - フォーマットされた値
+    pub fn format(&self, file: &PathKind, dir_size: Option<DirSizeState>, width: usize) -> String {
+        let info = file.info();
+        let value = match self {
+            Column::Name => return info.format_file_name(width),
+            Column::Modified => info.formatted_modified(),
+            Column::Changed => info.formatted_changed(),
+            Column::Accessed => info.formatted_accessed(),
+            Column::Size => match (file, dir_size) {
+                (PathKind::FILE(info), _) => info.format_file_size(),
+                (PathKind::DIR(_), Some(DirSizeState::Measured(size))) => format::file_size(size.bytes),
+                (PathKind::DIR(_), Some(DirSizeState::Measuring)) => "...".to_string(),
+                (PathKind::DIR(_), None) => "<DIR>".to_string(),
+            },
+            Column::Files => match dir_size {
+                Some(DirSizeState::Measured(size)) => format::number(size.files),
+                _ => String::new(),
+            },
+            Column::Permissions => info.format_permissions(),
+            Column::Owner => info.owner.clone().unwrap_or_default(),
+            Column::Group => info.group.clone().unwrap_or_default(),
+            Column::Inode => info.inode.map(|inode| inode.to_string()).unwrap_or_default(),
+            Column::Links => info.links.map(|links| links.to_string()).unwrap_or_default(),
+            Column::Extension => info.extension().to_string(),
+            Column::Type => file_type(file).to_string(),
+        };
+        fit(value.trim_start(), width, self.is_right_aligned())
+    }
+
+    /// 右寄せで表示する列かどうかを取得する
+    fn is_right_aligned(&self) -> bool {
+        matches!(self, Column::Size | Column::Files | Column::Inode | Column::Links)
+    }
+}
+
+/// ファイル種別の表示名を取得する
+/// モードビットが取得できる場合はそれを優先する
+fn file_type(file: &PathKind) -> &'static str {
+    match file.info().mode.map(|mode| mode & 0o170000) {
+        Some(0o120000) => "link",
+        Some(0o010000) => "fifo",
+        Some(0o140000) => "sock",
+        Some(0o020000) => "char",
+        Some(0o060000) => "block",
+        _ => match file {
+            PathKind::FILE(_) => "file",
+            PathKind::DIR(_) => "dir",
+        },
+    }
+}
+
+/// 文字列を指定幅に収める（超える場合は切り詰める）
+fn fit(value: &str, width: usize, right_aligned: bool) -> String {
+    let value: String = value.chars().take(width).collect();
+    if right_aligned {
+        format!("{:>width$}", value)
+    } else {
+        format!("{:<width$}", value)
+    }
+}
+
+/// 列の間のスペース
+const SPACING: usize = 2;
+/// 行末の余白
+const MARGIN: usize = 3;
+
+/// すべての列を省略せずに表示するのに必要な幅を求める
+///
+/// # 引数
+/// * `columns` - 表示列と幅（ファイル名の幅は最小幅）
+///
+/// # 戻り値
+/// `usize` - 必要な幅
+pub fn required_width(columns: &[(Column, usize)]) -> usize {
+    let width: usize = columns.iter().map(|(_, width)| width + SPACING).sum();
+    width.saturating_sub(SPACING) + MARGIN
+}
+
+/// 描画エリアの幅に収まる列とその幅を求める
+/// ファイル名の幅が最小幅を下回る場合は、標準以外の列、標準の列の順に末尾から表示を省略する
+///
+/// # 引数
+/// * `columns` - 表示列と幅（ファイル名の幅は最小幅）
+/// * `area_width` - 描画エリアの幅
+///
+/// # 戻り値
+/// `Vec<(Column, usize)>` - 表示する列と幅
+pub fn fit_columns(columns: &[(Column, usize)], area_width: usize) -> Vec<(Column, usize)> {
+    let min_name_width = columns.iter()
+        .find(|(column, _)| *column == Column::Name)
+        .map_or(Column::MIN_NAME_WIDTH, |(_, width)| *width);
+
+    let mut visible: Vec<(Column, usize)> = columns.to_vec();
+    loop {
+        let fixed_width: usize = visible.iter()
+            .filter(|(column, _)| *column != Column::Name)
+            .map(|(_, width)| width + SPACING)
+            .sum();
+        let name_width = area_width.saturating_sub(fixed_width + MARGIN);
+
+        if name_width >= min_name_width || visible.len() <= 1 {
+            return visible.into_iter()
+                .map(|(column, width)| (column, if column == Column::Name { name_width } else { width }))
+                .collect();
+        }
+
+        // ファイル名以外の最後の列を省略する
+        let removable = visible.iter().rposition(|(column, _)| !Column::STANDARD.contains(column))
+            .or_else(|| visible.iter().rposition(|(column, _)| *column != Column::Name));
+        match removable {
+            Some(index) => { visible.remove(index); }
+            None => return vec![(Column::Name, name_width)],
+        }
+    }
+}
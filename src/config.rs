@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::column::Column;
+use crate::i18n::Language;
+use crate::keymap::Action;
+use crate::opener::OpenRule;
+use crate::theme::StyleSpec;
+
+/// 設定ファイルの内容
+/// 項目を省略した場合は既定値を使用する
+///
+/// # フィールド
+/// * `layout` - ファイル一覧のレイアウト設定
+/// * `keys` - キー割り当て（キーの組み合わせと操作）
+/// * `theme` - 配色の設定
+/// * `language` - 表示言語（auto、en、ja）
+/// * `format` - ファイルサイズ・日時の表示形式
+/// * `open` - ファイルを開くプログラムの規則（`[[open]]`、省略時は既定の規則）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub layout: LayoutConfig,
+    pub keys: HashMap<String, Action>,
+    pub theme: ThemeConfig,
+    pub language: Language,
+    pub format: FormatConfig,
+    pub open: Vec<OpenRule>,
+}
+
+impl Config {
+    /// 設定ファイルを読み込む
+    /// 設定ファイルが存在しない場合は既定値を返します
+    ///
+    /// # 戻り値
+    /// `io::Result<Config>` - 設定内容（設定ファイルの書式が不正な場合はエラー）
+    pub fn load() -> io::Result<Config> {
+        let Some(path) = config_path() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// ファイルを開くプログラムの規則を取得する
+    /// 設定ファイルで指定されていない場合は既定の規則を返します
+    pub fn open_rules(&self) -> Vec<OpenRule> {
+        if self.open.is_empty() {
+            OpenRule::defaults()
+        } else {
+            self.open.clone()
+        }
+    }
+}
+
+/// 設定ファイルのパスを取得する
+/// 環境変数 `ONE_CONFIG` が指定されている場合はそのパスを使用します
+/// それ以外は `$XDG_CONFIG_HOME/one/config.toml`、`~/.config/one/config.toml`、
+/// Windowsでは `%APPDATA%\one\config.toml` の順に決定します
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("ONE_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// テーマファイルのパスを取得する
+/// 設定ディレクトリの `themes/<名前>.toml` です
+pub fn theme_path(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("themes").join(format!("{}.toml", name)))
+}
+
+/// コマンド履歴のファイルのパスを取得する
+/// 設定ディレクトリの `history` です
+pub fn history_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("history"))
+}
+
+/// 設定ディレクトリのパスを取得する
+fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("one"))
+}
+
+/// レイアウトの種類
+///
+/// # バリアント
+/// * `Compact` - 標準の列のみを表示する
+/// * `Detailed` - パーミッションや所有者などの列も表示する
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutMode {
+    #[default]
+    Compact,
+    Detailed,
+}
+
+/// ファイル一覧の列数
+/// 設定ファイルでは `"auto"` または 1 以上の数値で指定する
+///
+/// # バリアント
+/// * `Auto` - 画面の幅とレイアウトに合わせて列数を決める
+/// * `Fixed` - 指定した列数で表示する
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListColumns {
+    #[default]
+    Auto,
+    Fixed(usize),
+}
+
+impl<'de> Deserialize<'de> for ListColumns {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Count(usize),
+            Name(String),
+        }
+        match Value::deserialize(deserializer)? {
+            Value::Count(count) if count > 0 => Ok(ListColumns::Fixed(count)),
+            Value::Name(name) if name == "auto" => Ok(ListColumns::Auto),
+            _ => Err(serde::de::Error::custom("list_columns must be \"auto\" or a number greater than 0")),
+        }
+    }
+}
+
+impl ListColumns {
+    /// 描画エリアの幅に応じた列数を求める
+    ///
+    /// # 引数
+    /// * `area_width` - 描画エリアの幅
+    /// * `required_width` - 1列の表示に必要な幅
+    ///
+    /// # 戻り値
+    /// `usize` - 列数（1以上、各列にファイル名の最小幅が入る数まで）
+    pub fn count(&self, area_width: usize, required_width: usize) -> usize {
+        let max_count = area_width / Column::MIN_NAME_WIDTH;
+        match self {
+            ListColumns::Auto => (area_width / required_width.max(1)).min(max_count).max(1),
+            ListColumns::Fixed(count) => (*count).min(max_count).max(1),
+        }
+    }
+}
+
+/// ファイル一覧のスクロール方法
+///
+/// # バリアント
+/// * `Page` - 1画面単位でページを切り替える
+/// * `Smooth` - フォーカスの前後に余白を残しながら1行ずつスクロールする
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollMode {
+    #[default]
+    Page,
+    Smooth,
+}
+
+/// ディレクトリのサイズの計算方法
+///
+/// # バリアント
+/// * `Manual` - 操作したときだけ計算する
+/// * `Auto` - ディレクトリを開くたびに、その中のディレクトリをバックグラウンドで計算する
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirSizeMode {
+    #[default]
+    Manual,
+    Auto,
+}
+
+/// ファイル一覧のレイアウト設定
+///
+/// ```toml
+/// [layout]
+/// mode = "compact"
+/// list_columns = "auto"
+/// scroll = "smooth"
+/// scroll_margin = 3
+/// dir_sizes = "auto"
+/// compact = ["name", "mtime", "size"]
+/// detailed = ["permissions", "owner", "group", "name", "mtime", "size"]
+///
+/// [layout.widths]
+/// name = 20
+/// owner = 12
+/// ```
+///
+/// # フィールド
+/// * `mode` - 起動時のレイアウト
+/// * `list_columns` - 起動時のファイル一覧の列数
+/// * `scroll` - 起動時のスクロール方法
+/// * `scroll_margin` - 1行ずつスクロールする場合に、フォーカスの前後に残す行数
+/// * `dir_sizes` - ディレクトリのサイズの計算方法
+/// * `compact` - 標準レイアウトの表示列（左から順に表示）
+/// * `detailed` - 詳細レイアウトの表示列（左から順に表示）
+/// * `widths` - 列ごとの幅（ファイル名は最小幅）
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub mode: LayoutMode,
+    pub list_columns: ListColumns,
+    pub scroll: ScrollMode,
+    pub scroll_margin: usize,
+    pub dir_sizes: DirSizeMode,
+    pub compact: Vec<Column>,
+    pub detailed: Vec<Column>,
+    pub widths: HashMap<Column, usize>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            mode: LayoutMode::Compact,
+            list_columns: ListColumns::Auto,
+            scroll: ScrollMode::Page,
+            scroll_margin: 3,
+            dir_sizes: DirSizeMode::Manual,
+            compact: Column::STANDARD.to_vec(),
+            detailed: Column::DETAILED.to_vec(),
+            widths: HashMap::new(),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// 指定したレイアウトの表示列と幅を取得する
+    ///
+    /// # 引数
+    /// * `mode` - レイアウトの種類
+    ///
+    /// # 戻り値
+    /// `Vec<(Column, usize)>` - 表示列と幅
+    pub fn columns(&self, mode: LayoutMode) -> Vec<(Column, usize)> {
+        let columns = match mode {
+            LayoutMode::Compact => &self.compact,
+            LayoutMode::Detailed => &self.detailed,
+        };
+        columns.iter()
+            .map(|column| (*column, self.widths.get(column).copied().unwrap_or_else(|| column.default_width())))
+            .collect()
+    }
+}
+
+/// 配色の設定
+///
+/// ```toml
+/// [theme]
+/// name = "light"
+/// ls_colors = true
+///
+/// [theme.styles]
+/// focus = { fg = "black", bg = "lightyellow" }
+/// ```
+///
+/// # フィールド
+/// * `name` - テーマの名前（dark、light、high-contrast、またはテーマファイルの名前）
+/// * `ls_colors` - 環境変数 `LS_COLORS` でファイルを色分けするかどうか
+/// * `styles` - テーマの配色に上書きする配色
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: String,
+    pub ls_colors: bool,
+    pub styles: HashMap<String, StyleSpec>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            name: "dark".to_string(),
+            ls_colors: true,
+            styles: HashMap::new(),
+        }
+    }
+}
+
+/// ファイルサイズの単位
+///
+/// # バリアント
+/// * `Iec` - 1024単位（K、M、G、T）
+/// * `Si` - 1000単位（k、M、G、T）
+/// * `Bytes` - 単位を付けずにバイト数をそのまま表示する
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeFormat {
+    #[default]
+    Iec,
+    Si,
+    Bytes,
+}
+
+/// 日時の表示形式
+/// 設定ファイルでは `"default"`、`"iso"`、`"relative"`、またはstrftime形式の書式で指定する
+///
+/// # バリアント
+/// * `Default` - YYYY/MM/DD HH:MM
+/// * `Iso` - ISO 8601（YYYY-MM-DDTHH:MM:SS）
+/// * `Relative` - 現在からの経過時間（3時間前など）
+/// * `Custom` - strftime形式の書式
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum DateFormat {
+    #[default]
+    Default,
+    Iso,
+    Relative,
+    Custom(String),
+}
+
+impl From<String> for DateFormat {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "default" => DateFormat::Default,
+            "iso" => DateFormat::Iso,
+            "relative" => DateFormat::Relative,
+            _ => DateFormat::Custom(value),
+        }
+    }
+}
+
+/// ファイルサイズ・日時の表示形式の設定
+///
+/// ```toml
+/// [format]
+/// size = "si"
+/// locale = "de"
+/// date = "relative"
+/// ```
+///
+/// # フィールド
+/// * `size` - ファイルサイズの単位
+/// * `locale` - 桁区切り・小数点の記号に使うロケール（num-formatのロケール名）
+/// * `date` - 日時の表示形式
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    pub size: SizeFormat,
+    pub locale: String,
+    pub date: DateFormat,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            size: SizeFormat::Iec,
+            locale: "en".to_string(),
+            date: DateFormat::Default,
+        }
+    }
+}
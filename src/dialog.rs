@@ -0,0 +1,858 @@
+use std::path::PathBuf;
+
+use ratatui::{
+    buffer::Buffer, crossterm::event::{KeyCode, KeyEvent}, layout::{Constraint, Flex, Layout, Rect},
+    style::{Modifier, Style}, symbols::border, text::{Line, Span}, widgets::{Block, Clear, Paragraph, Widget}
+};
+
+use crate::{archive::ArchiveFormat, fms::PathKind, format, i18n::{tr, Msg}, operation::{ConflictAction, OperationKind}};
+
+/// ダイアログ列挙型
+///
+/// # バリアント
+/// * `Destination` - コピー・移動先の入力
+/// * `Extract` - アーカイブの展開先の入力
+/// * `Conflict` - コピー・移動先の競合の解決方法の選択
+/// * `Attribute` - パーミッション・所有者の変更
+/// * `OpenWith` - ファイルを開くプログラムの選択
+/// * `Exec` - シェルのコマンドラインの入力
+/// * `Pack` - 作成するアーカイブの名前と形式の入力
+/// * `Goto` - 移動先のディレクトリ（またはSFTPのURL）の入力
+/// * `Password` - SFTPの接続先のパスワードの入力
+/// * `HostKey` - 未知のSFTPの接続先のホスト鍵を受け入れるかの選択
+/// * `Delete` - 使用量ビューで選択したファイルを削除するかの選択（削除するパス）
+/// * `Message` - メッセージの表示
+pub enum Dialog {
+    Destination(OperationKind, InputDialog),
+    Extract(InputDialog),
+    Conflict(Box<ConflictDialog>),
+    Attribute(AttributeDialog),
+    OpenWith(PathBuf, MenuDialog),
+    Exec(ExecDialog),
+    Pack(PackDialog),
+    Goto(InputDialog),
+    Password(InputDialog),
+    HostKey(MenuDialog),
+    Delete(PathBuf, MenuDialog),
+    Message(MessageDialog),
+}
+
+impl Dialog {
+    /// フッターに表示する、ダイアログで使えるキーと操作の名前を取得する
+    pub fn function_keys(&self) -> Vec<(KeyCode, String)> {
+        match self {
+            Dialog::Destination(..) | Dialog::Extract(_) | Dialog::Goto(_) | Dialog::Password(_) => vec![(KeyCode::Enter, tr(Msg::Confirm)), (KeyCode::Esc, tr(Msg::Cancel))],
+            Dialog::Conflict(_) => {
+                let mut keys: Vec<(KeyCode, String)> = ConflictAction::ALL.iter()
+                    .map(|action| (KeyCode::Char(action.key()), action.label()))
+                    .collect();
+                keys.extend([
+                    (KeyCode::Char('A'), tr(Msg::ApplyToAll)), (KeyCode::Enter, tr(Msg::Confirm)), (KeyCode::Esc, tr(Msg::Abort)),
+                ]);
+                keys
+            }
+            Dialog::Attribute(_) => vec![
+                (KeyCode::F(2), tr(Msg::Recursive)), (KeyCode::Tab, tr(Msg::NextField)),
+                (KeyCode::Enter, tr(Msg::Confirm)), (KeyCode::Esc, tr(Msg::Cancel)),
+            ],
+            Dialog::OpenWith(..) | Dialog::HostKey(_) | Dialog::Delete(..) => vec![(KeyCode::Enter, tr(Msg::Confirm)), (KeyCode::Esc, tr(Msg::Cancel))],
+            Dialog::Exec(_) => vec![
+                (KeyCode::F(2), tr(Msg::Output)), (KeyCode::Up, tr(Msg::History)),
+                (KeyCode::Enter, tr(Msg::Run)), (KeyCode::Esc, tr(Msg::Cancel)),
+            ],
+            Dialog::Pack(_) => vec![(KeyCode::Tab, tr(Msg::Format)), (KeyCode::Enter, tr(Msg::Confirm)), (KeyCode::Esc, tr(Msg::Cancel))],
+            Dialog::Message(_) => vec![(KeyCode::Enter, tr(Msg::Close))],
+        }
+    }
+}
+
+impl Dialog {
+    /// 配色を指定して描画するウィジェットを取得する
+    ///
+    /// # 引数
+    /// * `style` - ダイアログの配色（テーマから取得する）
+    pub fn styled(&self, style: DialogStyle) -> StyledDialog<'_> {
+        StyledDialog { dialog: self, style }
+    }
+}
+
+/// ダイアログの配色
+///
+/// # フィールド
+/// * `border` - 枠線
+/// * `label` - 項目名
+/// * `hint` - 操作の説明
+/// * `selected` - 選択している項目
+/// * `newer` - 競合ダイアログで更新日時が新しい方の印
+#[derive(Clone, Copy, Debug)]
+pub struct DialogStyle {
+    pub border: Style,
+    pub label: Style,
+    pub hint: Style,
+    pub selected: Style,
+    pub newer: Style,
+}
+
+/// 配色を指定したダイアログのウィジェット
+///
+/// # フィールド
+/// * `dialog` - 描画するダイアログ
+/// * `style` - ダイアログの配色
+pub struct StyledDialog<'a> {
+    dialog: &'a Dialog,
+    style: DialogStyle,
+}
+
+impl Widget for StyledDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = &self.style;
+        match self.dialog {
+            Dialog::Destination(_, dialog) | Dialog::Extract(dialog) | Dialog::Goto(dialog) | Dialog::Password(dialog) => {
+                dialog.render(area, buf, style)
+            }
+            Dialog::Conflict(dialog) => dialog.render(area, buf, style),
+            Dialog::Attribute(dialog) => dialog.render(area, buf, style),
+            Dialog::OpenWith(_, dialog) | Dialog::HostKey(dialog) | Dialog::Delete(_, dialog) => dialog.render(area, buf, style),
+            Dialog::Exec(dialog) => dialog.render(area, buf, style),
+            Dialog::Pack(dialog) => dialog.render(area, buf, style),
+            Dialog::Message(dialog) => dialog.render(area, buf, style),
+        }
+    }
+}
+
+/// 画面中央にダイアログの描画エリアを確保する
+///
+/// # 引数
+/// * `area` - 画面全体の描画エリア
+/// * `width` - ダイアログの幅
+/// * `height` - ダイアログの高さ
+///
+/// # 戻り値
+/// `Rect` - ダイアログの描画エリア
+fn centered_area(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width.min(area.width))])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height.min(area.height))])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+/// ダイアログの枠を描画して、内側の描画エリアを返す
+fn render_frame(title: &str, area: Rect, buf: &mut Buffer, style: &DialogStyle) -> Rect {
+    Clear.render(area, buf);
+    let block = Block::bordered()
+        .title(Line::from(format!(" {} ", title)).centered())
+        .border_set(border::ROUNDED)
+        .border_style(style.border);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    inner
+}
+
+/// 入力ダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Submit` - 入力が確定された
+/// * `Cancel` - 入力がキャンセルされた
+/// * `Pending` - 入力中
+pub enum InputResult {
+    Submit(String),
+    Cancel,
+    Pending,
+}
+
+/// 1行の文字列を入力するダイアログ
+///
+/// # フィールド
+/// * `title` - ダイアログのタイトル
+/// * `value` - 入力中の文字列
+/// * `masked` - 入力中の文字列を伏せて表示するかどうか（パスワードの入力）
+pub struct InputDialog {
+    title: String,
+    value: String,
+    masked: bool,
+}
+
+impl InputDialog {
+    /// 新しいInputDialogインスタンスを返します。
+    pub fn new(title: &str, value: &str) -> InputDialog {
+        InputDialog {
+            title: title.to_string(),
+            value: value.to_string(),
+            masked: false,
+        }
+    }
+
+    /// 入力中の文字列を伏せて表示するようにする
+    pub fn masked(mut self) -> InputDialog {
+        self.masked = true;
+        self
+    }
+
+    /// キー入力を処理する
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputResult {
+        match key.code {
+            KeyCode::Enter => InputResult::Submit(self.value.clone()),
+            KeyCode::Esc => InputResult::Cancel,
+            KeyCode::Backspace => {
+                self.value.pop();
+                InputResult::Pending
+            }
+            KeyCode::Char(c) => {
+                self.value.push(c);
+                InputResult::Pending
+            }
+            _ => InputResult::Pending,
+        }
+    }
+}
+
+impl InputDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let area = centered_area(area, area.width.saturating_sub(10), 4);
+        let inner = render_frame(&self.title, area, buf, style);
+
+        // 入力欄に収まらない場合は末尾を表示する
+        let max_chars = inner.width.saturating_sub(1) as usize;
+        let skip = self.value.chars().count().saturating_sub(max_chars);
+        let visible: String = match self.masked {
+            true => "*".repeat(self.value.chars().count() - skip),
+            false => self.value.chars().skip(skip).collect(),
+        };
+
+        let lines = vec![
+            Line::from(vec![
+                Span::raw(visible),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            Line::from(Span::styled(tr(Msg::InputHint), style.hint)),
+        ];
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// 競合ダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Resolve` - 解決方法と「すべてに適用」するかどうかが選択された
+/// * `Cancel` - 残りの操作がキャンセルされた
+/// * `Pending` - 選択中
+pub enum ConflictResult {
+    Resolve(ConflictAction, bool),
+    Cancel,
+    Pending,
+}
+
+/// コピー・移動先に同名のファイルが存在する場合に、解決方法を選択するダイアログ
+///
+/// # フィールド
+/// * `kind` - 操作種別
+/// * `src` - コピー元のファイル情報
+/// * `dest` - コピー先のファイル情報
+/// * `selected` - 選択中の解決方法のインデックス
+/// * `apply_to_all` - 以降の競合にも同じ解決方法を適用するかどうか
+pub struct ConflictDialog {
+    kind: OperationKind,
+    src: PathKind,
+    dest: PathKind,
+    selected: usize,
+    apply_to_all: bool,
+}
+
+impl ConflictDialog {
+    /// 新しいConflictDialogインスタンスを返します。
+    pub fn new(kind: OperationKind, src: PathKind, dest: PathKind) -> ConflictDialog {
+        ConflictDialog {
+            kind,
+            src,
+            dest,
+            selected: 0,
+            apply_to_all: false,
+        }
+    }
+
+    /// キー入力を処理する
+    pub fn handle_key(&mut self, key: KeyEvent) -> ConflictResult {
+        let count = ConflictAction::ALL.len();
+        match key.code {
+            KeyCode::Left => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Right | KeyCode::Tab => self.selected = (self.selected + 1) % count,
+            KeyCode::Char(' ') => self.apply_to_all = !self.apply_to_all,
+            KeyCode::Enter => return ConflictResult::Resolve(ConflictAction::ALL[self.selected], self.apply_to_all),
+            KeyCode::Esc => return ConflictResult::Cancel,
+            KeyCode::Char(c) => {
+                let c = c.to_ascii_uppercase();
+                if c == 'A' {
+                    self.apply_to_all = !self.apply_to_all;
+                } else if let Some(action) = ConflictAction::ALL.iter().find(|action| action.key() == c) {
+                    return ConflictResult::Resolve(*action, self.apply_to_all);
+                }
+            }
+            _ => {}
+        }
+        ConflictResult::Pending
+    }
+
+    /// コピー元・コピー先のファイル情報の行を取得する
+    fn file_line(label: String, file: &PathKind, other: &PathKind, name_width: usize, style: &DialogStyle) -> Line<'static> {
+        let info = file.info();
+        let size = match file {
+            PathKind::FILE(info) => info.format_file_size(),
+            PathKind::DIR(_) => format!("{:>width$}", "<DIR>", width = format::size_width()),
+        };
+        // 更新日時が新しい方に印を付ける
+        let newer = match (info.last_modified, other.info().last_modified) {
+            (Some(a), Some(b)) if a > b => Span::styled(tr(Msg::Newer), style.newer),
+            _ => Span::raw(""),
+        };
+        Line::from(vec![
+            Span::styled(label, style.label),
+            Span::raw(info.format_file_name(name_width)),
+            Span::raw("  "),
+            Span::raw(size),
+            Span::raw("  "),
+            Span::raw(info.formatted_modified()),
+            newer,
+        ])
+    }
+}
+
+impl ConflictDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let area = centered_area(area, 76, 9);
+        let title = tr(Msg::ConflictTitle(self.kind));
+        let inner = render_frame(&title, area, buf, style);
+
+        // ラベル(10) + サイズ + 日付 + 印(9)
+        let fixed_width = 10 + (format::size_width() + 2) + (format::date_width() + 2) + 9;
+        let name_width = (inner.width as usize).saturating_sub(fixed_width).max(8);
+
+        let mut actions: Vec<Span> = Vec::new();
+        for (i, action) in ConflictAction::ALL.iter().enumerate() {
+            let item_style = if i == self.selected {
+                style.selected
+            } else {
+                Style::default()
+            };
+            actions.push(Span::styled(format!("[{}]{}", action.key(), action.label()), item_style));
+            actions.push(Span::raw(" "));
+        }
+
+        let check = if self.apply_to_all { "[x]" } else { "[ ]" };
+        let lines = vec![
+            ConflictDialog::file_line(tr(Msg::SourceLabel(self.kind)), &self.src, &self.dest, name_width, style),
+            ConflictDialog::file_line(tr(Msg::DestinationLabel(self.kind)), &self.dest, &self.src, name_width, style),
+            Line::raw(""),
+            Line::from(actions),
+            Line::from(format!("{} {}", check, tr(Msg::ApplyToAllOption))),
+            Line::raw(""),
+            Line::from(Span::styled(tr(Msg::ConflictHint), style.hint)),
+        ];
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// 属性ダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Submit` - 変更が確定された（適用しない項目は `None`）
+/// * `Cancel` - 変更がキャンセルされた
+/// * `Pending` - 入力中
+pub enum AttributeResult {
+    Submit { mode: Option<String>, owner: Option<String>, recursive: bool },
+    Cancel,
+    Pending,
+}
+
+/// パーミッションと所有者を変更するダイアログ
+///
+/// # フィールド
+/// * `target` - 変更対象の表示名
+/// * `current_mode` - 現在のパーミッション（rwxr-xr-x 形式）
+/// * `mode` - パーミッションの入力値（8進数または記号形式）
+/// * `initial_mode` - パーミッションの初期値
+/// * `owner` - 所有者の入力値（所有者:グループ）
+/// * `initial_owner` - 所有者の初期値
+/// * `recursive` - ディレクトリの中身も変更するかどうか
+/// * `multiple` - 変更対象が複数のファイルかどうか
+/// * `focus` - 入力中の項目（0: パーミッション, 1: 所有者）
+pub struct AttributeDialog {
+    target: String,
+    current_mode: String,
+    mode: String,
+    initial_mode: String,
+    owner: String,
+    initial_owner: String,
+    recursive: bool,
+    multiple: bool,
+    focus: usize,
+}
+
+impl AttributeDialog {
+    /// 新しいAttributeDialogインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `target` - 変更対象の表示名
+    /// * `file` - 初期値として表示するファイル情報
+    /// * `multiple` - 変更対象が複数のファイルかどうか
+    pub fn new(target: &str, file: &PathKind, multiple: bool) -> AttributeDialog {
+        let info = file.info();
+        let mode = info.mode.map(|mode| format!("{:o}", mode & 0o7777)).unwrap_or_default();
+        let owner = match (&info.owner, &info.group) {
+            (Some(owner), Some(group)) => format!("{}:{}", owner, group),
+            _ => String::new(),
+        };
+        AttributeDialog {
+            target: target.to_string(),
+            current_mode: info.format_permissions(),
+            initial_mode: mode.clone(),
+            mode,
+            initial_owner: owner.clone(),
+            owner,
+            recursive: false,
+            multiple,
+            focus: 0,
+        }
+    }
+
+    /// キー入力を処理する
+    /// 中身も変更する場合や複数のファイルを変更する場合は、初期値のままでもすべてのファイルに適用します
+    pub fn handle_key(&mut self, key: KeyEvent) -> AttributeResult {
+        let field = if self.focus == 0 { &mut self.mode } else { &mut self.owner };
+        match key.code {
+            KeyCode::Enter => {
+                let apply_all = self.recursive || self.multiple;
+                let changed = |value: &String, initial: &String| {
+                    (!value.is_empty() && (apply_all || value != initial)).then(|| value.clone())
+                };
+                return AttributeResult::Submit {
+                    mode: changed(&self.mode, &self.initial_mode),
+                    owner: changed(&self.owner, &self.initial_owner),
+                    recursive: self.recursive,
+                };
+            }
+            KeyCode::Esc => return AttributeResult::Cancel,
+            KeyCode::Tab | KeyCode::Down | KeyCode::Up | KeyCode::BackTab => self.focus = 1 - self.focus,
+            KeyCode::F(2) => self.recursive = !self.recursive,
+            KeyCode::Backspace => { field.pop(); }
+            KeyCode::Char(c) => field.push(c),
+            _ => {}
+        }
+        AttributeResult::Pending
+    }
+}
+
+impl AttributeDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let area = centered_area(area, 60, 10);
+        let inner = render_frame(&tr(Msg::AttributeTitle), area, buf, style);
+
+        let field = |label: String, value: &str, focused: bool| {
+            let mut spans = vec![
+                Span::styled(label, style.label),
+                Span::raw(value.to_string()),
+            ];
+            if focused {
+                spans.push(Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)));
+            }
+            Line::from(spans)
+        };
+        let check = if self.recursive { "[x]" } else { "[ ]" };
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(tr(Msg::TargetLabel), style.label),
+                Span::raw(self.target.as_str()),
+            ]),
+            Line::from(vec![
+                Span::styled(tr(Msg::CurrentLabel), style.label),
+                Span::raw(format!("{} {}", self.current_mode, self.initial_owner)),
+            ]),
+            Line::raw(""),
+            field(tr(Msg::ModeLabel), &self.mode, self.focus == 0),
+            field(tr(Msg::OwnerLabel), &self.owner, self.focus == 1),
+            Line::from(format!("{} {}", check, tr(Msg::RecursiveOption))),
+            Line::from(Span::styled(tr(Msg::AttributeHint), style.hint)),
+            Line::from(Span::styled(tr(Msg::AttributeExample), style.hint)),
+        ];
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// メニューダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Select` - 項目が選択された（項目のインデックス）
+/// * `Cancel` - 選択がキャンセルされた
+/// * `Pending` - 選択中
+pub enum MenuResult {
+    Select(usize),
+    Cancel,
+    Pending,
+}
+
+/// 項目を1つ選択するダイアログ
+///
+/// # フィールド
+/// * `title` - ダイアログのタイトル
+/// * `items` - 選択肢
+/// * `selected` - 選択中の項目のインデックス
+pub struct MenuDialog {
+    title: String,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl MenuDialog {
+    /// 新しいMenuDialogインスタンスを返します。
+    pub fn new(title: &str, items: Vec<String>) -> MenuDialog {
+        MenuDialog {
+            title: title.to_string(),
+            items,
+            selected: 0,
+        }
+    }
+
+    /// キー入力を処理する
+    /// 1～9の数字キーでは、その番号の項目を直接選択します
+    pub fn handle_key(&mut self, key: KeyEvent) -> MenuResult {
+        let count = self.items.len().max(1);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.selected = (self.selected + 1) % count,
+            KeyCode::Enter if !self.items.is_empty() => return MenuResult::Select(self.selected),
+            KeyCode::Esc => return MenuResult::Cancel,
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < self.items.len() {
+                    return MenuResult::Select(index);
+                }
+            }
+            _ => {}
+        }
+        MenuResult::Pending
+    }
+}
+
+impl MenuDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let height = (self.items.len() as u16).saturating_add(4);
+        let area = centered_area(area, area.width.saturating_sub(10), height);
+        let inner = render_frame(&self.title, area, buf, style);
+
+        let mut lines: Vec<Line> = self.items.iter().enumerate()
+            .map(|(i, item)| {
+                let item_style = if i == self.selected {
+                    style.selected
+                } else {
+                    Style::default()
+                };
+                let number = if i < 9 { format!("{} ", i + 1) } else { "  ".to_string() };
+                Line::from(vec![
+                    Span::styled(number, style.label),
+                    Span::styled(item.as_str(), item_style),
+                ])
+            })
+            .collect();
+        lines.push(Line::raw(""));
+        lines.push(Line::from(Span::styled(tr(Msg::MenuHint), style.hint)));
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// コマンドラインダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Submit` - コマンドラインと、出力をビューアに表示するかどうかが確定された
+/// * `Cancel` - 入力がキャンセルされた
+/// * `Pending` - 入力中
+pub enum ExecResult {
+    Submit { command_line: String, capture: bool },
+    Cancel,
+    Pending,
+}
+
+/// シェルのコマンドラインを入力するダイアログ
+/// 上下キーで履歴のコマンドラインを呼び出せる
+///
+/// # フィールド
+/// * `value` - 入力中のコマンドライン
+/// * `history` - 履歴（古い順）
+/// * `history_index` - 呼び出し中の履歴のインデックス（`None` は新しい入力）
+/// * `draft` - 履歴を呼び出す前に入力していたコマンドライン
+/// * `capture` - 出力をビューアに表示するかどうか（しない場合は端末を一時停止して実行する）
+pub struct ExecDialog {
+    value: String,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    draft: String,
+    capture: bool,
+}
+
+impl ExecDialog {
+    /// 新しいExecDialogインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `history` - 履歴（古い順）
+    pub fn new(history: Vec<String>) -> ExecDialog {
+        ExecDialog {
+            value: String::new(),
+            history,
+            history_index: None,
+            draft: String::new(),
+            capture: false,
+        }
+    }
+
+    /// キー入力を処理する
+    pub fn handle_key(&mut self, key: KeyEvent) -> ExecResult {
+        match key.code {
+            KeyCode::Enter if !self.value.trim().is_empty() => {
+                return ExecResult::Submit { command_line: self.value.clone(), capture: self.capture };
+            }
+            KeyCode::Esc => return ExecResult::Cancel,
+            KeyCode::F(2) => self.capture = !self.capture,
+            KeyCode::Up => {
+                let index = match self.history_index {
+                    Some(index) => index.saturating_sub(1),
+                    None if self.history.is_empty() => return ExecResult::Pending,
+                    None => {
+                        self.draft = self.value.clone();
+                        self.history.len() - 1
+                    }
+                };
+                self.history_index = Some(index);
+                self.value = self.history[index].clone();
+            }
+            KeyCode::Down => match self.history_index {
+                Some(index) if index + 1 < self.history.len() => {
+                    self.history_index = Some(index + 1);
+                    self.value = self.history[index + 1].clone();
+                }
+                Some(_) => {
+                    self.history_index = None;
+                    self.value = std::mem::take(&mut self.draft);
+                }
+                None => {}
+            },
+            KeyCode::Backspace => { self.value.pop(); }
+            KeyCode::Char(c) => self.value.push(c),
+            _ => {}
+        }
+        ExecResult::Pending
+    }
+}
+
+impl ExecDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let area = centered_area(area, area.width.saturating_sub(10), 7);
+        let inner = render_frame(&tr(Msg::ExecTitle), area, buf, style);
+
+        // 入力欄に収まらない場合は末尾を表示する
+        let max_chars = inner.width.saturating_sub(3) as usize;
+        let skip = self.value.chars().count().saturating_sub(max_chars);
+        let visible: String = self.value.chars().skip(skip).collect();
+        let check = if self.capture { "[x]" } else { "[ ]" };
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("$ ", style.label),
+                Span::raw(visible),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            Line::from(format!("{} {}", check, tr(Msg::CaptureOption))),
+            Line::from(Span::styled(tr(Msg::PlaceholderHint), style.hint)),
+            Line::raw(""),
+            Line::from(Span::styled(tr(Msg::ExecHint), style.hint)),
+        ];
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// 圧縮ダイアログのキー操作の結果
+///
+/// # バリアント
+/// * `Submit` - アーカイブのファイル名（拡張子を含む）と形式が確定された
+/// * `Cancel` - 入力がキャンセルされた
+/// * `Pending` - 入力中
+pub enum PackResult {
+    Submit { file_name: String, format: ArchiveFormat },
+    Cancel,
+    Pending,
+}
+
+/// 作成するアーカイブの名前と形式を入力するダイアログ
+/// 名前には選択中の形式の拡張子を付け加える
+///
+/// # フィールド
+/// * `target` - 圧縮対象の表示名
+/// * `name` - アーカイブの名前の入力値（拡張子を除く）
+/// * `format` - 選択中の形式の `ArchiveFormat::PACK` のインデックス
+pub struct PackDialog {
+    target: String,
+    name: String,
+    format: usize,
+}
+
+impl PackDialog {
+    /// 新しいPackDialogインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `target` - 圧縮対象の表示名
+    /// * `name` - アーカイブの名前の初期値（拡張子を除く）
+    pub fn new(target: &str, name: &str) -> PackDialog {
+        PackDialog {
+            target: target.to_string(),
+            name: name.to_string(),
+            format: 0,
+        }
+    }
+
+    /// 拡張子を付け加えたアーカイブのファイル名を取得する
+    fn file_name(&self) -> String {
+        format!("{}{}", self.name, ArchiveFormat::PACK[self.format].extension())
+    }
+
+    /// キー入力を処理する
+    pub fn handle_key(&mut self, key: KeyEvent) -> PackResult {
+        let count = ArchiveFormat::PACK.len();
+        match key.code {
+            KeyCode::Enter if !self.name.trim().is_empty() => {
+                return PackResult::Submit { file_name: self.file_name(), format: ArchiveFormat::PACK[self.format] };
+            }
+            KeyCode::Esc => return PackResult::Cancel,
+            KeyCode::Tab | KeyCode::Right => self.format = (self.format + 1) % count,
+            KeyCode::BackTab | KeyCode::Left => self.format = (self.format + count - 1) % count,
+            KeyCode::Backspace => { self.name.pop(); }
+            KeyCode::Char(c) => self.name.push(c),
+            _ => {}
+        }
+        PackResult::Pending
+    }
+}
+
+impl PackDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let area = centered_area(area, area.width.saturating_sub(10), 8);
+        let inner = render_frame(&tr(Msg::PackTitle), area, buf, style);
+
+        // 入力欄に収まらない場合は末尾を表示する
+        let label_width = 10;
+        let max_chars = (inner.width as usize).saturating_sub(label_width + 1);
+        let skip = self.name.chars().count().saturating_sub(max_chars);
+        let visible: String = self.name.chars().skip(skip).collect();
+
+        let mut formats: Vec<Span> = Vec::new();
+        for (i, format) in ArchiveFormat::PACK.iter().enumerate() {
+            let item_style = if i == self.format {
+                style.selected
+            } else {
+                Style::default()
+            };
+            formats.push(Span::styled(format.extension(), item_style));
+            formats.push(Span::raw(" "));
+        }
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(tr(Msg::TargetLabel), style.label),
+                Span::raw(self.target.as_str()),
+            ]),
+            Line::raw(""),
+            Line::from(vec![
+                Span::styled(tr(Msg::NameLabel), style.label),
+                Span::raw(visible),
+                Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+            ]),
+            Line::from([vec![Span::styled(tr(Msg::FormatLabel), style.label)], formats].concat()),
+            Line::from(Span::styled(format!("→ {}", self.file_name()), style.hint)),
+            Line::from(Span::styled(tr(Msg::PackHint), style.hint)),
+        ];
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+/// メッセージを表示するダイアログ
+/// 何かキーを押すと閉じる
+///
+/// # フィールド
+/// * `title` - ダイアログのタイトル
+/// * `lines` - 表示するメッセージ
+pub struct MessageDialog {
+    title: String,
+    lines: Vec<String>,
+}
+
+impl MessageDialog {
+    /// 新しいMessageDialogインスタンスを返します。
+    pub fn new(title: &str, lines: Vec<String>) -> MessageDialog {
+        MessageDialog {
+            title: title.to_string(),
+            lines,
+        }
+    }
+}
+
+impl MessageDialog {
+    /// ダイアログを描画する
+    fn render(&self, area: Rect, buf: &mut Buffer, style: &DialogStyle) {
+        let height = (self.lines.len() as u16).saturating_add(3);
+        let area = centered_area(area, area.width.saturating_sub(10), height);
+        let inner = render_frame(&self.title, area, buf, style);
+
+        let mut lines: Vec<Line> = self.lines.iter().map(|line| Line::raw(line.as_str())).collect();
+        lines.push(Line::from(Span::styled(tr(Msg::PressAnyKey), style.hint)));
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submit(dialog: &mut AttributeDialog) -> (Option<String>, Option<String>, bool) {
+        match dialog.handle_key(KeyEvent::from(KeyCode::Enter)) {
+            AttributeResult::Submit { mode, owner, recursive } => (mode, owner, recursive),
+            _ => panic!("not submitted"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn attribute_dialog_submits_unchanged_values_only_for_recursive_or_multiple_targets() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = crate::fms::path_information(temp.path()).unwrap();
+
+        let (mode, owner, _) = submit(&mut AttributeDialog::new("dir", &file, false));
+        assert_eq!((mode, owner), (None, None));
+
+        let mut dialog = AttributeDialog::new("dir", &file, false);
+        dialog.handle_key(KeyEvent::from(KeyCode::F(2)));
+        let (mode, owner, recursive) = submit(&mut dialog);
+        assert!(recursive);
+        assert!(mode.is_some() && owner.is_some());
+
+        let (mode, owner, recursive) = submit(&mut AttributeDialog::new("2 files", &file, true));
+        assert!(!recursive);
+        assert!(mode.is_some() && owner.is_some());
+    }
+
+    #[test]
+    fn attribute_dialog_submits_edited_values() {
+        let mut dialog = AttributeDialog::new("file", &crate::fms::parent_dir_entry(), false);
+        for c in "u+x".chars() {
+            dialog.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        let (mode, owner, _) = submit(&mut dialog);
+        assert!(mode.is_some_and(|mode| mode.ends_with("u+x")));
+        assert_eq!(owner, None);
+    }
+}
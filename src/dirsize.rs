@@ -0,0 +1,173 @@
+#[cfg(unix)]
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// ディレクトリの合計サイズとファイル数
+///
+/// # フィールド
+/// * `bytes` - ディレクトリ以下のすべてのファイルの合計サイズ（バイト）
+/// * `files` - ディレクトリ以下のファイル数（ディレクトリは含まない）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirSize {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// ディレクトリのサイズの計算状況
+///
+/// # バリアント
+/// * `Measuring` - 計算待ち・計算中
+/// * `Measured` - 計算済み
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirSizeState {
+    Measuring,
+    Measured(DirSize),
+}
+
+/// ディレクトリ以下の合計サイズとファイル数を求める
+/// シンボリックリンクはたどらずにリンク自体を数え、複数のハードリンクがあるファイルは1回だけ数えます
+/// `dir` 自体がシンボリックリンクの場合は、リンク先を数えずに0を返します
+/// 読み込めないディレクトリは飛ばします
+///
+/// # 引数
+/// * `dir` - ディレクトリのパス
+/// * `cancelled` - 計算を中止するかどうかを判定する処理
+///
+/// # 戻り値
+/// `Option<DirSize>` - 合計サイズとファイル数（中止された場合は `None`）
+pub fn measure(dir: &Path, cancelled: impl Fn() -> bool) -> Option<DirSize> {
+    let mut size = DirSize::default();
+    if fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return Some(size);
+    }
+    #[cfg(unix)]
+    let mut linked = HashSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if cancelled() {
+                return None;
+            }
+            // DirEntryのメタデータはシンボリックリンクをたどらない
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+            #[cfg(unix)]
+            if metadata.nlink() > 1 && !linked.insert((metadata.dev(), metadata.ino())) {
+                continue;
+            }
+            size.bytes += metadata.len();
+            size.files += 1;
+        }
+    }
+    Some(size)
+}
+
+/// ディレクトリのサイズを、バックグラウンドのスレッドで1つずつ計算する
+/// 計算の依頼は順番に処理し、`clear` で未処理の依頼と計算中の処理を取り消せます
+///
+/// # フィールド
+/// * `sender` - 計算の依頼（世代とディレクトリのパス）
+/// * `receiver` - 計算の結果
+/// * `generation` - 依頼の世代（`clear` で進め、古い世代の依頼は処理しない）
+pub struct DirSizer {
+    sender: Sender<(u64, PathBuf)>,
+    receiver: Receiver<(PathBuf, DirSize)>,
+    generation: Arc<AtomicU64>,
+}
+
+impl DirSizer {
+    /// 計算のスレッドを開始する
+    /// スレッドはDirSizerを破棄すると終了します
+    pub fn new() -> DirSizer {
+        let (sender, requests) = mpsc::channel::<(u64, PathBuf)>();
+        let (results, receiver) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = Arc::clone(&generation);
+        thread::spawn(move || {
+            for (generation, dir) in requests {
+                let cancelled = || current.load(Ordering::Relaxed) != generation;
+                if cancelled() {
+                    continue;
+                }
+                if let Some(size) = measure(&dir, cancelled) {
+                    if results.send((dir, size)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        DirSizer { sender, receiver, generation }
+    }
+
+    /// ディレクトリのサイズの計算を依頼する
+    pub fn request(&self, dir: PathBuf) {
+        let _ = self.sender.send((self.generation.load(Ordering::Relaxed), dir));
+    }
+
+    /// 未処理の依頼と計算中の処理を取り消す
+    pub fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 計算が終わったディレクトリのサイズを受け取る
+    pub fn take_results(&self) -> Vec<(PathBuf, DirSize)> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn measure_does_not_follow_symlinks() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("a"), b"12345").unwrap();
+        std::os::unix::fs::symlink(&dir, temp.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+
+        let size = measure(&dir, || false).unwrap();
+        assert_eq!(size.files, 2);
+        assert!(size.bytes >= 5);
+        assert_eq!(measure(&temp.path().join("link"), || false), Some(DirSize::default()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn measure_counts_hard_links_once() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir(temp.path().join("sub")).unwrap();
+        fs::write(temp.path().join("a"), b"1234567").unwrap();
+        fs::hard_link(temp.path().join("a"), temp.path().join("b")).unwrap();
+        fs::hard_link(temp.path().join("a"), temp.path().join("sub/c")).unwrap();
+
+        let size = measure(temp.path(), || false).unwrap();
+        assert_eq!(size, DirSize { bytes: 7, files: 1 });
+    }
+
+    #[test]
+    fn measure_stops_when_cancelled() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("a"), b"1").unwrap();
+        assert_eq!(measure(temp.path(), || true), None);
+    }
+}
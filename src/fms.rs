@@ -1,15 +1,97 @@
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
 use std::fs::Metadata;
 use std::io;
-use std::path::Path;
-use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 
 use chrono::DateTime;
 use chrono::Local;
-use num_format::Locale;
-use num_format::ToFormattedString;
 use unicode_width::UnicodeWidthStr;
 
+use crate::format;
+use crate::i18n::{tr, Msg};
+use crate::permission;
+
+/// ファイル操作のエラー
+/// 表示する文言は `message` でメッセージに変換し、画面の言語で表示する
+///
+/// # バリアント
+/// * `NotADirectory` - ディレクトリではないパスが指定された
+/// * `AlreadyExists` - 作成するファイルが既に存在する
+/// * `ReadOnly` - 読み込み専用のファイルシステム（アーカイブなど）を変更しようとした
+/// * `HostKeyChanged` - 接続先のホスト鍵がknown_hostsと一致しない
+/// * `AuthenticationFailed` - 接続先で認証できなかった
+/// * `InvalidFileName` - ファイル名を文字列に変換できない
+/// * `CopyIntoItself` / `MoveIntoItself` - ディレクトリを自身の中にコピー・移動しようとした
+/// * `InvalidMode` - パーミッションの指定が不正
+/// * `NoSuchUser` / `NoSuchGroup` - 存在しないユーザー・グループが指定された
+/// * `ModeUnsupported` / `OwnerUnsupported` - このプラットフォームでは変更できない
+/// * `Io` - OSのエラー
+#[derive(Debug)]
+pub enum FmsError {
+    NotADirectory(PathBuf),
+    AlreadyExists(PathBuf),
+    ReadOnly,
+    HostKeyChanged(String),
+    AuthenticationFailed(String),
+    InvalidFileName(OsString),
+    CopyIntoItself,
+    MoveIntoItself,
+    InvalidMode(String),
+    NoSuchUser(String),
+    NoSuchGroup(String),
+    #[cfg_attr(unix, allow(dead_code))]
+    ModeUnsupported,
+    #[cfg_attr(unix, allow(dead_code))]
+    OwnerUnsupported,
+    Io(io::Error),
+}
+
+/// ファイル操作の結果
+pub type Result<T> = std::result::Result<T, FmsError>;
+
+impl FmsError {
+    /// 画面に表示するメッセージを取得する
+    /// OSのエラーはOSの文言をそのまま表示します
+    pub fn message(&self) -> Option<Msg> {
+        Some(match self {
+            FmsError::NotADirectory(path) => Msg::NotADirectory(path.display().to_string()),
+            FmsError::AlreadyExists(path) => Msg::AlreadyExists(path.display().to_string()),
+            FmsError::ReadOnly => Msg::ReadOnly,
+            FmsError::HostKeyChanged(host) => Msg::HostKeyChanged(host.clone()),
+            FmsError::AuthenticationFailed(origin) => Msg::AuthenticationFailed(origin.clone()),
+            FmsError::InvalidFileName(name) => Msg::InvalidFileName(name.to_string_lossy().into_owned()),
+            FmsError::CopyIntoItself => Msg::CopyIntoItself,
+            FmsError::MoveIntoItself => Msg::MoveIntoItself,
+            FmsError::InvalidMode(spec) => Msg::InvalidMode(spec.clone()),
+            FmsError::NoSuchUser(name) => Msg::NoSuchUser(name.clone()),
+            FmsError::NoSuchGroup(name) => Msg::NoSuchGroup(name.clone()),
+            FmsError::ModeUnsupported => Msg::ModeUnsupported,
+            FmsError::OwnerUnsupported => Msg::OwnerUnsupported,
+            FmsError::Io(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for FmsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, self.message()) {
+            (FmsError::Io(e), _) => write!(f, "{}", e),
+            (_, Some(msg)) => write!(f, "{}", tr(msg)),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for FmsError {}
+
+impl From<io::Error> for FmsError {
+    fn from(e: io::Error) -> Self {
+        FmsError::Io(e)
+    }
+}
+
 /// ファイル情報構造体
 /// 
 /// # フィールド
@@ -17,15 +99,29 @@ use unicode_width::UnicodeWidthStr;
 /// * `file_name` - ファイル名
 /// * `file_size` - ファイルサイズ
 /// * `last_modified` - 更新日時
+/// * `last_changed` - 属性変更日時（Unix以外では作成日時）
+/// * `last_accessed` - アクセス日時
+/// * `mode` - ファイル種別を含むモードビット（Unixのみ）
+/// * `owner` - 所有者名（Unixのみ）
+/// * `group` - グループ名（Unixのみ）
+/// * `inode` - iノード番号（Unixのみ）
+/// * `links` - ハードリンク数（Unixのみ）
 /// 
 /// # 例
 /// 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct FileInformation {
-    pub file_no: u16,
+    pub file_no: usize,
     pub file_name: String,
     pub file_size: u64,
     pub last_modified: Option<DateTime<Local>>,
+    pub last_changed: Option<DateTime<Local>>,
+    pub last_accessed: Option<DateTime<Local>>,
+    pub mode: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub inode: Option<u64>,
+    pub links: Option<u64>,
 }
 
 /// ファイル構造体のメソッド実装
@@ -51,13 +147,16 @@ impl FileInformation {
             // 文字列が短い場合はスペースを追加
             let padding = max_width - name_width;
             format!("{}{}", self.file_name, " ".repeat(padding))
+        } else if max_width < 2 {
+            // 省略記号も入らない場合は空白で埋める
+            " ".repeat(max_width)
         } else {
-            // 文字列が長すぎる場合は切り詰める
+            // 文字列が長すぎる場合は切り詰める（省略記号の1桁を残す）
             let mut truncated_name = String::new();
             let mut current_width = 0;
             for c in self.file_name.chars() {
                 let char_width = UnicodeWidthStr::width(c.to_string().as_str());
-                if current_width + char_width > max_width - 2 {
+                if current_width + char_width > max_width.saturating_sub(1) {
                     break;
                 }
                 truncated_name.push(c);
@@ -77,53 +176,59 @@ impl FileInformation {
     }
 
     /// ファイルサイズをフォーマットする
-    /// 1024バイト以上の場合はKB、MB、GBに変換する
-    /// 三桁カンマ区切りでフォーマットする
-    /// # 引数
+    /// 設定された単位（1024単位・1000単位・バイト数）とロケールの桁区切りでフォーマットする
     ///
-    /// # 例
-    ///  
     /// # 戻り値
     /// `String` - フォーマットされたサイズ
     pub fn format_file_size(&self) -> String {
-        let size = self.file_size as f64;
-        const GIGABYTE: f64 = 1024.0 * 1024.0 * 1024.0;
-        const MEGABYTE: f64 = 1024.0 * 1024.0;
-        const KILOBYTE: f64 = 1024.0;
-
-        let formatted_size = if size >= GIGABYTE {
-            format!("{:.1}G", (size / GIGABYTE))
-        } else if size >= MEGABYTE {
-            format!("{:.1}M", (size / MEGABYTE))
-        } else if size >= KILOBYTE {
-            format!("{:.1}K", (size / KILOBYTE))
-        } else {
-            format!("{} ", (size as u64).to_formatted_string(&Locale::en))
-        };
-  
-        // 小数点以下が0の場合は小数点以下を削除
-        // それ以外は小数点以下を残す
-        if let Some((int_part, dec_part)) = formatted_size.split_once('.') {
-            let num = int_part.parse::<u64>().unwrap();
-            format!("{:>5}.{}", num.to_formatted_string(&Locale::en), dec_part)
-        } else {
-            format!("{:>8}", formatted_size)
-        }
+        format::file_size(self.file_size)
     }
 
-    /// 更新日時をフォーマットする
-    /// YYYY/MM/DD HH:MM 形式でフォーマットする 
-    /// # 引数
+    /// 更新日時を設定された形式でフォーマットする
     ///
-    /// # 例
-    ///  
     /// # 戻り値
     /// `String` - フォーマットされた更新日時
     pub fn formatted_modified(&self) -> String {
-        if let Some(modified) = self.last_modified {
-            modified.format("%Y/%m/%d %H:%M").to_string()
-        } else {
-            " ".repeat(16)
+        format::datetime(self.last_modified)
+    }
+
+    /// 属性変更日時を設定された形式でフォーマットする
+    ///
+    /// # 戻り値
+    /// `String` - フォーマットされた属性変更日時
+    pub fn formatted_changed(&self) -> String {
+        format::datetime(self.last_changed)
+    }
+
+    /// アクセス日時を設定された形式でフォーマットする
+    ///
+    /// # 戻り値
+    /// `String` - フォーマットされたアクセス日時
+    pub fn formatted_accessed(&self) -> String {
+        format::datetime(self.last_accessed)
+    }
+
+    /// 拡張子を取得する
+    /// 先頭のドットのみのファイル名（.bashrc など）は拡張子なしとして扱う
+    ///
+    /// # 戻り値
+    /// `&str` - 拡張子（ドットを含まない）、ない場合は空文字
+    pub fn extension(&self) -> &str {
+        match self.file_name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => extension,
+            _ => "",
+        }
+    }
+
+    /// パーミッションをフォーマットする
+    /// rwxr-xr-x 形式（先頭はファイル種別）でフォーマットする
+    /// 
+    /// # 戻り値
+    /// `String` - フォーマットされたパーミッション（取得できない場合は空白）
+    pub fn format_permissions(&self) -> String {
+        match self.mode {
+            Some(mode) => permission::format_mode(mode),
+            None => " ".repeat(10),
         }
     }
 }
@@ -136,10 +241,20 @@ impl FileInformation {
 /// 
 /// # 例
 /// 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub enum PathKind {
     FILE(FileInformation),   // ファイル
-    DIR(FileInformation),    // ディレクトリ  
+    DIR(FileInformation),    // ディレクトリ
+}
+
+impl PathKind {
+    /// ファイル情報を取得する
+    pub fn info(&self) -> &FileInformation {
+        match self {
+            PathKind::FILE(info) | PathKind::DIR(info) => info,
+        }
+    }
 }
 
 /// ディレクトリ内のファイル一覧を取得します。
@@ -150,17 +265,16 @@ pub enum PathKind {
 /// 
 /// # 戻り値
 /// `Vec<PathKind>` - ファイル一覧
-
-pub fn list_files_in_directory(dir: &str) -> io::Result<Vec<PathKind>> {
+pub fn list_files_in_directory(dir: &str) -> Result<Vec<PathKind>> {
     let directory_path = Path::new(dir);
     let mut file_list: Vec<PathKind> = Vec::new();
 
     if !directory_path.is_dir() {
-        return Err(Error::new(ErrorKind::Other, "指定されたパスはディレクトリではありません"));
+        return Err(FmsError::NotADirectory(directory_path.to_path_buf()));
     } else {
         // ディレクトリの場合は ..（親ディレクトリ） を追加
         let mut file_no = 0;
-        file_list.push(PathKind::DIR(FileInformation {file_no, file_name: "..".to_string(), file_size: 0, last_modified: None}));
+        file_list.push(parent_dir_entry());
         for entry in fs::read_dir(directory_path)? {
             file_no += 1;
             let entry = entry?;
@@ -169,26 +283,39 @@ pub fn list_files_in_directory(dir: &str) -> io::Result<Vec<PathKind>> {
             if let Some(file_name )= entry.file_name().to_str() {
                 add_file_info(&mut file_list, file_no, file_name, &metadata, path.is_dir())?;
             } else {
-                println!("{:?}", entry.file_name().to_str());
-                return Err(Error::new(ErrorKind::Other, "ファイ名変換でエラー"));
+                return Err(FmsError::InvalidFileName(entry.file_name()));
             }
         }
     }
 
-    // 名前順で並び替え
-    // 但しディレクトリが先に来るようにする
-    file_list.sort_unstable_by (|a, b| {
-        match (a, b) {
-            (PathKind::DIR(_), PathKind::FILE(_)) => std::cmp::Ordering::Less,
-            (PathKind::FILE(_), PathKind::DIR(_)) => std::cmp::Ordering::Greater,
-            (PathKind::DIR(a), PathKind::DIR(b)) => a.file_name.cmp(&b.file_name),
-            (PathKind::FILE(a), PathKind::FILE(b)) => a.file_name.cmp(&b.file_name),
-        }
-    });
+    sort_file_list(&mut file_list);
 
     Ok(file_list)
 }
 
+/// ファイル一覧の先頭に置く親ディレクトリ（..）を作成する
+pub fn parent_dir_entry() -> PathKind {
+    PathKind::DIR(FileInformation { file_no: 0, file_name: "..".to_string(), ..Default::default() })
+}
+
+/// ファイル一覧を名前順で並び替える
+/// 但しディレクトリが先に来るようにする
+pub fn sort_file_list(file_list: &mut [PathKind]) {
+    file_list.sort_unstable_by(compare_file_order);
+}
+
+/// ファイル一覧での2つのファイルの順序を比較する（親ディレクトリ（..）が先頭、ディレクトリが先、同じ種類は名前順）
+pub fn compare_file_order(a: &PathKind, b: &PathKind) -> std::cmp::Ordering {
+    match (a, b) {
+        (PathKind::DIR(_), PathKind::FILE(_)) => std::cmp::Ordering::Less,
+        (PathKind::FILE(_), PathKind::DIR(_)) => std::cmp::Ordering::Greater,
+        // `-` や `#` で始まる名前は、名前順では .. より前になる
+        (PathKind::DIR(a), PathKind::DIR(b)) => (a.file_name != "..").cmp(&(b.file_name != ".."))
+            .then_with(|| a.file_name.cmp(&b.file_name)),
+        (PathKind::FILE(a), PathKind::FILE(b)) => a.file_name.cmp(&b.file_name),
+    }
+}
+
 /// ファイル情報を追加する関数
 /// 
 /// # 引数
@@ -200,14 +327,30 @@ pub fn list_files_in_directory(dir: &str) -> io::Result<Vec<PathKind>> {
 /// 
 /// # 戻り値
 /// `io::Result<()>` - 成功した場合は空のタプルを返す
-fn add_file_info(file_list: &mut Vec<PathKind>, file_no: u16, file_name: &str, metadata: &Metadata, is_dir: bool) ->io::Result<()>{
-    let file_info = FileInformation {
+fn add_file_info(file_list: &mut Vec<PathKind>, file_no: usize, file_name: &str, metadata: &Metadata, is_dir: bool) ->io::Result<()>{
+    #[allow(unused_mut)]
+    let mut file_info = FileInformation {
         file_no,
         file_name: file_name.to_string(),
         file_size: metadata.len(),
         last_modified: Some(metadata.modified()?.into()),
+        last_changed: metadata.created().ok().map(Into::into),
+        last_accessed: metadata.accessed().ok().map(Into::into),
+        ..Default::default()
     };
 
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        file_info.last_changed = DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32)
+            .map(|datetime| datetime.with_timezone(&Local));
+        file_info.mode = Some(metadata.mode());
+        file_info.owner = Some(permission::user_name(metadata.uid()).unwrap_or_else(|| metadata.uid().to_string()));
+        file_info.group = Some(permission::group_name(metadata.gid()).unwrap_or_else(|| metadata.gid().to_string()));
+        file_info.inode = Some(metadata.ino());
+        file_info.links = Some(metadata.nlink());
+    }
+
     if is_dir {
         file_list.push(PathKind::DIR(file_info));
     } else {
@@ -215,4 +358,304 @@ fn add_file_info(file_list: &mut Vec<PathKind>, file_no: u16, file_name: &str, m
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 指定パスのファイル情報を取得する
+///
+/// # 引数
+/// * `path` - ファイルまたはディレクトリのパス
+///
+/// # 戻り値
+/// `PathKind` - ファイル情報
+pub fn path_information(path: &Path) -> Result<PathKind> {
+    let metadata = fs::metadata(path)?;
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| FmsError::InvalidFileName(path.as_os_str().to_os_string()))?;
+    let mut file_list = Vec::with_capacity(1);
+    add_file_info(&mut file_list, 0, file_name, &metadata, metadata.is_dir())?;
+    Ok(file_list.remove(0))
+}
+
+/// ディレクトリ内の1つのファイルの情報を、ファイル一覧（`list_files_in_directory`）と同じ方法で取得する
+/// シンボリックリンクはリンク自体の情報で、リンク先がディレクトリの場合はディレクトリとして扱います
+///
+/// # 引数
+/// * `path` - ファイルのパス
+/// * `file_no` - ファイル番号
+pub fn entry_information(path: &Path, file_no: usize) -> Result<PathKind> {
+    let metadata = fs::symlink_metadata(path)?;
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| FmsError::InvalidFileName(path.as_os_str().to_os_string()))?;
+    let mut file_list = Vec::with_capacity(1);
+    add_file_info(&mut file_list, file_no, file_name, &metadata, path.is_dir())?;
+    Ok(file_list.remove(0))
+}
+
+/// 重複しないパスを生成する
+/// `name.ext` が既に存在する場合は `name(1).ext`、`name(2).ext` ... の順に空いている名前を探す
+///
+/// # 引数
+/// * `path` - 元のパス
+///
+/// # 戻り値
+/// `PathBuf` - 存在しないパス
+pub fn unique_path(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let mut count = 1;
+    loop {
+        let candidate = parent.join(format!("{}({}){}", stem, count, extension));
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        count += 1;
+    }
+}
+
+/// ファイルまたはディレクトリをコピーする
+/// ディレクトリの場合は再帰的にコピーし、コピー先に既に存在するファイルは上書きする
+/// シンボリックリンクはたどらずに、同じ場所を指すリンクを作成する
+///
+/// # 引数
+/// * `src` - コピー元のパス
+/// * `dest` - コピー先のパス
+///
+/// # 戻り値
+/// `Result<()>` - 成功した場合は空のタプルを返す
+pub fn copy_path(src: &Path, dest: &Path) -> Result<()> {
+    let file_type = fs::symlink_metadata(src)?.file_type();
+    if file_type.is_symlink() {
+        copy_symlink(src, dest)
+    } else if file_type.is_dir() {
+        if is_inside(src, dest) {
+            return Err(FmsError::CopyIntoItself);
+        }
+        if !fs::symlink_metadata(dest).is_ok_and(|metadata| metadata.is_dir()) {
+            fs::create_dir_all(dest)?;
+        }
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        // コピー先のリンクの先に書き込まないように、リンクは削除してからコピーする
+        if fs::symlink_metadata(dest).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            fs::remove_file(dest)?;
+        }
+        fs::copy(src, dest)?;
+        Ok(())
+    }
+}
+
+/// シンボリックリンクを、同じ場所を指すリンクとしてコピーする
+/// コピー先にディレクトリ以外のファイルが存在する場合は、削除してから作成する
+fn copy_symlink(src: &Path, dest: &Path) -> Result<()> {
+    let target = fs::read_link(src)?;
+    if fs::symlink_metadata(dest).is_ok_and(|metadata| !metadata.is_dir()) {
+        fs::remove_file(dest)?;
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&target, dest)?;
+    #[cfg(windows)]
+    if fs::metadata(src).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(&target, dest)?;
+    } else {
+        std::os::windows::fs::symlink_file(&target, dest)?;
+    }
+    Ok(())
+}
+
+/// コピー・移動先がコピー・移動元のディレクトリの中かどうかを判定する
+/// 相対パスやシンボリックリンクを含むパスでも判定できるように、実際のパスで比較する
+///
+/// # 引数
+/// * `src` - コピー・移動元のディレクトリ
+/// * `dest` - コピー・移動先のパス（まだ存在しなくてもよい）
+fn is_inside(src: &Path, dest: &Path) -> bool {
+    let Ok(src) = src.canonicalize() else {
+        return dest.starts_with(src);
+    };
+    // コピー先はまだ存在しないことが多いため、存在する祖先を実際のパスにして残りをつなげる
+    let mut existing = dest;
+    let mut rest = Vec::new();
+    let dest = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break rest.iter().rev().fold(canonical, |path: PathBuf, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => break dest.to_path_buf(),
+        }
+    };
+    dest.starts_with(src)
+}
+
+/// ファイルまたはディレクトリを移動する
+/// 別のデバイスで名前の変更で移動できない場合は、コピーしてから元を削除する
+///
+/// # 引数
+/// * `src` - 移動元のパス
+/// * `dest` - 移動先のパス
+///
+/// # 戻り値
+/// `Result<()>` - 成功した場合は空のタプルを返す
+pub fn move_path(src: &Path, dest: &Path) -> Result<()> {
+    if fs::symlink_metadata(src)?.is_dir() && is_inside(src, dest) {
+        return Err(FmsError::MoveIntoItself);
+    }
+    match fs::rename(src, dest) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_path(src, dest)?;
+            remove_path(src)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// ファイルまたはディレクトリを削除する
+///
+/// # 引数
+/// * `path` - 削除するパス
+///
+/// # 戻り値
+/// `Result<()>` - 成功した場合は空のタプルを返す
+pub fn remove_path(path: &Path) -> Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn format_file_name_fits_any_width() {
+        let info = FileInformation { file_name: "long_name.txt".to_string(), ..Default::default() };
+        assert_eq!(info.format_file_name(15), "long_name.txt  ");
+        assert_eq!(info.format_file_name(13), "long_name.txt");
+        assert_eq!(info.format_file_name(6), "long_…");
+        assert_eq!(info.format_file_name(2), "l…");
+        assert_eq!(info.format_file_name(1), " ");
+        assert_eq!(info.format_file_name(0), "");
+
+        // 全角文字が省略記号の手前に入らない場合は空白で埋める
+        let info = FileInformation { file_name: "日本語".to_string(), ..Default::default() };
+        assert_eq!(info.format_file_name(4), "日… ");
+        assert_eq!(info.format_file_name(2), "… ");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_path_recreates_symlinks_without_following_them() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/file"), "data").unwrap();
+        // 親ディレクトリへのリンク（たどるとループになる）と、ファイルへのリンク
+        symlink("..", src.join("sub/up")).unwrap();
+        symlink("file", src.join("sub/link")).unwrap();
+
+        let dest = temp.path().join("dest");
+        copy_path(&src, &dest).unwrap();
+
+        assert_eq!(fs::read_link(dest.join("sub/up")).unwrap(), Path::new(".."));
+        assert_eq!(fs::read_link(dest.join("sub/link")).unwrap(), Path::new("file"));
+        assert_eq!(fs::read_to_string(dest.join("sub/file")).unwrap(), "data");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_path_does_not_write_through_a_symlink_at_the_destination() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("target");
+        fs::write(&target, "keep").unwrap();
+        let src = temp.path().join("src");
+        fs::write(&src, "new").unwrap();
+        let dest = temp.path().join("dest");
+        symlink(&target, &dest).unwrap();
+
+        copy_path(&src, &dest).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep");
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new");
+        assert!(!fs::symlink_metadata(&dest).unwrap().file_type().is_symlink());
+    }
+
+    #[test]
+    fn copy_path_rejects_copying_into_itself_through_another_spelling() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+
+        let dotted = src.join("sub").join("..");
+        assert!(matches!(copy_path(&dotted, &src.join("copy")), Err(FmsError::CopyIntoItself)));
+
+        #[cfg(unix)]
+        {
+            let alias = temp.path().join("alias");
+            symlink(&src, &alias).unwrap();
+            assert!(matches!(copy_path(&src, &alias.join("copy")), Err(FmsError::CopyIntoItself)));
+        }
+    }
+
+    #[test]
+    fn move_path_renames_within_a_device_and_rejects_moving_into_itself() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("file"), "data").unwrap();
+
+        assert!(matches!(move_path(&src, &src.join("inner")), Err(FmsError::MoveIntoItself)));
+
+        let dest = temp.path().join("dest");
+        move_path(&src, &dest).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(dest.join("file")).unwrap(), "data");
+    }
+
+    #[test]
+    fn move_path_returns_rename_errors_other_than_crossing_devices() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("file"), "data").unwrap();
+        // 空でないディレクトリへの名前の変更は失敗する（コピーして削除してはいけない）
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("other"), "other").unwrap();
+
+        assert!(move_path(&src, &dest).is_err());
+        assert_eq!(fs::read_to_string(src.join("file")).unwrap(), "data");
+        assert!(!dest.join("file").exists());
+    }
+
+    #[test]
+    fn list_keeps_the_parent_dir_first() {
+        let temp = tempfile::tempdir().unwrap();
+        for dir in ["-dash", "#hash", "b"] {
+            fs::create_dir(temp.path().join(dir)).unwrap();
+        }
+        fs::write(temp.path().join("!file"), "").unwrap();
+
+        let names: Vec<String> = list_files_in_directory(temp.path().to_str().unwrap()).unwrap().iter()
+            .map(|file| file.info().file_name.clone())
+            .collect();
+        assert_eq!(names, ["..", "#hash", "-dash", "b", "!file"]);
+    }
+}
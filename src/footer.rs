@@ -1,59 +1,135 @@
-use ratatui::{buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style, Stylize}, text::{Line, Span}, widgets::{Block, Paragraph, Widget}
+use ratatui::{buffer::Buffer, crossterm::event::{KeyCode, KeyModifiers}, layout::{Constraint, Direction, Layout, Rect}, text::{Line, Span}, widgets::Widget
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-pub struct FooterWidget;
+use crate::{app::{App, ClickTarget}, i18n::tr, keymap::KeyPress, theme::Theme};
 
-impl Widget for FooterWidget {
+/// ファンクションキーの数
+const FUNCTION_KEY_COUNT: u8 = 12;
+
+/// 修飾キーの表示に使う幅
+const MODIFIER_LABEL_LENGTH: u16 = 4;
+
+pub struct FooterWidget<'a> {
+    app: &'a App,
+}
+
+impl FooterWidget<'_> {
+    pub fn new(app: &App) -> FooterWidget<'_> {
+        FooterWidget { app }
+    }
+
+    /// 現在の画面（ダイアログ・ビューア・使用量ビュー・ファイル一覧）に応じた、フッターに表示するキーを取得する
+    fn function_keys(&self) -> Vec<FunctionKeyWidget> {
+        if let Some(keys) = self.app.dialog_function_keys() {
+            return keys.into_iter()
+                .map(|(code, label)| {
+                    let key = KeyPress::new(code, KeyModifiers::NONE).to_string();
+                    FunctionKeyWidget::new(&label, &key, Some(ClickTarget::Key(code)))
+                })
+                .collect();
+        }
+
+        (1..=FUNCTION_KEY_COUNT)
+            .map(|number| {
+                let key = number.to_string();
+                if let Some(view_keys) = self.app.view_function_keys() {
+                    match view_keys.iter().find(|(n, _)| *n == number) {
+                        Some((_, msg)) => FunctionKeyWidget::new(&tr(msg.clone()), &key, Some(ClickTarget::Key(KeyCode::F(number)))),
+                        None => FunctionKeyWidget::new("", &key, None),
+                    }
+                } else {
+                    // 修飾キーが押されている場合は、修飾キーとの組み合わせに割り当てられた操作を表示する
+                    match self.app.keymap().function_key(number, self.app.held_modifiers()) {
+                        Some(action) => FunctionKeyWidget::new(&action.label(), &key, Some(ClickTarget::Action(action))),
+                        None => FunctionKeyWidget::new("", &key, None),
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl Widget for FooterWidget<'_> {
     fn render (self, area: Rect, buf: &mut Buffer) {
+        let keys = self.function_keys();
 
-        let fnc1 = FunctionKeyWidget::new("Quit", "F1");
-        fnc1.render(area, buf);
-        let fnc2 = FunctionKeyWidget::new("Quit", "F2");
-        fnc2.render(area, buf);
+        // 修飾キーが押されている場合は、左端に修飾キーを表示する
+        let modifiers = self.app.held_modifiers();
+        let modifier_label: String = [(KeyModifiers::CONTROL, "^"), (KeyModifiers::ALT, "M-"), (KeyModifiers::SHIFT, "S-")]
+            .into_iter()
+            .filter(|(modifier, _)| modifiers.contains(*modifier))
+            .map(|(_, label)| label)
+            .collect();
+        let area = if modifiers.is_empty() || self.app.has_dialog() || self.app.view_function_keys().is_some() {
+            area
+        } else {
+            let [modifier_area, rest] = Layout::horizontal([Constraint::Length(MODIFIER_LABEL_LENGTH), Constraint::Min(0)])
+                .areas(area);
+            Span::styled(format!("{:<width$}", modifier_label, width = MODIFIER_LABEL_LENGTH as usize),
+                self.app.theme().modifier)
+                .render(modifier_area, buf);
+            rest
+        };
+
+        // 画面の幅に合わせて、すべてのキーを等幅に並べる
+        let count = keys.len().max(1) as u32;
+        let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, count); keys.len()])
+        .split(area);
+
+        for (key, chunk) in keys.into_iter().zip(chunks.iter()) {
+            // クリックで操作を実行できるように領域を登録する
+            if let Some(target) = key.target.clone() {
+                self.app.add_click_area(*chunk, target);
+            }
+            key.render(*chunk, buf, self.app.theme());
+        }
     }
 }
 
 pub struct FunctionKeyWidget {
     label: String,
     key: String,
+    target: Option<ClickTarget>,
 }
 
 impl FunctionKeyWidget {
-    pub fn new(label: &str, key: &str) -> Self {
+    pub fn new(label: &str, key: &str, target: Option<ClickTarget>) -> Self {
         Self {
             label: label.to_string(),
             key: key.to_string(),
+            target,
         }
     }
 }
 
-impl Widget for FunctionKeyWidget {
-    fn render (self, area: Rect, buf: &mut Buffer) {
-        let mut keys:Vec<(FunctionKeyWidget)> = Vec::new();
-        keys.push(FunctionKeyWidget::new("終了", "F1"));
-        // keys.push(FunctionKeyWidget::new("XXXX", "F2"));
-        
-        const KEY_LABEL_LENGTH: u16 = 12;
-
-        let chunks =   Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(vec![
-            Constraint::Length(KEY_LABEL_LENGTH); keys.len()
-        ])
-        .split(area);  //keys.push(FunctionKeyWidget::new("Save", "F2"));
-
-        for (i, key) in keys.iter().enumerate() {
-            let key_label = Line::from(vec![
-                Span::styled(" ", Style::default().bg(Color::Gray)),
-                Span::styled(&key.label, Style::default().fg(Color::Black).bg(Color::Gray)),
-                Span::styled(" ", Style::default().bg(Color::Gray)),
-                Span::styled(&key.key, Style::default().fg(Color::Yellow).bg(Color::Gray)),
-                Span::styled(" ", Style::default().bg(Color::Gray)),
-            ]);
-            
-            key_label.render(chunks[i], buf);
-        }    
+impl FunctionKeyWidget {
+    /// テーマの配色で1つのキーを描画する
+    fn render(self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        // 名前は、キーと区切りの空白を除いた幅に収まるように切り詰める
+        let label_width = (area.width as usize).saturating_sub(self.key.len() + 1);
+        let label = truncate(&self.label, label_width);
+        let padding = label_width.saturating_sub(label.width());
 
+        let key_label = Line::from(vec![
+            Span::styled(self.key, theme.key),
+            Span::styled(label, theme.key_label),
+            Span::styled(" ".repeat(padding), theme.key_label),
+        ]);
 
+        key_label.render(area, buf);
    }
-}
\ No newline at end of file
+}
+
+/// 文字列を表示幅に収まるように切り詰める
+fn truncate(value: &str, width: usize) -> String {
+    let mut used = 0;
+    value.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
@@ -0,0 +1,293 @@
+use std::io;
+use std::sync::OnceLock;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, TimeZone};
+use num_format::{Locale, ToFormattedString};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{DateFormat, FormatConfig, SizeFormat};
+use crate::i18n::{tr, Msg};
+
+/// 単位付きのファイルサイズの表示幅（9,999.9G）
+const SIZE_WIDTH: usize = 8;
+
+/// バイト数をそのまま表示する場合の表示幅（999,999,999,999,999）
+const BYTES_WIDTH: usize = 19;
+
+/// 経過時間で表示する場合の表示幅
+const RELATIVE_WIDTH: usize = 10;
+
+/// ファイルサイズ・日時の表示形式
+///
+/// # フィールド
+/// * `size` - ファイルサイズの単位
+/// * `locale` - 桁区切り・小数点の記号に使うロケール
+/// * `date` - 日時の表示形式
+/// * `date_width` - 日時の表示幅
+struct Format {
+    size: SizeFormat,
+    locale: Locale,
+    date: DateFormat,
+    date_width: usize,
+}
+
+impl Format {
+    /// 数値を桁区切りでフォーマットする
+    fn number(&self, value: u64) -> String {
+        value.to_formatted_string(&self.locale)
+    }
+
+    /// ファイルサイズを右寄せでフォーマットする（`file_size` を参照）
+    fn file_size(&self, bytes: u64) -> String {
+        let (base, units): (f64, [&str; 4]) = match self.size {
+            SizeFormat::Bytes => return format!("{:>width$}", self.number(bytes), width = BYTES_WIDTH),
+            SizeFormat::Iec => (1024.0, ["K", "M", "G", "T"]),
+            SizeFormat::Si => (1000.0, ["k", "M", "G", "T"]),
+        };
+
+        let mut size = bytes as f64;
+        let mut unit = None;
+        for candidate in units {
+            if size < base {
+                break;
+            }
+            size /= base;
+            unit = Some(candidate);
+        }
+
+        match unit {
+            // 整数部は桁区切り、小数部は1桁で、ロケールの小数点の記号を使う
+            Some(unit) => {
+                let tenths = (size * 10.0).round() as u64;
+                let value = format!("{}{}{}{}", self.number(tenths / 10), self.locale.decimal(), tenths % 10, unit);
+                format!("{:>width$}", value, width = SIZE_WIDTH)
+            }
+            None => format!("{:>width$} ", self.number(bytes), width = SIZE_WIDTH - 1),
+        }
+    }
+
+    /// 日時をフォーマットする（`datetime` を参照）
+    fn datetime(&self, datetime: Option<DateTime<Local>>) -> String {
+        let value = match (&self.date, datetime) {
+            (_, None) => String::new(),
+            (DateFormat::Relative, Some(datetime)) => relative(&datetime),
+            (date, Some(datetime)) => format_absolute(date, &datetime),
+        };
+        let padding = self.date_width.saturating_sub(value.width());
+        format!("{}{}", value, " ".repeat(padding))
+    }
+}
+
+/// 使用中の表示形式
+static FORMAT: OnceLock<Format> = OnceLock::new();
+
+/// 表示形式を設定する
+/// 起動時に1度だけ呼び出し、2回目以降の呼び出しは無視されます
+///
+/// # 引数
+/// * `config` - 設定ファイルの `[format]` の内容
+///
+/// # 戻り値
+/// `io::Result<()>` - ロケール名や日時の書式が不正な場合はエラー
+pub fn init(config: &FormatConfig) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("[format] {}", message));
+
+    let locale = Locale::from_name(&config.locale)
+        .map_err(|_| invalid(format!("unknown locale `{}`", config.locale)))?;
+    if let DateFormat::Custom(pattern) = &config.date {
+        if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+            return Err(invalid(format!("invalid date format `{}`", pattern)));
+        }
+    }
+
+    let _ = FORMAT.set(Format {
+        size: config.size,
+        locale,
+        date: config.date.clone(),
+        date_width: measure_date_width(&config.date),
+    });
+    Ok(())
+}
+
+/// 使用中の表示形式を取得する
+fn current() -> &'static Format {
+    FORMAT.get_or_init(|| Format {
+        size: SizeFormat::Iec,
+        locale: Locale::en,
+        date: DateFormat::Default,
+        date_width: measure_date_width(&DateFormat::Default),
+    })
+}
+
+/// 日時の書式の表示幅を求める
+/// 月名・曜日名を含む書式に備えて、名前が最も長くなる日時（9月の水曜日）で計測します
+fn measure_date_width(date: &DateFormat) -> usize {
+    let sample = Local.with_ymd_and_hms(2000, 9, 27, 23, 59, 59).single();
+    match (date, sample) {
+        (DateFormat::Relative, _) => RELATIVE_WIDTH,
+        (_, Some(sample)) => format_absolute(date, &sample).width(),
+        (_, None) => 16,
+    }
+}
+
+/// ファイルサイズの表示幅を取得する
+pub fn size_width() -> usize {
+    match current().size {
+        SizeFormat::Bytes => BYTES_WIDTH,
+        SizeFormat::Iec | SizeFormat::Si => SIZE_WIDTH,
+    }
+}
+
+/// 日時の表示幅を取得する
+pub fn date_width() -> usize {
+    current().date_width
+}
+
+/// 数値を桁区切りでフォーマットする
+pub fn number(value: u64) -> String {
+    current().number(value)
+}
+
+/// ファイルサイズを右寄せでフォーマットする
+/// 単位付きの場合は、単位未満のサイズはバイト数をそのまま表示します
+///
+/// # 引数
+/// * `bytes` - ファイルサイズ（バイト）
+///
+/// # 戻り値
+/// `String` - フォーマットされたサイズ（`size_width` の幅）
+pub fn file_size(bytes: u64) -> String {
+    current().file_size(bytes)
+}
+
+/// 日時をフォーマットする
+/// 日時がない場合は同じ幅の空白を返します
+///
+/// # 引数
+/// * `datetime` - 日時
+///
+/// # 戻り値
+/// `String` - フォーマットされた日時（`date_width` の幅）
+pub fn datetime(datetime: Option<DateTime<Local>>) -> String {
+    current().datetime(datetime)
+}
+
+/// 日時を書式に従ってフォーマットする
+fn format_absolute(date: &DateFormat, datetime: &DateTime<Local>) -> String {
+    let pattern = match date {
+        DateFormat::Default | DateFormat::Relative => "%Y/%m/%d %H:%M",
+        DateFormat::Iso => "%Y-%m-%dT%H:%M:%S",
+        DateFormat::Custom(pattern) => pattern,
+    };
+    datetime.format(pattern).to_string()
+}
+
+/// 現在からの経過時間をフォーマットする
+/// 未来の日時は「たった今」として扱います
+fn relative(datetime: &DateTime<Local>) -> String {
+    let seconds = (Local::now() - *datetime).num_seconds().max(0);
+    tr(relative_msg(seconds))
+}
+
+/// 経過秒数に応じた経過時間のメッセージを取得する
+fn relative_msg(seconds: i64) -> Msg {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    match seconds {
+        s if s < MINUTE => Msg::JustNow,
+        s if s < HOUR => Msg::MinutesAgo(s / MINUTE),
+        s if s < DAY => Msg::HoursAgo(s / HOUR),
+        s if s < MONTH => Msg::DaysAgo(s / DAY),
+        s if s < YEAR => Msg::MonthsAgo(s / MONTH),
+        s => Msg::YearsAgo(s / YEAR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::{tr_in, Language};
+
+    /// テスト用の表示形式
+    fn format(size: SizeFormat, locale: Locale, date: DateFormat) -> Format {
+        let date_width = measure_date_width(&date);
+        Format { size, locale, date, date_width }
+    }
+
+    #[test]
+    fn file_size_switches_units_at_the_base() {
+        let si = format(SizeFormat::Si, Locale::en, DateFormat::Default);
+        let iec = format(SizeFormat::Iec, Locale::en, DateFormat::Default);
+        let cases = [
+            (999, "    999 ", "    999 "),
+            (1000, "    1.0k", "  1,000 "),
+            (1023, "    1.0k", "  1,023 "),
+            (1024, "    1.0k", "    1.0K"),
+            (1_500_000, "    1.5M", "    1.4M"),
+            (1024 * 1024 * 1024, "    1.1G", "    1.0G"),
+            (u64::MAX, "18,446,744.1T", "16,777,216.0T"),
+        ];
+        for (bytes, expected_si, expected_iec) in cases {
+            assert_eq!(si.file_size(bytes), expected_si, "SI {}", bytes);
+            assert_eq!(iec.file_size(bytes), expected_iec, "IEC {}", bytes);
+        }
+        for bytes in [0, 999, 1000, 1023, 1024, 999_949, 1_048_575, 1 << 40] {
+            assert_eq!(si.file_size(bytes).width(), SIZE_WIDTH, "SI {}", bytes);
+            assert_eq!(iec.file_size(bytes).width(), SIZE_WIDTH, "IEC {}", bytes);
+        }
+    }
+
+    #[test]
+    fn file_size_uses_the_locale_separators() {
+        let de = format(SizeFormat::Bytes, Locale::de, DateFormat::Default);
+        assert_eq!(de.file_size(1_234_567), format!("{:>width$}", "1.234.567", width = BYTES_WIDTH));
+        let fr = format(SizeFormat::Bytes, Locale::fr, DateFormat::Default);
+        let separator = Locale::fr.separator();
+        assert_eq!(fr.file_size(1_234_567), format!("{:>width$}", format!("1{0}234{0}567", separator), width = BYTES_WIDTH));
+
+        let de = format(SizeFormat::Iec, Locale::de, DateFormat::Default);
+        assert_eq!(de.file_size(1536), "    1,5K");
+        assert_eq!(de.file_size(1_234_567_890_123), "    1,1T");
+        assert_eq!(de.file_size(1023 * 1024 * 1024 * 1024 * 1024), "1.023,0T");
+    }
+
+    #[test]
+    fn relative_dates_fit_the_column() {
+        const DAY: i64 = 24 * 60 * 60;
+        let seconds = [0, 59, 60, 3599, 3600, DAY - 1, DAY, 30 * DAY - 1, 30 * DAY, 365 * DAY - 1, 365 * DAY, 99 * 365 * DAY];
+        for language in [Language::English, Language::Japanese] {
+            for s in seconds {
+                let text = tr_in(language, relative_msg(s));
+                assert!(text.width() <= RELATIVE_WIDTH, "{:?} {}: {:?}", language, s, text);
+            }
+        }
+        assert_eq!(tr_in(Language::English, relative_msg(30 * DAY - 1)), "29d ago");
+        assert_eq!(tr_in(Language::Japanese, relative_msg(365 * DAY - 1)), "12か月前");
+    }
+
+    #[test]
+    fn absolute_dates_use_the_pattern_and_its_width() {
+        let datetime = Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let default = format(SizeFormat::Iec, Locale::en, DateFormat::Default);
+        assert_eq!(default.datetime(Some(datetime)), "2024/01/02 03:04");
+        assert_eq!(default.datetime(None), " ".repeat(16));
+
+        let iso = format(SizeFormat::Iec, Locale::en, DateFormat::Iso);
+        assert_eq!(iso.date_width, 19);
+        assert_eq!(iso.datetime(Some(datetime)), "2024-01-02T03:04:05");
+
+        // 月名・曜日名の幅は、最も長くなる日時で計測する
+        let custom = format(SizeFormat::Iec, Locale::en, DateFormat::Custom("%A %e %B".to_string()));
+        assert_eq!(custom.date_width, "Wednesday 27 September".len());
+        assert_eq!(custom.datetime(Some(datetime)), format!("{:<22}", "Tuesday  2 January"));
+
+        let relative = format(SizeFormat::Iec, Locale::en, DateFormat::Relative);
+        assert_eq!(relative.date_width, RELATIVE_WIDTH);
+        assert_eq!(relative.datetime(Some(Local::now())).width(), RELATIVE_WIDTH);
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+/// ディレクトリのあるファイルシステムの情報
+///
+/// # フィールド
+/// * `mount_point` - マウントポイント（取得できない場合は空）
+/// * `fs_type` - ファイルシステムの種類（取得できない場合は空）
+/// * `free` - 空き容量（一般ユーザーが使えるバイト数）
+/// * `total` - 全体の容量（バイト）
+#[derive(Clone, Debug, Default)]
+pub struct FsInfo {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub free: u64,
+    pub total: u64,
+}
+
+/// ディレクトリのあるファイルシステムの情報を取得する
+///
+/// # 引数
+/// * `path` - ディレクトリのパス
+///
+/// # 戻り値
+/// `Option<FsInfo>` - ファイルシステムの情報（取得できない場合は `None`）
+#[cfg(unix)]
+pub fn filesystem(path: &Path) -> Option<FsInfo> {
+    unix::filesystem(path)
+}
+
+/// ディレクトリのあるファイルシステムの情報を取得する
+/// Unix以外では取得できないため、常に `None` を返します
+#[cfg(not(unix))]
+pub fn filesystem(_path: &Path) -> Option<FsInfo> {
+    None
+}
+
+/// statvfsで容量を取得し、OSごとの方法でマウントポイントと種類を取得する
+#[cfg(unix)]
+mod unix {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::FsInfo;
+
+    pub fn filesystem(path: &Path) -> Option<FsInfo> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        // f_frsize が0のファイルシステムでは f_bsize を単位にする
+        let block_size = match stat.f_frsize as u64 {
+            0 => stat.f_bsize as u64,
+            size => size,
+        };
+        let (mount_point, fs_type) = mount(path, &c_path).unwrap_or_default();
+        Some(FsInfo {
+            mount_point,
+            fs_type,
+            free: stat.f_bavail as u64 * block_size,
+            total: stat.f_blocks as u64 * block_size,
+        })
+    }
+
+    /// /proc/self/mountinfo から、パスを含む一番深いマウントポイントと種類を探す
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn mount(path: &Path, _c_path: &CString) -> Option<(String, String)> {
+        let path = path.canonicalize().ok()?;
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+        mountinfo.lines()
+            .filter_map(|line| {
+                // ID 親ID デバイス ルート マウントポイント オプション [任意のフィールド...] - 種類 ソース スーパーブロックのオプション
+                let (fields, rest) = line.split_once(" - ")?;
+                let mount_point = unescape(fields.split(' ').nth(4)?);
+                let fs_type = rest.split(' ').next()?.to_string();
+                Some((mount_point, fs_type))
+            })
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.len())
+    }
+
+    /// mountinfo で8進数にエスケープされた空白などを元に戻す
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn unescape(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut unescaped = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let octal = bytes.get(i + 1..i + 4)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+            match octal {
+                Some(byte) if bytes[i] == b'\\' => {
+                    unescaped.push(byte);
+                    i += 4;
+                }
+                _ => {
+                    unescaped.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&unescaped).into_owned()
+    }
+
+    /// statfs のマウントポイントと種類を取得する
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly"))]
+    fn mount(_path: &Path, c_path: &CString) -> Option<(String, String)> {
+        use std::ffi::CStr;
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return None;
+        }
+        let mount_point = unsafe { CStr::from_ptr(stat.f_mntonname.as_ptr()) }.to_string_lossy().into_owned();
+        let fs_type = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) }.to_string_lossy().into_owned();
+        Some((mount_point, fs_type))
+    }
+
+    /// マウントポイントと種類を取得する方法がないOSでは、容量のみ表示する
+    #[cfg(not(any(
+        target_os = "linux", target_os = "android",
+        target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "dragonfly"
+    )))]
+    fn mount(_path: &Path, _c_path: &CString) -> Option<(String, String)> {
+        None
+    }
+}
@@ -1,14 +1,19 @@
-use ratatui::{buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style, Stylize}, symbols::border, text::{Line, Span}, widgets::{Block, Paragraph, Widget}
+use ratatui::{buffer::Buffer, layout::{Alignment, Rect}, symbols::border, text::{Line, Span}, widgets::{Block, Paragraph, Widget}
 };
 
-use crate::{app::App, utils::div_cell};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{app::{App, ClickTarget}, format, i18n::{tr, Msg}, utils::div_cell};
+
+/// ヘッダーの右側の項目の間のスペース
+const SEGMENT_SPACING: usize = 2;
 
 pub struct HeaderWidget<'a> {
     app: &'a App,
 }
 
 impl HeaderWidget<'_> {
-    pub fn new<'a>(app: &'a App) -> HeaderWidget<'a> {
+    pub fn new(app: &App) -> HeaderWidget<'_> {
         HeaderWidget { app }
     }
 }
@@ -16,40 +21,87 @@ impl HeaderWidget<'_> {
 impl Widget for HeaderWidget<'_> {
     fn render (self, area: Rect, buf: &mut Buffer) {
 
+        let theme = self.app.theme();
+
         // タイトルの設定    
-        let title = Line::from(" -ONE- file and directory manager ").style(Style::default().fg(Color::Blue));
+        let title = Line::from(tr(Msg::Title)).style(theme.title);
 
         // ブロックの設定
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::ROUNDED)
-            .border_style(Style::default().fg(Color::Blue));
+            .border_style(theme.border);
 
         // 現在のディレクトリパス    
         let dir_path_label = "PATH=";
-        let dir_path_value = self.app.dir_path();
+        let dir_path_value = self.app.display_path();
 
-        // ページ情報（現在のページ数/全ページ数）
-        let page_label = "PAGE=";
+        // 右側に表示する項目（ラベル・値・幅が足りない場合に省略する順番）
+        // マウントポイントと種類、空き容量/全体の容量、一覧のファイル数と合計サイズ、ページ情報（現在のページ数/全ページ数）
+        let mut segments: Vec<(&str, String, u8)> = Vec::new();
+        if let Some(fs_info) = self.app.fs_info() {
+            let mount = match (fs_info.mount_point.is_empty(), fs_info.fs_type.is_empty()) {
+                (true, _) => None,
+                (false, true) => Some(fs_info.mount_point),
+                (false, false) => Some(format!("{}({})", fs_info.mount_point, fs_info.fs_type)),
+            };
+            if let Some(mount) = mount {
+                segments.push(("MOUNT=", mount, 0));
+            }
+            let free = format!("{}/{}", format::file_size(fs_info.free).trim_start(), format::file_size(fs_info.total).trim_start());
+            segments.push(("FREE=", free, 2));
+        }
+        let (file_count, total_size) = self.app.listing_totals();
+        segments.push(("FILES=", format!("{}({})", format::number(file_count as u64), format::file_size(total_size).trim_start()), 1));
         let total_pages = div_cell(self.app.count_file_list(),self.app.max_files_per_page());
-        let page_value = format!("{}/{}", self.app.current_page_no(), total_pages);
+        segments.push(("PAGE=", format!("{}/{}", self.app.current_page_no(), total_pages), u8::MAX));
 
 
         // `block` の内部の幅を計算(両端の枠線分(2)を除く)
         let inner_width = area.width.saturating_sub(2) as usize;
 
-        let dir_path_len = dir_path_label.len() + dir_path_value.len();
-        let page_len = page_label.len() + page_value.len();
-        let space_between = inner_width.saturating_sub(dir_path_len + page_len);
+        // パスと右側の項目が収まるまで、優先度の低い項目から省略する（ページ情報は常に表示する）
+        let dir_path_len = dir_path_label.len() + dir_path_value.width();
+        let segments_len = |segments: &[(&str, String, u8)]| -> usize {
+            let len: usize = segments.iter().map(|(label, value, _)| label.len() + value.width() + SEGMENT_SPACING).sum();
+            len.saturating_sub(SEGMENT_SPACING)
+        };
+        while dir_path_len + SEGMENT_SPACING + segments_len(&segments) > inner_width {
+            let lowest = segments.iter().enumerate()
+                .filter(|(_, (_, _, priority))| *priority != u8::MAX)
+                .min_by_key(|(_, (_, _, priority))| *priority)
+                .map(|(index, _)| index);
+            match lowest {
+                Some(index) => { segments.remove(index); }
+                None => break,
+            }
+        }
+        let space_between = inner_width.saturating_sub(dir_path_len + segments_len(&segments));
 
-        // 現在のディレクトリパスとページ情報をフォーマット
-        let formatted_line = Line::from(vec![
-            Span::styled(dir_path_label, Style::default().fg(Color::LightCyan)),
-            Span::styled(dir_path_value, Style::default().fg(Color::Blue)),
+        // 現在のディレクトリパスと右側の項目をフォーマット
+        let mut spans = vec![
+            Span::styled(dir_path_label, theme.label),
+            Span::styled(dir_path_value.as_str(), theme.value),
             Span::raw(" ".repeat(space_between)),
-            Span::styled(page_label, Style::default().fg(Color::LightCyan)),
-            Span::styled(page_value, Style::default().fg(Color::Blue)),
-        ]);
+        ];
+        for (i, (label, value, _)) in segments.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" ".repeat(SEGMENT_SPACING)));
+            }
+            spans.push(Span::styled(label, theme.label));
+            spans.push(Span::styled(value, theme.value));
+        }
+        let formatted_line = Line::from(spans);
+
+        // パスの各階層をクリックでそのディレクトリに移動できるように領域を登録する
+        let inner = block.inner(area);
+        let path_x = inner.x + dir_path_label.len() as u16;
+        for (start, end, target) in path_segments(&dir_path_value) {
+            let x = path_x + dir_path_value[..start].width() as u16;
+            let width = dir_path_value[start..end].width() as u16;
+            let segment = Rect::new(x, inner.y, width, 1).intersection(inner);
+            self.app.add_click_area(segment, ClickTarget::Path(target));
+        }
 
         Paragraph::new(formatted_line)
             .block(block)
@@ -57,4 +109,30 @@ impl Widget for HeaderWidget<'_> {
             .render(area, buf);
 
     }
-}
\ No newline at end of file
+}
+
+/// パスを階層ごとに分割する
+/// 区切り文字（/ または \）も直前の階層に含める
+///
+/// # 引数
+/// * `path` - ディレクトリパス
+///
+/// # 戻り値
+/// `Vec<(usize, usize, String)>` - 各階層の開始位置、終了位置（バイト単位）と、その階層までのパス
+fn path_segments(path: &str) -> Vec<(usize, usize, String)> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, c) in path.char_indices() {
+        if c == '/' || c == '\\' {
+            let end = i + c.len_utf8();
+            // ルート（/）やドライブ（c:/）は区切り文字を含めたパス、それ以外は区切り文字を除いたパス
+            let target = if start == 0 || path[..i].ends_with(':') { &path[..end] } else { &path[..i] };
+            segments.push((start, end, target.to_string()));
+            start = end;
+        }
+    }
+    if start < path.len() {
+        segments.push((start, path.len(), path.to_string()));
+    }
+    segments
+}
@@ -0,0 +1,469 @@
+use std::env;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::keymap::Action;
+use crate::operation::{ConflictAction, OperationKind};
+
+/// 表示言語
+/// 設定ファイルでは括弧内の名前で指定する
+///
+/// # バリアント
+/// * `Auto` - 環境変数 `LC_ALL`、`LC_MESSAGES`、`LANG` から決める（auto）
+/// * `English` - 英語（en）
+/// * `Japanese` - 日本語（ja）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "ja")]
+    Japanese,
+}
+
+impl Language {
+    /// `Auto` の場合は環境変数のロケールから言語を決める
+    /// 最初に設定されている変数が `ja` で始まる場合は日本語、それ以外は英語です
+    fn resolve(self) -> Language {
+        if self != Language::Auto {
+            return self;
+        }
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.starts_with("ja") {
+            Language::Japanese
+        } else {
+            Language::English
+        }
+    }
+}
+
+/// 使用中の言語
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// 表示言語を設定する
+/// 起動時に1度だけ呼び出し、2回目以降の呼び出しは無視されます
+pub fn init(language: Language) {
+    let _ = LANGUAGE.set(language.resolve());
+}
+
+/// 使用中の言語を取得する
+fn language() -> Language {
+    *LANGUAGE.get_or_init(|| Language::Auto.resolve())
+}
+
+/// 画面に表示するメッセージ
+/// 文言は言語ごとのカタログ（`english`、`japanese`）で定義する
+#[derive(Clone, Debug)]
+pub enum Msg {
+    // 画面全体
+    Title,
+    TerminalTooSmall(u16, u16),
+    PressAnyKey,
+    WaitingForJobs,
+
+    // 操作の名前
+    Action(Action),
+    Operation(OperationKind),
+    Conflict(ConflictAction),
+
+    // フッターのキーの名前
+    Confirm,
+    Cancel,
+    Abort,
+    ApplyToAll,
+    Recursive,
+    NextField,
+    Close,
+    Wrap,
+    Delete,
+    Output,
+    History,
+    Run,
+    Format,
+
+    // ダイアログ
+    Error,
+    ViewError,
+    OpenWithTitle(String),
+    OpenError,
+    NoOpener,
+    CommandFailed(String),
+    MenuHint,
+    ArchiveError,
+    ExtractPrompt,
+    ExtractError,
+    ExecTitle,
+    CaptureOption,
+    PlaceholderHint,
+    ExecHint,
+    PressEnterToReturn,
+    PackTitle,
+    NameLabel,
+    FormatLabel,
+    PackHint,
+    Packing(String),
+    PackError,
+    Loading(usize),
+    Connecting(String),
+    CopyingTo(String),
+    RunningCommand(String),
+    LoadError,
+    GotoPrompt,
+    InvalidUrl(String),
+    ConnectError,
+    UnknownHostTitle(String),
+    TrustHostKey(String),
+    PasswordPrompt(String),
+    PasswordRetry(String),
+    Scanning(String, String),
+    UsageTotal(String, String),
+    DeleteTitle(String),
+    DeleteError,
+    OperationError(OperationKind),
+    AttributeError,
+    DestinationPrompt(OperationKind),
+    FileCount(usize),
+    InputHint,
+    ConflictTitle(OperationKind),
+    SourceLabel(OperationKind),
+    DestinationLabel(OperationKind),
+    Newer,
+    ApplyToAllOption,
+    ConflictHint,
+    AttributeTitle,
+    TargetLabel,
+    CurrentLabel,
+    ModeLabel,
+    OwnerLabel,
+    RecursiveOption,
+    AttributeHint,
+    AttributeExample,
+
+    // 経過時間
+    JustNow,
+    MinutesAgo(i64),
+    HoursAgo(i64),
+    DaysAgo(i64),
+    MonthsAgo(i64),
+    YearsAgo(i64),
+
+    // ファイル操作のエラー
+    NotADirectory(String),
+    AlreadyExists(String),
+    ReadOnly,
+    HostKeyChanged(String),
+    AuthenticationFailed(String),
+    InvalidFileName(String),
+    CopyIntoItself,
+    MoveIntoItself,
+    InvalidMode(String),
+    NoSuchUser(String),
+    NoSuchGroup(String),
+    ModeUnsupported,
+    OwnerUnsupported,
+}
+
+/// メッセージを使用中の言語の文言に変換する
+pub fn tr(msg: Msg) -> String {
+    tr_in(language(), msg)
+}
+
+/// メッセージを指定した言語の文言に変換する
+pub fn tr_in(language: Language, msg: Msg) -> String {
+    match language {
+        Language::Japanese => japanese(msg),
+        Language::English | Language::Auto => english(msg),
+    }
+}
+
+/// 英語のカタログ
+fn english(msg: Msg) -> String {
+    match msg {
+        Msg::Title => " -ONE- file and directory manager ".to_string(),
+        Msg::TerminalTooSmall(width, height) => format!("The terminal must be at least {}x{} (W x H).", width, height),
+        Msg::PressAnyKey => "Press any key".to_string(),
+        Msg::WaitingForJobs => "Waiting for background tasks to finish...".to_string(),
+
+        Msg::Action(action) => match action {
+            Action::Quit => "Quit",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::PageUp => "PgUp",
+            Action::PageDown => "PgDn",
+            Action::Home => "Top",
+            Action::End => "Bottom",
+            Action::Enter => "Open",
+            Action::OpenWith => "OpenWith",
+            Action::Exec => "Exec",
+            Action::Subshell => "Shell",
+            Action::View => "View",
+            Action::ToggleMark => "Mark",
+            Action::Copy => "Copy",
+            Action::Move => "Move",
+            Action::Attribute => "Attrs",
+            Action::Pack => "Pack",
+            Action::Goto => "Goto",
+            Action::Refresh => "Refresh",
+            Action::DirSize => "DirSize",
+            Action::DiskUsage => "Usage",
+            Action::ToggleLayout => "Detail",
+            Action::ToggleScroll => "Scroll",
+            Action::ListColumns(0) => "AutoCol",
+            Action::ListColumns(count) => return format!("{}Cols", count),
+            Action::None => "",
+        }.to_string(),
+        Msg::Operation(kind) => match kind {
+            OperationKind::Copy => "Copy",
+            OperationKind::Move => "Move",
+        }.to_string(),
+        Msg::Conflict(action) => match action {
+            ConflictAction::Overwrite => "Overwrite",
+            ConflictAction::Skip => "Skip",
+            ConflictAction::Rename => "Rename",
+            ConflictAction::OverwriteIfNewer => "Overwrite if newer",
+        }.to_string(),
+
+        Msg::Confirm => "OK".to_string(),
+        Msg::Cancel => "Cancel".to_string(),
+        Msg::Abort => "Abort".to_string(),
+        Msg::ApplyToAll => "All".to_string(),
+        Msg::Recursive => "Recurse".to_string(),
+        Msg::NextField => "Field".to_string(),
+        Msg::Close => "Close".to_string(),
+        Msg::Wrap => "Wrap".to_string(),
+        Msg::Delete => "Delete".to_string(),
+        Msg::Output => "Output".to_string(),
+        Msg::History => "History".to_string(),
+        Msg::Run => "Run".to_string(),
+        Msg::Format => "Format".to_string(),
+
+        Msg::Error => "Error".to_string(),
+        Msg::ViewError => "Cannot view the file".to_string(),
+        Msg::OpenWithTitle(name) => format!("Open {} with", name),
+        Msg::OpenError => "Cannot open the file".to_string(),
+        Msg::NoOpener => "No program is configured for this file".to_string(),
+        Msg::CommandFailed(status) => format!("The command exited with {}", status),
+        Msg::MenuHint => "↑↓:Select  1-9:Choose  Enter:OK  Esc:Cancel".to_string(),
+        Msg::ArchiveError => "Cannot open the archive".to_string(),
+        Msg::ExtractPrompt => "Extract to directory".to_string(),
+        Msg::ExtractError => "Extract errors".to_string(),
+        Msg::ExecTitle => "Shell command".to_string(),
+        Msg::CaptureOption => "[F2]Show the output in the viewer".to_string(),
+        Msg::PlaceholderHint => "%f:File  %m:Marked files  %d:Directory  %o:Previous directory  %%:%".to_string(),
+        Msg::ExecHint => "↑↓:History  Enter:Run  Esc:Cancel".to_string(),
+        Msg::PressEnterToReturn => "Press Enter to return".to_string(),
+        Msg::PackTitle => "Pack into an archive".to_string(),
+        Msg::NameLabel => "Name    : ".to_string(),
+        Msg::FormatLabel => "Format  : ".to_string(),
+        Msg::PackHint => "Tab/←→:Format  Enter:OK  Esc:Cancel".to_string(),
+        Msg::Packing(name) => format!("Packing {}", name),
+        Msg::PackError => "Pack errors".to_string(),
+        Msg::Loading(count) => format!("Loading {} entries...  Esc:Cancel", count),
+        Msg::Connecting(origin) => format!("Connecting to {}...  Esc:Cancel", origin),
+        Msg::CopyingTo(dest) => format!("Copying to {}", dest),
+        Msg::RunningCommand(command_line) => format!("Running {}...  Esc:Cancel", command_line),
+        Msg::LoadError => "Cannot read some entries".to_string(),
+        Msg::GotoPrompt => "Go to directory (or sftp://[user@]host[:port][/path])".to_string(),
+        Msg::InvalidUrl(url) => format!("Invalid URL: {}", url),
+        Msg::ConnectError => "Cannot connect".to_string(),
+        Msg::UnknownHostTitle(host) => format!("Unknown host key: {}", host),
+        Msg::TrustHostKey(fingerprint) => format!("Trust {} and connect", fingerprint),
+        Msg::PasswordPrompt(origin) => format!("Password or passphrase for {}", origin),
+        Msg::PasswordRetry(origin) => format!("Authentication failed. Password for {}", origin),
+        Msg::Scanning(files, size) => format!("Scanning {} files, {}...  Esc:Cancel ", files, size),
+        Msg::UsageTotal(size, files) => format!("{} in {} files", size, files),
+        Msg::DeleteTitle(path) => format!("Delete {}?", path),
+        Msg::DeleteError => "Cannot delete".to_string(),
+        Msg::OperationError(kind) => format!("{} errors", english(Msg::Operation(kind))),
+        Msg::AttributeError => "Cannot change attributes".to_string(),
+        Msg::DestinationPrompt(kind) => format!("{} to directory", english(Msg::Operation(kind))),
+        Msg::FileCount(count) => format!("{} files", count),
+        Msg::InputHint => "Enter:OK  Esc:Cancel".to_string(),
+        Msg::ConflictTitle(_) => "The destination already exists".to_string(),
+        Msg::SourceLabel(_) => "From    : ".to_string(),
+        Msg::DestinationLabel(_) => "To      : ".to_string(),
+        Msg::Newer => " (newer)".to_string(),
+        Msg::ApplyToAllOption => "[A]Apply to all".to_string(),
+        Msg::ConflictHint => "←→:Select  Enter:OK  Esc:Abort".to_string(),
+        Msg::AttributeTitle => "Change attributes".to_string(),
+        Msg::TargetLabel => "Target  : ".to_string(),
+        Msg::CurrentLabel => "Current : ".to_string(),
+        Msg::ModeLabel => "Mode    : ".to_string(),
+        Msg::OwnerLabel => "Owner   : ".to_string(),
+        Msg::RecursiveOption => "[F2]Include subdirectories".to_string(),
+        Msg::AttributeHint => "Tab:Next field  Enter:OK  Esc:Cancel".to_string(),
+        Msg::AttributeExample => "Mode: 755 / u+x,go-w  Owner: user:group".to_string(),
+
+        Msg::JustNow => "just now".to_string(),
+        Msg::MinutesAgo(n) => format!("{}min ago", n),
+        Msg::HoursAgo(n) => format!("{}h ago", n),
+        Msg::DaysAgo(n) => format!("{}d ago", n),
+        Msg::MonthsAgo(n) => format!("{}mo ago", n),
+        Msg::YearsAgo(n) => format!("{}y ago", n),
+
+        Msg::NotADirectory(path) => format!("{} is not a directory", path),
+        Msg::AlreadyExists(path) => format!("{} already exists", path),
+        Msg::ReadOnly => "The file system is read-only".to_string(),
+        Msg::HostKeyChanged(host) => format!("The host key of {} does not match known_hosts", host),
+        Msg::AuthenticationFailed(origin) => format!("Authentication failed: {}", origin),
+        Msg::InvalidFileName(name) => format!("Cannot convert the file name: {}", name),
+        Msg::CopyIntoItself => "Cannot copy a directory into itself".to_string(),
+        Msg::MoveIntoItself => "Cannot move a directory into itself".to_string(),
+        Msg::InvalidMode(spec) => format!("Invalid mode: {}", spec),
+        Msg::NoSuchUser(name) => format!("No such user: {}", name),
+        Msg::NoSuchGroup(name) => format!("No such group: {}", name),
+        Msg::ModeUnsupported => "Changing permissions is not supported on this platform".to_string(),
+        Msg::OwnerUnsupported => "Changing the owner is not supported on this platform".to_string(),
+    }
+}
+
+/// 日本語のカタログ
+fn japanese(msg: Msg) -> String {
+    match msg {
+        Msg::Title => " -ONE- ファイル・ディレクトリマネージャー ".to_string(),
+        Msg::TerminalTooSmall(width, height) => format!("表示可能なターミナルの最小サイズは(W x H)は{}x{}です。", width, height),
+        Msg::PressAnyKey => "何かキーを押してください".to_string(),
+        Msg::WaitingForJobs => "バックグラウンドの処理の終了を待っています...".to_string(),
+
+        Msg::Action(action) => match action {
+            Action::Quit => "終了",
+            Action::Up => "上へ",
+            Action::Down => "下へ",
+            Action::Left => "左へ",
+            Action::Right => "右へ",
+            Action::PageUp => "前頁",
+            Action::PageDown => "次頁",
+            Action::Home => "先頭",
+            Action::End => "末尾",
+            Action::Enter => "開く",
+            Action::OpenWith => "開く…",
+            Action::Exec => "実行",
+            Action::Subshell => "シェル",
+            Action::View => "表示",
+            Action::ToggleMark => "マーク",
+            Action::Copy => "コピー",
+            Action::Move => "移動",
+            Action::Attribute => "属性",
+            Action::Pack => "圧縮",
+            Action::Goto => "移動",
+            Action::Refresh => "再読込",
+            Action::DirSize => "容量計算",
+            Action::DiskUsage => "使用量",
+            Action::ToggleLayout => "詳細",
+            Action::ToggleScroll => "スクロール",
+            Action::ListColumns(0) => "自動列",
+            Action::ListColumns(count) => return format!("{}列", count),
+            Action::None => "",
+        }.to_string(),
+        Msg::Operation(kind) => match kind {
+            OperationKind::Copy => "コピー",
+            OperationKind::Move => "移動",
+        }.to_string(),
+        Msg::Conflict(action) => match action {
+            ConflictAction::Overwrite => "上書き",
+            ConflictAction::Skip => "スキップ",
+            ConflictAction::Rename => "名前変更",
+            ConflictAction::OverwriteIfNewer => "新しければ上書き",
+        }.to_string(),
+
+        Msg::Confirm => "確定".to_string(),
+        Msg::Cancel => "取消".to_string(),
+        Msg::Abort => "中止".to_string(),
+        Msg::ApplyToAll => "すべて".to_string(),
+        Msg::Recursive => "再帰".to_string(),
+        Msg::NextField => "項目".to_string(),
+        Msg::Close => "閉じる".to_string(),
+        Msg::Wrap => "折返し".to_string(),
+        Msg::Delete => "削除".to_string(),
+        Msg::Output => "出力表示".to_string(),
+        Msg::History => "履歴".to_string(),
+        Msg::Run => "実行".to_string(),
+        Msg::Format => "形式".to_string(),
+
+        Msg::Error => "エラー".to_string(),
+        Msg::ViewError => "表示エラー".to_string(),
+        Msg::OpenWithTitle(name) => format!("{} を開くプログラム", name),
+        Msg::OpenError => "ファイルを開けません".to_string(),
+        Msg::NoOpener => "このファイルを開くプログラムが設定されていません".to_string(),
+        Msg::CommandFailed(status) => format!("コマンドが異常終了しました（{}）", status),
+        Msg::MenuHint => "↑↓:選択  1-9:番号で選択  Enter:確定  Esc:キャンセル".to_string(),
+        Msg::ArchiveError => "アーカイブを開けません".to_string(),
+        Msg::ExtractPrompt => "展開先のディレクトリ".to_string(),
+        Msg::ExtractError => "展開エラー".to_string(),
+        Msg::ExecTitle => "コマンドの実行".to_string(),
+        Msg::CaptureOption => "[F2]出力をビューアに表示".to_string(),
+        Msg::PlaceholderHint => "%f:ファイル  %m:マーク  %d:ディレクトリ  %o:前のディレクトリ  %%:%".to_string(),
+        Msg::ExecHint => "↑↓:履歴  Enter:実行  Esc:キャンセル".to_string(),
+        Msg::PressEnterToReturn => "Enterキーで戻ります".to_string(),
+        Msg::PackTitle => "アーカイブに圧縮".to_string(),
+        Msg::NameLabel => "名前    : ".to_string(),
+        Msg::FormatLabel => "形式    : ".to_string(),
+        Msg::PackHint => "Tab/←→:形式切替  Enter:確定  Esc:キャンセル".to_string(),
+        Msg::Packing(name) => format!("{} に圧縮中", name),
+        Msg::PackError => "圧縮エラー".to_string(),
+        Msg::Loading(count) => format!("{} 件を読み込み中...  Esc:中止", count),
+        Msg::Connecting(origin) => format!("{} に接続中...  Esc:中止", origin),
+        Msg::CopyingTo(dest) => format!("{} にコピー中", dest),
+        Msg::RunningCommand(command_line) => format!("{} を実行中...  Esc:中止", command_line),
+        Msg::LoadError => "読み込めないファイルがあります".to_string(),
+        Msg::GotoPrompt => "移動先のディレクトリ（または sftp://[user@]host[:port][/path]）".to_string(),
+        Msg::InvalidUrl(url) => format!("URLが不正です: {}", url),
+        Msg::ConnectError => "接続できません".to_string(),
+        Msg::UnknownHostTitle(host) => format!("未知のホスト鍵: {}", host),
+        Msg::TrustHostKey(fingerprint) => format!("{} を信頼して接続", fingerprint),
+        Msg::PasswordPrompt(origin) => format!("{} のパスワード・パスフレーズ", origin),
+        Msg::PasswordRetry(origin) => format!("認証に失敗しました。{} のパスワード", origin),
+        Msg::Scanning(files, size) => format!("{} ファイル・{} をスキャン中...  Esc:中止 ", files, size),
+        Msg::UsageTotal(size, files) => format!("{} / {} ファイル", size, files),
+        Msg::DeleteTitle(path) => format!("{} を削除しますか?", path),
+        Msg::DeleteError => "削除エラー".to_string(),
+        Msg::OperationError(kind) => format!("{}エラー", japanese(Msg::Operation(kind))),
+        Msg::AttributeError => "属性の変更エラー".to_string(),
+        Msg::DestinationPrompt(kind) => format!("{}先のディレクトリ", japanese(Msg::Operation(kind))),
+        Msg::FileCount(count) => format!("{}個のファイル", count),
+        Msg::InputHint => "Enter:確定  Esc:キャンセル".to_string(),
+        Msg::ConflictTitle(kind) => format!("{}先に同じ名前が存在します", japanese(Msg::Operation(kind))),
+        Msg::SourceLabel(kind) => format!("{}元: ", japanese(Msg::Operation(kind))),
+        Msg::DestinationLabel(kind) => format!("{}先: ", japanese(Msg::Operation(kind))),
+        Msg::Newer => " (新しい)".to_string(),
+        Msg::ApplyToAllOption => "[A]すべてに適用".to_string(),
+        Msg::ConflictHint => "←→:選択  Enter:決定  Esc:中止".to_string(),
+        Msg::AttributeTitle => "属性の変更".to_string(),
+        Msg::TargetLabel => "対象    : ".to_string(),
+        Msg::CurrentLabel => "現在    : ".to_string(),
+        Msg::ModeLabel => "権限    : ".to_string(),
+        Msg::OwnerLabel => "所有者  : ".to_string(),
+        Msg::RecursiveOption => "[F2]サブディレクトリも変更".to_string(),
+        Msg::AttributeHint => "Tab:項目切替  Enter:確定  Esc:キャンセル".to_string(),
+        Msg::AttributeExample => "権限: 755 / u+x,go-w  所有者: user:group".to_string(),
+
+        Msg::JustNow => "たった今".to_string(),
+        Msg::MinutesAgo(n) => format!("{}分前", n),
+        Msg::HoursAgo(n) => format!("{}時間前", n),
+        Msg::DaysAgo(n) => format!("{}日前", n),
+        Msg::MonthsAgo(n) => format!("{}か月前", n),
+        Msg::YearsAgo(n) => format!("{}年前", n),
+
+        Msg::NotADirectory(path) => format!("{} はディレクトリではありません", path),
+        Msg::AlreadyExists(path) => format!("{} は既に存在します", path),
+        Msg::ReadOnly => "読み込み専用のファイルシステムです".to_string(),
+        Msg::HostKeyChanged(host) => format!("{} のホスト鍵がknown_hostsと一致しません", host),
+        Msg::AuthenticationFailed(origin) => format!("認証に失敗しました: {}", origin),
+        Msg::InvalidFileName(name) => format!("ファイル名を変換できません: {}", name),
+        Msg::CopyIntoItself => "コピー先がコピー元のディレクトリ内です".to_string(),
+        Msg::MoveIntoItself => "移動先が移動元のディレクトリ内です".to_string(),
+        Msg::InvalidMode(spec) => format!("パーミッションの指定が不正です: {}", spec),
+        Msg::NoSuchUser(name) => format!("ユーザーが存在しません: {}", name),
+        Msg::NoSuchGroup(name) => format!("グループが存在しません: {}", name),
+        Msg::ModeUnsupported => "このプラットフォームではパーミッションを変更できません".to_string(),
+        Msg::OwnerUnsupported => "このプラットフォームでは所有者を変更できません".to_string(),
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::fms::FmsError;
+
+/// バックグラウンドの処理の進行状況
+/// 処理するスレッドが更新し、画面の描画で参照する
+///
+/// # フィールド
+/// * `total` - 処理する全体の量（バイト数など）
+/// * `done` - 処理済みの量
+/// * `current` - 処理中の対象の名前
+#[derive(Default)]
+pub struct Progress {
+    total: AtomicU64,
+    done: AtomicU64,
+    current: Mutex<String>,
+}
+
+impl Progress {
+    /// 処理する全体の量を設定する
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// 処理済みの量を加算する
+    pub fn add_done(&self, amount: u64) {
+        self.done.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// 処理中の対象の名前を設定する
+    pub fn set_current(&self, name: &str) {
+        if let Ok(mut current) = self.current.lock() {
+            name.clone_into(&mut current);
+        }
+    }
+
+    /// 処理済みの割合（0.0～1.0）を取得する
+    pub fn ratio(&self) -> f64 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        (self.done.load(Ordering::Relaxed) as f64 / total as f64).clamp(0.0, 1.0)
+    }
+
+    /// 処理中の対象の名前を取得する
+    pub fn current(&self) -> String {
+        self.current.lock().map(|current| current.clone()).unwrap_or_default()
+    }
+}
+
+/// バックグラウンドで実行する処理
+/// 処理の結果は、ファイル操作の場合は発生したエラーの一覧です
+///
+/// # フィールド
+/// * `title` - 進行状況に表示する処理の名前
+/// * `progress` - 進行状況
+/// * `handle` - 処理するスレッド（終了すると、処理の結果を返す）
+pub struct Job<T = Vec<(PathBuf, FmsError)>> {
+    title: String,
+    progress: Arc<Progress>,
+    handle: JoinHandle<T>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    /// 処理をバックグラウンドのスレッドで開始する
+    ///
+    /// # 引数
+    /// * `title` - 進行状況に表示する処理の名前
+    /// * `task` - 処理（進行状況を更新しながら実行し、結果を返す）
+    pub fn spawn<F>(title: String, task: F) -> Job<T>
+    where
+        F: FnOnce(&Progress) -> T + Send + 'static,
+    {
+        let progress = Arc::new(Progress::default());
+        let shared = Arc::clone(&progress);
+        let handle = thread::spawn(move || task(&shared));
+        Job { title, progress, handle }
+    }
+
+    /// 処理の名前を取得する
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// 進行状況を取得する
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// 処理が終了したかどうかを取得する
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// 処理の終了を待って、結果を取得する
+    ///
+    /// # 戻り値
+    /// `Option<T>` - 処理の結果（処理が異常終了した場合は `None`）
+    pub fn result(self) -> Option<T> {
+        self.handle.join().ok()
+    }
+}
+
+impl Job {
+    /// 処理の終了を待って、発生したエラーを取得する
+    pub fn join(self) -> Vec<(PathBuf, FmsError)> {
+        let title = self.title;
+        self.handle.join().unwrap_or_else(|_| {
+            vec![(PathBuf::from(title), FmsError::Io(std::io::Error::other("the background task panicked")))]
+        })
+    }
+}
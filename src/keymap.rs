@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::i18n::{tr, Msg};
+
+/// キーに割り当てられる操作
+/// 設定ファイルでは括弧内の名前で指定する
+///
+/// # バリアント
+/// * `Quit` - 終了する（quit）
+/// * `Up` / `Down` / `Left` / `Right` - フォーカスを移動する（up, down, left, right）
+/// * `PageUp` / `PageDown` - 1画面分フォーカスを移動する（page_up, page_down）
+/// * `Home` / `End` - 先頭・末尾にフォーカスを移動する（home, end）
+/// * `Enter` - フォーカスされているディレクトリに移動する、ファイルは規則に従ってプログラムで開く（enter）
+/// * `OpenWith` - フォーカスされているファイルを開くプログラムを選択する（open_with）
+/// * `View` - フォーカスされているファイルの内容を表示する（view）
+/// * `Exec` - シェルのコマンドラインを入力して実行する（exec）
+/// * `Subshell` - 現在のディレクトリでシェルを起動する（subshell）
+/// * `ToggleMark` - マークを切り替える（toggle_mark）
+/// * `Copy` / `Move` - コピー・移動する（copy, move）
+/// * `Attribute` - パーミッション・所有者を変更する（attribute）
+/// * `Pack` - アーカイブに圧縮する（pack）
+/// * `Goto` - 移動先のディレクトリ・SFTPのURLを入力して移動する（goto）
+/// * `Refresh` - ファイル一覧を読み直す（refresh）
+/// * `DirSize` - ディレクトリの合計サイズとファイル数を計算する（dir_size）
+/// * `DiskUsage` - 現在のディレクトリ以下の使用量をスキャンして表示する（disk_usage）
+/// * `ToggleLayout` - 標準と詳細のレイアウトを切り替える（toggle_layout）
+/// * `ToggleScroll` - スクロール方法を切り替える（toggle_scroll）
+/// * `ListColumns` - ファイル一覧の列数を指定する、0は自動（columns_auto, columns_1 ～ columns_9）
+/// * `None` - 割り当てを解除する（none）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    OpenWith,
+    View,
+    Exec,
+    Subshell,
+    ToggleMark,
+    Copy,
+    Move,
+    Attribute,
+    Pack,
+    Goto,
+    Refresh,
+    DirSize,
+    DiskUsage,
+    ToggleLayout,
+    ToggleScroll,
+    ListColumns(usize),
+    None,
+}
+
+impl Action {
+    /// 名前で指定できる操作の一覧
+    const NAMED: [(&'static str, Action); 26] = [
+        ("quit", Action::Quit),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("left", Action::Left),
+        ("right", Action::Right),
+        ("page_up", Action::PageUp),
+        ("page_down", Action::PageDown),
+        ("home", Action::Home),
+        ("end", Action::End),
+        ("enter", Action::Enter),
+        ("open_with", Action::OpenWith),
+        ("view", Action::View),
+        ("exec", Action::Exec),
+        ("subshell", Action::Subshell),
+        ("toggle_mark", Action::ToggleMark),
+        ("copy", Action::Copy),
+        ("move", Action::Move),
+        ("attribute", Action::Attribute),
+        ("pack", Action::Pack),
+        ("goto", Action::Goto),
+        ("refresh", Action::Refresh),
+        ("dir_size", Action::DirSize),
+        ("disk_usage", Action::DiskUsage),
+        ("toggle_layout", Action::ToggleLayout),
+        ("toggle_scroll", Action::ToggleScroll),
+        ("none", Action::None),
+    ];
+
+    /// フッターなどに表示する操作の名前を取得する
+    pub fn label(&self) -> String {
+        tr(Msg::Action(*self))
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if let Some((_, action)) = Action::NAMED.iter().find(|(action_name, _)| *action_name == name) {
+            return Ok(*action);
+        }
+        match name.strip_prefix("columns_") {
+            Some("auto") => Ok(Action::ListColumns(0)),
+            Some(count) => match count.parse() {
+                Ok(count @ 1..=9) => Ok(Action::ListColumns(count)),
+                _ => Err(format!("unknown action `{}`", name)),
+            },
+            None => Err(format!("unknown action `{}`", name)),
+        }
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+/// 修飾キーを含む1回のキー入力
+///
+/// # フィールド
+/// * `code` - キー
+/// * `modifiers` - 修飾キー（文字キーとBackTabのShiftはキー自体で区別するため含めない）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    /// 新しいKeyPressインスタンスを返します。
+    /// Shift+Tab は、端末から届くキーと同じ BackTab にします
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyPress {
+        let mut modifiers = modifiers & (KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            code => code,
+        };
+        if let KeyCode::Char(_) | KeyCode::BackTab = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        KeyPress { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        KeyPress::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = String;
+
+    /// `ctrl+x`、`alt+F5`、`shift+pgup`、`a` などの形式を解析する
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid key `{}`", spec);
+
+        // `+` や `ctrl++` のように + キー自体を指定する場合を考慮して、修飾キーとキーに分ける
+        let (modifier_names, key) = match spec.strip_suffix('+') {
+            Some(rest) => (rest.strip_suffix('+').unwrap_or(rest), "+"),
+            None => spec.rsplit_once('+').unwrap_or(("", spec)),
+        };
+        if key.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pgup" | "pageup" => KeyCode::PageUp,
+            "pgdn" | "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "ins" | "insert" => KeyCode::Insert,
+            "del" | "delete" => KeyCode::Delete,
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n @ 1..=24) => KeyCode::F(n),
+                        _ => return Err(invalid()),
+                    },
+                }
+            }
+        };
+        Ok(KeyPress::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    /// フッターに表示する形式（`F5`、`^X`、`M-x` など）でフォーマットする
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "^")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Char(' ') => write!(f, "Spc"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "Ent"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "BTab"),
+            KeyCode::Backspace => write!(f, "BS"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Delete => write!(f, "Del"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// キー入力の照合結果
+///
+/// # バリアント
+/// * `Action` - 操作が確定した
+/// * `Pending` - 複数キーの組み合わせの途中
+/// * `Unbound` - 割り当てられていないキー
+pub enum KeyMatch {
+    Action(Action),
+    Pending,
+    Unbound,
+}
+
+/// キー割り当て
+/// 既定の割り当てに、設定ファイルの `[keys]` の割り当てを上書きする
+///
+/// ```toml
+/// [keys]
+/// "ctrl+q" = "quit"
+/// "g g" = "home"          # スペース区切りで複数キーの組み合わせ
+/// "F1" = "none"           # 割り当ての解除
+/// ```
+///
+/// # フィールド
+/// * `bindings` - キーの組み合わせと操作の対応
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+}
+
+impl Keymap {
+    /// 既定のキー割り当て
+    const DEFAULT: [(&'static str, Action); 46] = [
+        ("F1", Action::Quit),
+        ("F2", Action::ToggleLayout),
+        ("shift+F2", Action::ToggleScroll),
+        ("F3", Action::View),
+        ("shift+F3", Action::OpenWith),
+        ("F4", Action::Attribute),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("left", Action::Left),
+        ("right", Action::Right),
+        ("pgup", Action::PageUp),
+        ("pgdn", Action::PageDown),
+        ("home", Action::Home),
+        ("end", Action::End),
+        ("enter", Action::Enter),
+        ("space", Action::Enter),
+        ("ins", Action::ToggleMark),
+        ("F5", Action::Copy),
+        ("shift+F5", Action::Pack),
+        ("F6", Action::Move),
+        ("F9", Action::Exec),
+        ("shift+F9", Action::Subshell),
+        ("a", Action::Attribute),
+        ("p", Action::Pack),
+        ("o", Action::OpenWith),
+        ("x", Action::Exec),
+        ("!", Action::Subshell),
+        ("z", Action::DirSize),
+        ("u", Action::DiskUsage),
+        ("i", Action::ToggleLayout),
+        ("s", Action::ToggleScroll),
+        ("0", Action::ListColumns(0)),
+        ("1", Action::ListColumns(1)),
+        ("2", Action::ListColumns(2)),
+        ("3", Action::ListColumns(3)),
+        ("4", Action::ListColumns(4)),
+        ("5", Action::ListColumns(5)),
+        ("6", Action::ListColumns(6)),
+        ("7", Action::ListColumns(7)),
+        ("8", Action::ListColumns(8)),
+        ("9", Action::ListColumns(9)),
+        ("ctrl+c", Action::Quit),
+        ("ctrl+l", Action::Goto),
+        ("ctrl+r", Action::Refresh),
+        ("g g", Action::Home),
+        ("shift+g", Action::End),
+    ];
+
+    /// 既定の割り当てに設定ファイルの割り当てを上書きしたKeymapインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `config` - 設定ファイルの `[keys]` の内容（キーの組み合わせと操作）
+    ///
+    /// # 戻り値
+    /// `io::Result<Keymap>` - キー割り当て（キーの指定が不正な場合はエラー）
+    pub fn new(config: &HashMap<String, Action>) -> io::Result<Keymap> {
+        let mut keymap = Keymap { bindings: Vec::new() };
+        for (spec, action) in Keymap::DEFAULT {
+            keymap.bind(parse_keys(spec).map_err(io::Error::other)?, action);
+        }
+
+        // 設定ファイルの割り当ては順序が不定なので、キーの指定順に並べてから適用する
+        let mut user_bindings: Vec<(&String, &Action)> = config.iter().collect();
+        user_bindings.sort_by(|a, b| a.0.cmp(b.0));
+        for (spec, action) in user_bindings {
+            let keys = parse_keys(spec)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("[keys] {}", e)))?;
+            keymap.bind(keys, *action);
+        }
+        Ok(keymap)
+    }
+
+    /// キーの組み合わせに操作を割り当てる
+    /// 既に割り当てがある場合は置き換え、`Action::None` の場合は割り当てを解除する
+    fn bind(&mut self, keys: Vec<KeyPress>, action: Action) {
+        self.bindings.retain(|(bound, _)| *bound != keys);
+        if action != Action::None {
+            self.bindings.push((keys, action));
+        }
+    }
+
+    /// 入力済みのキーの組み合わせに対応する操作を探す
+    ///
+    /// # 引数
+    /// * `keys` - 入力済みのキーの組み合わせ
+    ///
+    /// # 戻り値
+    /// `KeyMatch` - 照合結果
+    pub fn lookup(&self, keys: &[KeyPress]) -> KeyMatch {
+        if let Some((_, action)) = self.bindings.iter().find(|(bound, _)| bound.as_slice() == keys) {
+            return KeyMatch::Action(*action);
+        }
+        if self.bindings.iter().any(|(bound, _)| bound.len() > keys.len() && bound.starts_with(keys)) {
+            return KeyMatch::Pending;
+        }
+        KeyMatch::Unbound
+    }
+
+    /// ファンクションキーに割り当てられている操作を取得する
+    /// 組み合わせの一部として割り当てられている場合は対象外です
+    ///
+    /// # 引数
+    /// * `number` - ファンクションキーの番号（F1は1）
+    /// * `modifiers` - 同時に押されている修飾キー
+    pub fn function_key(&self, number: u8, modifiers: KeyModifiers) -> Option<Action> {
+        let key = KeyPress::new(KeyCode::F(number), modifiers);
+        self.bindings.iter()
+            .find(|(keys, _)| keys.as_slice() == [key])
+            .map(|(_, action)| *action)
+    }
+}
+
+/// スペース区切りのキーの組み合わせを解析する
+fn parse_keys(spec: &str) -> Result<Vec<KeyPress>, String> {
+    let keys = spec.split_whitespace()
+        .map(KeyPress::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err(format!("invalid key `{}`", spec));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// キーの指定を解析する
+    fn key(spec: &str) -> KeyPress {
+        spec.parse().unwrap()
+    }
+
+    /// キーの組み合わせを照合する
+    fn lookup(keymap: &Keymap, spec: &str) -> KeyMatch {
+        keymap.lookup(&parse_keys(spec).unwrap())
+    }
+
+    #[test]
+    fn parsed_keys_display_as_footer_labels() {
+        let cases = [
+            ("F5", "F5"),
+            ("shift+F5", "S-F5"),
+            ("ctrl+x", "^X"),
+            ("alt+x", "M-x"),
+            ("ctrl+alt+del", "^M-Del"),
+            ("shift+pgup", "S-PgUp"),
+            ("shift+a", "A"),
+            ("G", "G"),
+            ("space", "Spc"),
+            ("enter", "Ent"),
+            ("+", "+"),
+            ("ctrl++", "^+"),
+            ("shift+tab", "BTab"),
+            ("up", "↑"),
+        ];
+        for (spec, label) in cases {
+            assert_eq!(key(spec).to_string(), label, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parsed_keys_match_terminal_events() {
+        let event = |code, modifiers| KeyPress::from(KeyEvent::new(code, modifiers));
+        assert_eq!(key("shift+g"), event(KeyCode::Char('G'), KeyModifiers::SHIFT));
+        assert_eq!(key("!"), event(KeyCode::Char('!'), KeyModifiers::SHIFT));
+        assert_eq!(key("shift+tab"), event(KeyCode::Tab, KeyModifiers::SHIFT));
+        assert_eq!(key("backtab"), event(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert_eq!(key("ctrl+c"), event(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(key("F3"), event(KeyCode::F(3), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn modifier_and_key_spellings() {
+        assert_eq!(key("control+x"), key("ctrl+x"));
+        assert_eq!(key("CTRL+x"), key("ctrl+x"));
+        assert_eq!(key("meta+x"), key("alt+x"));
+        assert_eq!(key("alt+ctrl+x"), key("ctrl+alt+x"));
+        assert_eq!(key("return"), key("enter"));
+        assert_eq!(key("escape"), key("esc"));
+        assert_eq!(key("pageup"), key("pgup"));
+        assert_eq!(key("pagedown"), key("pgdn"));
+        assert_eq!(key("insert"), key("ins"));
+        assert_eq!(key("delete"), key("del"));
+        assert_eq!(key("f12"), key("F12"));
+
+        for invalid in ["", "hyper+x", "x+ctrl", "F0", "F25", "xy", "ctrl+shift"] {
+            assert!(invalid.parse::<KeyPress>().is_err(), "{}", invalid);
+        }
+        // 末尾の + は + キー自体
+        assert_eq!(key("ctrl+"), key("ctrl++"));
+        assert!(parse_keys("   ").is_err());
+    }
+
+    #[test]
+    fn chords_wait_for_the_next_key() {
+        let keymap = Keymap::new(&HashMap::new()).unwrap();
+        assert!(matches!(lookup(&keymap, "g"), KeyMatch::Pending));
+        assert!(matches!(lookup(&keymap, "g g"), KeyMatch::Action(Action::Home)));
+        assert!(matches!(lookup(&keymap, "g x"), KeyMatch::Unbound));
+        assert!(matches!(lookup(&keymap, "shift+g"), KeyMatch::Action(Action::End)));
+        assert!(matches!(lookup(&keymap, "q"), KeyMatch::Unbound));
+    }
+
+    #[test]
+    fn config_overrides_and_unbinds_defaults() {
+        let config = HashMap::from([
+            ("g r".to_string(), Action::Refresh),
+            ("F1".to_string(), Action::None),
+            ("q".to_string(), Action::Quit),
+        ]);
+        let keymap = Keymap::new(&config).unwrap();
+        assert!(matches!(lookup(&keymap, "g r"), KeyMatch::Action(Action::Refresh)));
+        assert!(matches!(lookup(&keymap, "g g"), KeyMatch::Action(Action::Home)));
+        assert!(matches!(lookup(&keymap, "F1"), KeyMatch::Unbound));
+        assert!(matches!(lookup(&keymap, "q"), KeyMatch::Action(Action::Quit)));
+        assert_eq!(keymap.function_key(1, KeyModifiers::NONE), None);
+        assert_eq!(keymap.function_key(5, KeyModifiers::NONE), Some(Action::Copy));
+
+        let invalid = HashMap::from([("hyper+x".to_string(), Action::Quit)]);
+        assert!(Keymap::new(&invalid).is_err());
+    }
+
+    #[test]
+    fn action_names() {
+        assert_eq!("copy".parse(), Ok(Action::Copy));
+        assert_eq!("columns_auto".parse(), Ok(Action::ListColumns(0)));
+        for count in 1..=9 {
+            assert_eq!(format!("columns_{}", count).parse(), Ok(Action::ListColumns(count)));
+        }
+        for invalid in ["columns_0", "columns_10", "columns_", "columns_x", "columns", "Copy", "unknown"] {
+            assert!(invalid.parse::<Action>().is_err(), "{}", invalid);
+        }
+        for (name, action) in Action::NAMED {
+            assert_eq!(name.parse(), Ok(action));
+        }
+    }
+}
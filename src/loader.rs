@@ -0,0 +1,153 @@
+use std::fs::{self, ReadDir};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fms::{self, FmsError, PathKind};
+
+/// 読み込んだファイルを一覧に送る間隔
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 読み込みのスレッドから送る内容
+///
+/// # バリアント
+/// * `Entries` - 読み込んだファイル（一覧と同じ順に並べ替え済み）
+/// * `Done` - 読み込みが終わった（読み込めなかったファイルとエラー）
+enum LoadEvent {
+    Entries(Vec<PathKind>),
+    Done(Vec<(PathBuf, FmsError)>),
+}
+
+/// ディレクトリのファイル一覧の、バックグラウンドでの読み込み
+/// ファイルの多いディレクトリでも画面が止まらないように、読み込んだ分から一覧に追加する
+///
+/// # フィールド
+/// * `receiver` - 読み込みのスレッドから送られた内容
+/// * `cancelled` - 読み込みを中止するかどうか（スレッドと共有する）
+/// * `loaded` - 受け取ったファイル数
+/// * `errors` - 読み込みが終わった場合の、読み込めなかったファイルとエラー
+pub struct DirLoader {
+    receiver: Receiver<LoadEvent>,
+    cancelled: Arc<AtomicBool>,
+    loaded: usize,
+    errors: Option<Vec<(PathBuf, FmsError)>>,
+}
+
+impl DirLoader {
+    /// ディレクトリの読み込みを開始する
+    /// ディレクトリを開くところまでは呼び出し元で行い、開けない場合はエラーを返します
+    ///
+    /// # 引数
+    /// * `dir` - 読み込むディレクトリ
+    pub fn start(dir: &Path) -> fms::Result<DirLoader> {
+        if !dir.is_dir() {
+            return Err(FmsError::NotADirectory(dir.to_path_buf()));
+        }
+        let entries = fs::read_dir(dir)?;
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let shared = Arc::clone(&cancelled);
+        let dir = dir.to_path_buf();
+        thread::spawn(move || load(dir, entries, &sender, &shared));
+        Ok(DirLoader { receiver, cancelled, loaded: 0, errors: None })
+    }
+
+    /// 読み込んだファイルを、並び順を保ったままファイル一覧に追加する
+    ///
+    /// # 引数
+    /// * `files` - 追加するファイル一覧（並べ替え済み）
+    ///
+    /// # 戻り値
+    /// `bool` - 追加したファイルがあるかどうか
+    pub fn merge_into(&mut self, files: &mut Vec<PathKind>) -> bool {
+        let mut merged = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(LoadEvent::Entries(entries)) => {
+                    self.loaded += entries.len();
+                    merge_sorted(files, entries);
+                    merged = true;
+                }
+                Ok(LoadEvent::Done(errors)) => self.errors = Some(errors),
+                Err(TryRecvError::Empty) => break,
+                // スレッドが中断された場合も、読み込みを終える
+                Err(TryRecvError::Disconnected) => {
+                    self.errors.get_or_insert_with(Vec::new);
+                    break;
+                }
+            }
+        }
+        merged
+    }
+
+    /// 受け取ったファイル数を取得する
+    pub fn loaded(&self) -> usize {
+        self.loaded
+    }
+
+    /// 読み込みが終わった場合に、読み込めなかったファイルとエラーを取得する
+    ///
+    /// # 戻り値
+    /// `Option<Vec<(PathBuf, FmsError)>>` - エラー（読み込み中の場合は `None`）
+    pub fn take_finished(&mut self) -> Option<Vec<(PathBuf, FmsError)>> {
+        self.errors.take()
+    }
+}
+
+impl Drop for DirLoader {
+    /// 別のディレクトリに移動した場合なども、読み込みのスレッドを止める
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// ディレクトリのファイルを読み込んで、一定の間隔でまとめて送る
+/// 並べ替えもこのスレッドで行い、読み込めないファイルは飛ばして最後にまとめて送ります
+fn load(dir: PathBuf, entries: ReadDir, sender: &Sender<LoadEvent>, cancelled: &AtomicBool) {
+    let mut batch = Vec::new();
+    let mut errors = Vec::new();
+    let mut last_sent = Instant::now();
+    for (index, entry) in entries.enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        // 親ディレクトリ（..）が0番
+        match entry {
+            Ok(entry) => match fms::entry_information(&entry.path(), index + 1) {
+                Ok(file) => batch.push(file),
+                Err(e) => errors.push((entry.path(), e)),
+            },
+            Err(e) => errors.push((dir.clone(), e.into())),
+        }
+        if last_sent.elapsed() >= BATCH_INTERVAL {
+            fms::sort_file_list(&mut batch);
+            if sender.send(LoadEvent::Entries(std::mem::take(&mut batch))).is_err() {
+                return;
+            }
+            last_sent = Instant::now();
+        }
+    }
+    fms::sort_file_list(&mut batch);
+    let _ = sender.send(LoadEvent::Entries(batch));
+    let _ = sender.send(LoadEvent::Done(errors));
+}
+
+/// 並べ替え済みの2つのファイル一覧を、並び順を保ったまま1つにする
+fn merge_sorted(files: &mut Vec<PathKind>, entries: Vec<PathKind>) {
+    if entries.is_empty() {
+        return;
+    }
+    let existing = std::mem::take(files);
+    files.reserve(existing.len() + entries.len());
+    let mut existing = existing.into_iter().peekable();
+    let mut entries = entries.into_iter().peekable();
+    while let (Some(a), Some(b)) = (existing.peek(), entries.peek()) {
+        let next = if fms::compare_file_order(a, b).is_le() { existing.next() } else { entries.next() };
+        files.extend(next);
+    }
+    files.extend(existing);
+    files.extend(entries);
+}
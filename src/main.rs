@@ -1,73 +1,249 @@
 
-use std::{error::Error, io, path::Path};
+use std::{error::Error, io, process::ExitStatus, time::Duration};
 
-use fms::PathKind;
+use operation::OperationKind;
 use ratatui::{
     backend::{Backend, CrosstermBackend}, crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+        cursor::Show,
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
         execute,
-        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    }, layout::Alignment, style::{Color, Style}, Terminal
+        terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen, LeaveAlternateScreen},
+    }, layout::Alignment, Terminal
 };
 
 
 mod app;
 use crate::app::App;
 
+mod archive;
+
 mod header;
-use crate::header::HeaderWidget;
 
 mod body;
 
+mod cli;
+use crate::cli::Cli;
+
+mod column;
+
+mod config;
+use crate::config::{Config, ListColumns};
+
 mod footer;
-use crate::footer::FooterWidget;
 
-mod ui;
-use crate::ui::ui;
+mod dialog;
+
+mod dirsize;
 
 mod fms;
 
+mod format;
+
+mod fsinfo;
+
+mod i18n;
+use crate::i18n::{tr, Msg};
+
+mod job;
+
+mod keymap;
+use crate::keymap::Action;
+
+mod loader;
+
+mod opener;
+
+mod operation;
+
+mod permission;
+
+mod sftp;
+
+mod shell;
+use crate::shell::ExternalCommand;
+
+mod theme;
+
+mod usage;
+
 mod utils;
 
+mod vfs;
+
+mod viewer;
+
+mod watch;
+
 fn main() -> Result<(), Box<dyn Error>> {
 
     ///////////////////////////////
     // Application pre-run steps
 
+    // コマンドライン引数の解析
+    let options = match Cli::parse() {
+        Ok(Cli::Run(options)) => options,
+        Ok(Cli::Help) => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(Cli::Version) => {
+            println!("one {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Ok(Cli::Init(wrapper)) => {
+            print!("{}", wrapper);
+            return Ok(());
+        }
+        Err(message) => {
+            eprint!("error: {}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let dir_path = options.dir.to_str()
+        .ok_or_else(|| format!("{}: the path is not valid UTF-8", options.dir.display()))?;
+
+    // 設定ファイルの読み込みとアプリケーションの初期化
+    // （設定のエラーの場合は画面を初期化する前に終了する）
+    let config = Config::load()?;
+    i18n::init(config.language);
+    format::init(&config.format)?;
+    let app = App::new(dir_path, config)?;
+    if let Some(url) = &options.remote {
+        app.open_remote(url);
+    }
+
     // Initialize the terminal
-    enable_raw_mode()?;
-    let mut stderr = io::stderr();
-    execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
+    // 修飾キー単体の押下・解放を受け取れる端末では、キーボード拡張を有効にする
+    // （Shift・Ctrl・Altを押している間、フッターに組み合わせの割り当てを表示するため）
+    let keyboard_enhancement = supports_keyboard_enhancement().unwrap_or(false);
+    enter_terminal(keyboard_enhancement)?;
+    let stderr = io::stderr();
 
     let backend = CrosstermBackend::new(stderr);
     let mut terminal = Terminal::new(backend)?;
 
     ///////////////////////////////
     // Run the app
-    // let mut app = App::new(".")?;
-    let mut app = App::new("c:/xampp/php")?;
-    let res = run_app(&mut terminal, &app);
+    let res = run_app(&mut terminal, &app, keyboard_enhancement);
 
     ///////////////////////////////
     // Application post-run steps
     // (boilerplate code)
 
     // restore terminal state
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture,
-    )?;
+    leave_terminal(keyboard_enhancement)?;
 
     terminal.show_cursor()?;
 
+    // 作成途中のアーカイブが残らないように、バックグラウンドの処理の終了を待つ
+    if app.has_jobs() {
+        eprintln!("{}", tr(Msg::WaitingForJobs));
+        for error in app.wait_jobs() {
+            eprintln!("{}", error);
+        }
+    }
+
+    res?;
+
+    // シェルのラッパー関数が移動できるように、最後のディレクトリを書き込む
+    options.write_last_dir(&app.dir_path())?;
     Ok(())
 
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &App) -> io::Result<()> {
+/// 画面を全画面表示に切り替えて、キー・マウスの入力を受け取れるようにする
+///
+/// # 引数
+/// * `keyboard_enhancement` - キーボード拡張を有効にするかどうか
+fn enter_terminal(keyboard_enhancement: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+    if keyboard_enhancement {
+        execute!(io::stderr(), PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+        ))?;
+    }
+    Ok(())
+}
+
+/// 画面を元のシェルの表示に戻す
+///
+/// # 引数
+/// * `keyboard_enhancement` - 有効にしたキーボード拡張を元に戻すかどうか
+fn leave_terminal(keyboard_enhancement: bool) -> io::Result<()> {
+    if keyboard_enhancement {
+        execute!(io::stderr(), PopKeyboardEnhancementFlags)?;
+    }
+    disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture, Show)
+}
+
+/// 端末を一時停止して外部コマンドを実行し、終了後に画面を元に戻す
+///
+/// # 引数
+/// * `terminal` - 端末
+/// * `command` - 実行する外部コマンド
+/// * `keyboard_enhancement` - キーボード拡張が有効かどうか
+///
+/// # 戻り値
+/// `io::Result<io::Result<ExitStatus>>` - 外側は端末の切り替えのエラー、内側はコマンドの終了ステータス
+fn run_external<B: Backend>(terminal: &mut Terminal<B>, command: &ExternalCommand, keyboard_enhancement: bool)
+    -> io::Result<io::Result<ExitStatus>> {
+    leave_terminal(keyboard_enhancement)?;
+    let status = command.command().status();
+    if command.pause {
+        // 出力を確認できるように、Enterキーが押されるまで待つ
+        let status_line = match &status {
+            Ok(status) if !status.success() => format!("{}\n", tr(Msg::CommandFailed(status.to_string()))),
+            _ => String::new(),
+        };
+        eprint!("\n{}{} ", status_line, tr(Msg::PressEnterToReturn));
+        io::stdin().read_line(&mut String::new())?;
+    }
+    enter_terminal(keyboard_enhancement)?;
+    // 外部コマンドが描画した内容が残らないように、次の描画で画面全体を描き直す
+    terminal.clear()?;
+    Ok(status)
+}
+
+/// バックグラウンドの処理の実行中に、進行状況を描き直す間隔
+const JOB_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 入力がない間に、ディレクトリの変更を一覧に反映する間隔
+const WATCH_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &App, keyboard_enhancement: bool) -> io::Result<()> {
     loop {
+        // 終了したバックグラウンドの処理の結果を反映する
+        app.poll_jobs();
+
+        // 接続・認証が終わったら、確認・入力のダイアログを表示するか一覧に表示する
+        app.poll_connecting();
+
+        // 出力をビューアに表示する外部コマンドが終了したら、ビューアを表示する
+        app.poll_captured_command();
+
+        // バックグラウンドで読み込んだファイルを一覧に追加する
+        app.poll_loading();
+
+        // 計算が終わったディレクトリのサイズを反映する
+        app.poll_dir_sizes();
+
+        // 使用量のスキャンが終わったら使用量ビューを表示する
+        app.poll_usage_scan();
+
+        // 監視しているディレクトリの変更を一覧に反映する
+        app.poll_changes();
+
+        // 操作によって予約された外部コマンドを、端末を一時停止して実行する
+        if let Some(command) = app.take_external_command() {
+            let status = run_external(terminal, &command, keyboard_enhancement)?;
+            app.finish_external_command(&command, status);
+        }
+
         terminal.draw(|f| {
             let area = f.area();
             const MIN_WITH: u16 = 90;
@@ -75,8 +251,8 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &App) -> io::Result<()>
 
             if area.width < MIN_WITH || area.height < MIN_HEIGHT {
                 let warning = ratatui::widgets::Paragraph::new(
-                    format!("表示可能なターミナルの最小サイズは(W x H)は{}x{}です。", MIN_WITH, MIN_HEIGHT))
-                    .style(Style::default().fg(Color::Red))
+                    tr(Msg::TerminalTooSmall(MIN_WITH, MIN_HEIGHT)))
+                    .style(app.theme().warning)
                     .alignment(Alignment::Center);
                 
                 f.render_widget(warning, area);                
@@ -86,73 +262,207 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &App) -> io::Result<()>
             app.draw(f)
         })?;
 
-        // Event handling
+        // バックグラウンドの処理の進行状況やディレクトリの変更を描き直すために、入力を待つ時間を区切る
+        let busy = app.has_jobs()
+            || app.is_connecting()
+            || app.is_running_command()
+            || app.is_loading()
+            || app.is_measuring()
+            || app.is_scanning();
+        let timeout = if busy { JOB_REFRESH_INTERVAL } else { WATCH_REFRESH_INTERVAL };
+        if !event::poll(timeout)? {
+            continue;
+        }
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Release {
-                continue;
-            }
+        // Event handling
+        match event::read()? {
+            Event::Key(key) => {
+                // 修飾キー単体の押下・解放は、フッターの表示の切り替えにのみ使う
+                if let KeyCode::Modifier(modifier) = key.code {
+                    app.set_modifier_held(modifier, key.kind != event::KeyEventKind::Release);
+                    continue;
+                }
+                if key.kind == event::KeyEventKind::Release {
+                    continue;
+                }
+                app.sync_modifiers(key);
 
-            match key.code {
-                KeyCode::F(1) => {
-                    return Ok(());
+                // ダイアログ表示中はダイアログでキー入力を処理する
+                if app.has_dialog() {
+                    app.handle_dialog_key(key);
+                    continue;
                 }
-                KeyCode::Down => {
-                    let current_focus_no = app.focus_file_no();
-                    if current_focus_no < app.file_list().len() - 1 {
-                        app.set_focus_file_no(current_focus_no + 1);
-                    }
+
+                // ビューア表示中はビューアでキー入力を処理する
+                if app.has_viewer() {
+                    app.handle_viewer_key(key);
+                    continue;
                 }
-                KeyCode::Up => {
-                    let current_focus_no = app.focus_file_no();
-                    if current_focus_no > 0 {
-                        app.set_focus_file_no(current_focus_no - 1);
-                    }
+
+                // 使用量ビュー表示中は使用量ビューでキー入力を処理する
+                if app.has_usage() {
+                    app.handle_usage_key(key);
+                    continue;
                 }
-                KeyCode::Right => {
-                    // 1画面2分割されてるので、左側にフォーカスがある場合は、行数を足して、右横に移動する
-                    // 右横に移動した場合は、先頭にフォーカスを当てる
-                    let current_focus_no = app.focus_file_no();
-                    let max_file_count_in_page = app.max_files_per_page();
-                    let max_row_count = max_file_count_in_page / 2;
-                    let mut next_focus = current_focus_no + max_row_count;
-
-                    next_focus = next_focus / max_row_count * max_row_count;
-                    
-                    // 全体のファイル数を超えないようにする
-                    if next_focus < app.file_list().len() {
-                        app.set_focus_file_no(next_focus); 
-                    }
+
+                // 接続中・外部コマンドの実行中・一覧の読み込み中・使用量のスキャン中は、Escで中止する
+                if key.code == KeyCode::Esc
+                    && (app.cancel_connecting()
+                        || app.cancel_captured_command()
+                        || app.cancel_loading()
+                        || app.cancel_usage_scan())
+                {
+                    continue;
                 }
-                KeyCode::Left => {
-                    // 1画面2分割されてるので、右側にフォーカスがある場合は、行数を引いて、左横に移動する
-                    // 左横に移動した場合は、先頭にフォーカスを当てる
-                    let max_row_count = app.max_files_per_page() / 2;
-                    let mut next_focus: usize;
-
-                    // これ以上左に進めない場合はなにもしない
-                    let current_focus_no = app.focus_file_no();
-                    if current_focus_no >= max_row_count {
-                        next_focus = current_focus_no - max_row_count;
-                        next_focus = next_focus / max_row_count * max_row_count;
-                        app.set_focus_file_no(next_focus);
+
+                if let Some(action) = app.resolve_key(key) {
+                    if handle_action(app, action) {
+                        return Ok(());
                     }
                 }
-                KeyCode::Char(' ') | KeyCode::Enter => {
-                    if let PathKind::DIR(dir) = app.focused_file() {
-                        let new_dir_path = if dir.file_name == ".." {
-                            Path::new(&app.dir_path()).parent().unwrap().to_path_buf()
-                        } else {
-                            Path::new(&app.dir_path()).join(&dir.file_name)
-                        };
-                        let new_dir_path = new_dir_path.to_str().unwrap();
-                        app.set_dir_path(&new_dir_path);
+            }
+            Event::Mouse(mouse) => {
+                // フッターのファンクションキーがクリックされた場合は、割り当てられた操作を実行する
+                if let Some(action) = app.handle_mouse(mouse) {
+                    if handle_action(app, action) {
+                        return Ok(());
                     }
                 }
+            }
+            _ => {}
+        }
+    }    
+}
 
-                _ => {}
+/// 操作を実行する
+///
+/// # 引数
+/// * `app` - アプリケーション
+/// * `action` - キーに割り当てられた操作
+///
+/// # 戻り値
+/// `bool` - アプリケーションを終了する場合は `true`
+fn handle_action(app: &App, action: Action) -> bool {
+    match action {
+        Action::Quit => {
+            return true;
+        }
+        Action::Down => {
+            let current_focus_no = app.focus_file_no();
+            if current_focus_no < app.file_list().len() - 1 {
+                app.set_focus_file_no(current_focus_no + 1);
             }
         }
+        Action::Up => {
+            let current_focus_no = app.focus_file_no();
+            if current_focus_no > 0 {
+                app.set_focus_file_no(current_focus_no - 1);
+            }
+        }
+        Action::Right => {
+            // 1画面が複数列に分割されてるので、行数を足して、右横の列に移動する
+            // 右横に移動した場合は、先頭にフォーカスを当てる
+            // 列の先頭は、画面の先頭に表示しているファイル（1行ずつスクロールする場合はページの区切りとは限らない）から数える
+            let current_focus_no = app.focus_file_no();
+            let max_row_count = app.rows_per_column();
+            // 一覧をまだ描画していない（端末が小さい）場合は、列の行数が決まっていない
+            if max_row_count == 0 {
+                return false;
+            }
+            let offset = app.list_offset();
+            let column = current_focus_no.saturating_sub(offset) / max_row_count;
+            let next_focus = offset + (column + 1) * max_row_count;
 
-    }    
+            // 全体のファイル数を超えないようにする
+            if next_focus < app.file_list().len() {
+                app.set_focus_file_no(next_focus); 
+            }
+        }
+        Action::Left => {
+            // 1画面が複数列に分割されてるので、行数を引いて、左横の列に移動する
+            // 左横に移動した場合は、先頭にフォーカスを当てる
+            let max_row_count = app.rows_per_column();
+            if max_row_count == 0 {
+                return false;
+            }
+            let offset = app.list_offset();
+            let column = app.focus_file_no().saturating_sub(offset) / max_row_count;
+            let column_start = offset + column * max_row_count;
+
+            // これ以上左に進めない場合はなにもしない
+            if column_start > 0 {
+                app.set_focus_file_no(column_start.saturating_sub(max_row_count));
+            }
+        }
+        Action::PageDown => {
+            app.move_focus(app.max_files_per_page() as isize);
+        }
+        Action::PageUp => {
+            app.move_focus(-(app.max_files_per_page() as isize));
+        }
+        Action::Home => {
+            app.set_focus_file_no(0);
+        }
+        Action::End => {
+            app.set_focus_file_no(app.count_file_list().saturating_sub(1));
+        }
+        Action::ToggleScroll => {
+            app.toggle_scroll_mode();
+        }
+        Action::ListColumns(count) => {
+            // 0は画面の幅に合わせた自動、1～9は列数の指定
+            let list_columns = match count {
+                0 => ListColumns::Auto,
+                count => ListColumns::Fixed(count),
+            };
+            app.set_list_columns(list_columns);
+        }
+        Action::Enter => {
+            app.open_focused();
+        }
+        Action::OpenWith => {
+            app.start_open_with();
+        }
+        Action::View => {
+            app.open_viewer();
+        }
+        Action::Exec => {
+            app.start_exec();
+        }
+        Action::Subshell => {
+            app.start_subshell();
+        }
+        Action::ToggleMark => {
+            app.toggle_mark();
+        }
+        Action::Copy => {
+            app.start_file_operation(OperationKind::Copy);
+        }
+        Action::Move => {
+            app.start_file_operation(OperationKind::Move);
+        }
+        Action::Attribute => {
+            app.start_attribute_change();
+        }
+        Action::Pack => {
+            app.start_pack();
+        }
+        Action::Goto => {
+            app.start_goto();
+        }
+        Action::Refresh => {
+            app.refresh();
+        }
+        Action::DirSize => {
+            app.measure_dir_sizes();
+        }
+        Action::DiskUsage => {
+            app.start_usage_scan();
+        }
+        Action::ToggleLayout => {
+            app.toggle_layout();
+        }
+        Action::None => {}
+    }
+    false
 }
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::shell;
+
+/// ファイルを開くプログラムの規則
+/// 設定ファイルの `[[open]]` で指定し、上から順に照合する
+///
+/// ```toml
+/// [[open]]
+/// name = "editor"
+/// mime = ["text/*"]
+/// command = "${EDITOR:-vi} {}"
+///
+/// [[open]]
+/// extensions = ["png", "jpg", "pdf"]
+/// command = "xdg-open {}"
+/// background = true
+/// ```
+///
+/// # フィールド
+/// * `name` - 「プログラムから開く」メニューに表示する名前（省略時はコマンド）
+/// * `extensions` - 対象の拡張子（大文字・小文字を区別しない）
+/// * `mime` - 対象のMIMEタイプ（`text/*` のようにサブタイプを省略できる）
+/// * `command` - 実行するコマンド（`{}` はファイルのパスに置き換え、ない場合は末尾に付け加える）
+/// * `background` - 端末から切り離して実行するかどうか（GUIのプログラム向け）
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenRule {
+    pub name: Option<String>,
+    pub extensions: Vec<String>,
+    pub mime: Vec<String>,
+    pub command: String,
+    pub background: bool,
+}
+
+impl OpenRule {
+    /// 既定の規則
+    /// テキストはエディタ・ページャ、それ以外はデスクトップ環境の既定のプログラムで開く
+    pub fn defaults() -> Vec<OpenRule> {
+        let system_open = if cfg!(windows) {
+            "start \"\" {}"
+        } else if cfg!(target_os = "macos") {
+            "open {}"
+        } else {
+            "xdg-open {}"
+        };
+        vec![
+            OpenRule {
+                name: Some("editor".to_string()),
+                mime: vec!["text/*".to_string()],
+                command: if cfg!(windows) { "notepad {}" } else { "${EDITOR:-vi} {}" }.to_string(),
+                ..Default::default()
+            },
+            OpenRule {
+                name: Some("pager".to_string()),
+                mime: vec!["text/*".to_string()],
+                command: if cfg!(windows) { "more < {}" } else { "${PAGER:-less} {}" }.to_string(),
+                ..Default::default()
+            },
+            OpenRule {
+                name: Some("system".to_string()),
+                command: system_open.to_string(),
+                background: true,
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// メニューに表示する名前を取得する
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.command),
+            None => self.command.clone(),
+        }
+    }
+
+    /// ファイルが規則の対象かどうかを判定する
+    /// 拡張子・MIMEタイプのどちらも指定されていない規則はすべてのファイルが対象です
+    fn matches(&self, extension: &str, mime: &str) -> bool {
+        if self.extensions.is_empty() && self.mime.is_empty() {
+            return true;
+        }
+        self.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+            || self.mime.iter().any(|pattern| mime_matches(pattern, mime))
+    }
+
+    /// ファイルのパスを埋め込んだコマンドラインを取得する
+    pub fn command_line(&self, path: &Path) -> String {
+        let quoted = shell::quote(&path.to_string_lossy());
+        if self.command.contains("{}") {
+            self.command.replace("{}", &quoted)
+        } else {
+            format!("{} {}", self.command, quoted)
+        }
+    }
+}
+
+/// MIMEタイプがパターン（`*`、`text/*`、`image/png`）に一致するかを判定する
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        _ if pattern == "*" => true,
+        Some(top) => mime.split('/').next() == Some(top),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+/// ファイルに一致する規則を取得する
+///
+/// # 引数
+/// * `rules` - 規則（照合する順）
+/// * `path` - ファイルのパス
+///
+/// # 戻り値
+/// `Vec<&OpenRule>` - 一致した規則（優先順）
+pub fn matching_rules<'a>(rules: &'a [OpenRule], path: &Path) -> Vec<&'a OpenRule> {
+    let extension = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    let mime = detect_mime(path);
+    rules.iter().filter(|rule| rule.matches(&extension, mime)).collect()
+}
+
+/// ファイルの先頭のバイト列（マジックナンバー）からMIMEタイプを判定する
+/// 該当しない場合は、UTF-8として読めるものを `text/plain`、それ以外を `application/octet-stream` とします
+pub fn detect_mime(path: &Path) -> &'static str {
+    let mut head = Vec::with_capacity(512);
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(512).read_to_end(&mut head);
+    }
+    mime_from_bytes(&head)
+}
+
+/// バイト列からMIMEタイプを判定する
+fn mime_from_bytes(head: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 17] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"BZh", "application/x-bzip2"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"MZ", "application/x-msdownload"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    match head {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => return "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => return "audio/wav",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => return "video/mp4",
+        _ => {}
+    }
+    if head.get(257..262) == Some(b"ustar") {
+        return "application/x-tar";
+    }
+
+    // 末尾で途切れたマルチバイト文字は無視して、UTF-8として読めるかを判定する
+    let text = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if text && !head.contains(&0) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// テスト用の規則
+    fn rule(name: &str, extensions: &[&str], mime: &[&str]) -> OpenRule {
+        OpenRule {
+            name: Some(name.to_string()),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            mime: mime.iter().map(|m| m.to_string()).collect(),
+            command: name.to_string(),
+            background: false,
+        }
+    }
+
+    #[test]
+    fn mime_from_bytes_reads_magic_numbers() {
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        let cases: [(&[u8], &str); 12] = [
+            (b"\x89PNG\r\n\x1a\n....", "image/png"),
+            (b"\xff\xd8\xff\xe0", "image/jpeg"),
+            (b"%PDF-1.7", "application/pdf"),
+            (b"PK\x03\x04\x14\x00", "application/zip"),
+            (b"\x1f\x8b\x08\x00", "application/gzip"),
+            (b"RIFF\x00\x00\x00\x00WEBPVP8 ", "image/webp"),
+            (b"RIFF\x00\x00\x00\x00WAVEfmt ", "audio/wav"),
+            (b"\x00\x00\x00\x18ftypmp42", "video/mp4"),
+            (&tar, "application/x-tar"),
+            (b"hello, world\n", "text/plain"),
+            (b"", "text/plain"),
+            (b"\x00\x01\x02\x03", "application/octet-stream"),
+        ];
+        for (head, expected) in cases {
+            assert_eq!(mime_from_bytes(head), expected, "{:?}", head);
+        }
+    }
+
+    #[test]
+    fn mime_from_bytes_accepts_utf8_cut_at_the_end() {
+        let text = "日本語".as_bytes();
+        assert_eq!(mime_from_bytes(&text[..text.len() - 1]), "text/plain");
+        assert_eq!(mime_from_bytes(b"abc\xffdef"), "application/octet-stream");
+    }
+
+    #[test]
+    fn mime_matches_wildcards() {
+        assert!(mime_matches("*", "image/png"));
+        assert!(mime_matches("text/*", "text/plain"));
+        assert!(!mime_matches("text/*", "application/json"));
+        assert!(!mime_matches("text/*", "textual/plain"));
+        assert!(mime_matches("image/PNG", "image/png"));
+        assert!(!mime_matches("image/png", "image/jpeg"));
+    }
+
+    #[test]
+    fn matching_rules_keeps_the_rule_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let image = temp.path().join("photo.JPG");
+        fs::write(&image, b"\xff\xd8\xff\xe0").unwrap();
+        let text = temp.path().join("notes");
+        fs::write(&text, b"plain text\n").unwrap();
+
+        let rules = vec![
+            rule("viewer", &[".jpg", "png"], &[]),
+            rule("editor", &[], &["text/*"]),
+            rule("images", &[], &["image/*"]),
+            rule("any", &[], &[]),
+        ];
+        let names = |path: &Path| -> Vec<String> {
+            matching_rules(&rules, path).iter().map(|rule| rule.command.clone()).collect()
+        };
+        assert_eq!(names(&image), ["viewer", "images", "any"]);
+        assert_eq!(names(&text), ["editor", "any"]);
+    }
+
+    #[test]
+    fn command_line_places_the_quoted_path() {
+        let path = Path::new("/tmp/it's here.txt");
+        let with_placeholder = OpenRule { command: "less -R {} | cat".to_string(), ..Default::default() };
+        let without_placeholder = OpenRule { command: "xdg-open".to_string(), ..Default::default() };
+        if cfg!(windows) {
+            assert_eq!(without_placeholder.command_line(path), "xdg-open \"/tmp/it's here.txt\"");
+        } else {
+            assert_eq!(with_placeholder.command_line(path), r"less -R '/tmp/it'\''s here.txt' | cat");
+            assert_eq!(without_placeholder.command_line(path), r"xdg-open '/tmp/it'\''s here.txt'");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fms::{self, FmsError, PathKind};
+use crate::i18n::{tr, Msg};
+
+/// ファイル操作種別列挙型
+///
+/// # バリアント
+/// * `Copy` - コピー
+/// * `Move` - 移動
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationKind {
+    Copy,
+    Move,
+}
+
+impl OperationKind {
+    /// 操作の表示名を取得する
+    pub fn label(&self) -> String {
+        tr(Msg::Operation(*self))
+    }
+}
+
+/// 競合（コピー先に同名のファイルが存在する場合）の解決方法
+///
+/// # バリアント
+/// * `Overwrite` - 上書きする
+/// * `Skip` - スキップする
+/// * `Rename` - 名前に連番を付けてコピーする
+/// * `OverwriteIfNewer` - コピー元の方が新しい場合のみ上書きする
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictAction {
+    Overwrite,
+    Skip,
+    Rename,
+    OverwriteIfNewer,
+}
+
+impl ConflictAction {
+    /// ダイアログに表示する順番
+    pub const ALL: [ConflictAction; 4] = [
+        ConflictAction::Overwrite,
+        ConflictAction::Skip,
+        ConflictAction::Rename,
+        ConflictAction::OverwriteIfNewer,
+    ];
+
+    /// 解決方法の表示名を取得する
+    pub fn label(&self) -> String {
+        tr(Msg::Conflict(*self))
+    }
+
+    /// 解決方法を選択するキーを取得する
+    pub fn key(&self) -> char {
+        match self {
+            ConflictAction::Overwrite => 'O',
+            ConflictAction::Skip => 'S',
+            ConflictAction::Rename => 'R',
+            ConflictAction::OverwriteIfNewer => 'N',
+        }
+    }
+}
+
+/// ファイル操作の進行状況
+///
+/// # バリアント
+/// * `Conflict` - 競合が発生したため、解決方法の指定を待っている
+/// * `Finished` - すべてのファイルの処理が終了した
+pub enum OperationStatus {
+    Conflict { src: Box<PathKind>, dest: Box<PathKind> },
+    Finished,
+}
+
+/// コピー・移動操作の状態管理構造体
+/// 競合が発生した時点で処理を中断し、解決方法が指定されたら再開する
+///
+/// # フィールド
+/// * `kind` - 操作種別
+/// * `sources` - 操作対象のパス一覧
+/// * `dest_dir` - コピー・移動先のディレクトリ
+/// * `next_index` - 次に処理する対象のインデックス
+/// * `action_for_all` - 「すべてに適用」が指定された解決方法
+/// * `errors` - 処理中に発生したエラー（対象のパスとエラー）
+pub struct FileOperation {
+    kind: OperationKind,
+    sources: Vec<PathBuf>,
+    dest_dir: PathBuf,
+    next_index: usize,
+    action_for_all: Option<ConflictAction>,
+    errors: Vec<(PathBuf, FmsError)>,
+}
+
+impl FileOperation {
+    /// 新しいFileOperationインスタンスを返します。
+    pub fn new(kind: OperationKind, sources: Vec<PathBuf>, dest_dir: PathBuf) -> FileOperation {
+        FileOperation {
+            kind,
+            sources,
+            dest_dir,
+            next_index: 0,
+            action_for_all: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// 操作種別を取得する
+    pub fn kind(&self) -> OperationKind {
+        self.kind
+    }
+
+    /// 処理中に発生したエラーを取得する
+    pub fn errors(&self) -> &[(PathBuf, FmsError)] {
+        &self.errors
+    }
+
+    /// 競合が発生するか、すべての処理が終わるまで処理を進める
+    ///
+    /// # 戻り値
+    /// `OperationStatus` - 処理の進行状況
+    pub fn run(&mut self) -> OperationStatus {
+        while let Some(src) = self.sources.get(self.next_index).cloned() {
+            let Some(file_name) = src.file_name() else {
+                self.next_index += 1;
+                continue;
+            };
+            let dest = self.dest_dir.join(file_name);
+
+            // リンク切れのシンボリックリンクも、存在するファイルとして扱う
+            if fs::symlink_metadata(&dest).is_ok() {
+                match self.action_for_all {
+                    Some(action) => self.apply(&src, &dest, action),
+                    None => {
+                        // リンク先をたどらずに取得する（リンク切れでも競合として確認する）
+                        match (fms::entry_information(&src, 0), fms::entry_information(&dest, 0)) {
+                            (Ok(src), Ok(dest)) => return OperationStatus::Conflict { src: Box::new(src), dest: Box::new(dest) },
+                            (Err(e), _) | (_, Err(e)) => self.record_error(&src, e),
+                        }
+                    }
+                }
+            } else {
+                let result = self.execute(&src, &dest);
+                if let Err(e) = result {
+                    self.record_error(&src, e);
+                }
+            }
+            self.next_index += 1;
+        }
+        OperationStatus::Finished
+    }
+
+    /// 競合中の対象に解決方法を適用して、処理を再開する
+    ///
+    /// # 引数
+    /// * `action` - 解決方法
+    /// * `apply_to_all` - 以降の競合にも同じ解決方法を適用するかどうか
+    ///
+    /// # 戻り値
+    /// `OperationStatus` - 処理の進行状況
+    pub fn resolve(&mut self, action: ConflictAction, apply_to_all: bool) -> OperationStatus {
+        if apply_to_all {
+            self.action_for_all = Some(action);
+        }
+        if let Some(src) = self.sources.get(self.next_index).cloned() {
+            if let Some(file_name) = src.file_name() {
+                let dest = self.dest_dir.join(file_name);
+                self.apply(&src, &dest, action);
+            }
+            self.next_index += 1;
+        }
+        self.run()
+    }
+
+    /// 競合している対象に解決方法を適用する
+    fn apply(&mut self, src: &Path, dest: &Path, action: ConflictAction) {
+        // 同じファイル同士の場合は上書きできないので、名前変更以外はスキップする
+        let same_file = match (src.canonicalize(), dest.canonicalize()) {
+            (Ok(src), Ok(dest)) => src == dest,
+            _ => false,
+        };
+
+        let result = match action {
+            ConflictAction::Skip => Ok(()),
+            ConflictAction::Rename => self.execute(src, &fms::unique_path(dest)),
+            ConflictAction::Overwrite if !same_file => self.overwrite(src, dest),
+            ConflictAction::OverwriteIfNewer if !same_file && is_newer(src, dest) => self.overwrite(src, dest),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.record_error(src, e);
+        }
+    }
+
+    /// コピー先を上書きする
+    /// ディレクトリ同士の場合は中身をマージし（移動の場合はコピーしてから移動元を削除する）、
+    /// 種類が異なる場合やコピー先がシンボリックリンクの場合は、コピー先を削除してから処理する
+    fn overwrite(&self, src: &Path, dest: &Path) -> fms::Result<()> {
+        let is_dir = |path: &Path| fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir());
+        let dest_is_link = fs::symlink_metadata(dest).is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if dest_is_link || is_dir(src) != is_dir(dest) {
+            fms::remove_path(dest)?;
+        } else if is_dir(src) && self.kind == OperationKind::Move {
+            // 空でないディレクトリには名前を変更して移動できない
+            fms::copy_path(src, dest)?;
+            return fms::remove_path(src);
+        }
+        self.execute(src, dest)
+    }
+
+    /// コピーまたは移動を実行する
+    fn execute(&self, src: &Path, dest: &Path) -> fms::Result<()> {
+        match self.kind {
+            OperationKind::Copy => fms::copy_path(src, dest),
+            OperationKind::Move => fms::move_path(src, dest),
+        }
+    }
+
+    /// エラーを記録する
+    fn record_error(&mut self, src: &Path, error: FmsError) {
+        self.errors.push((src.to_path_buf(), error));
+    }
+}
+
+/// コピー元の更新日時がコピー先より新しいかどうかを判定する
+fn is_newer(src: &Path, dest: &Path) -> bool {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    match (modified(src), modified(dest)) {
+        (Some(src), Some(dest)) => src > dest,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_link_at_the_destination_is_a_conflict() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest_dir = temp.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::os::unix::fs::symlink("missing", dest_dir.join("file")).unwrap();
+        let src = temp.path().join("file");
+        fs::write(&src, "new").unwrap();
+
+        let mut operation = FileOperation::new(OperationKind::Copy, vec![src], dest_dir.clone());
+        assert!(matches!(operation.run(), OperationStatus::Conflict { .. }));
+        assert!(operation.errors().is_empty());
+
+        assert!(matches!(operation.resolve(ConflictAction::Overwrite, false), OperationStatus::Finished));
+        assert!(operation.errors().is_empty());
+        assert_eq!(fs::read_to_string(dest_dir.join("file")).unwrap(), "new");
+        assert!(!fs::symlink_metadata(dest_dir.join("file")).unwrap().file_type().is_symlink());
+    }
+
+    #[test]
+    fn move_overwrite_merges_into_a_non_empty_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("a");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("moved.txt"), "moved").unwrap();
+        fs::write(src.join("both.txt"), "from src").unwrap();
+        let dest_dir = temp.path().join("dest");
+        fs::create_dir_all(dest_dir.join("a")).unwrap();
+        fs::write(dest_dir.join("a/kept.txt"), "kept").unwrap();
+        fs::write(dest_dir.join("a/both.txt"), "from dest").unwrap();
+
+        let mut operation = FileOperation::new(OperationKind::Move, vec![src.clone()], dest_dir.clone());
+        assert!(matches!(operation.run(), OperationStatus::Conflict { .. }));
+        assert!(matches!(operation.resolve(ConflictAction::Overwrite, false), OperationStatus::Finished));
+
+        assert!(operation.errors().is_empty(), "{:?}", operation.errors());
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(dest_dir.join("a/moved.txt")).unwrap(), "moved");
+        assert_eq!(fs::read_to_string(dest_dir.join("a/both.txt")).unwrap(), "from src");
+        assert_eq!(fs::read_to_string(dest_dir.join("a/kept.txt")).unwrap(), "kept");
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::Path;
+
+use crate::fms::{self, FmsError};
+
+/// パーミッションを ls 形式（drwxr-xr-x）でフォーマットする
+///
+/// # 引数
+/// * `mode` - ファイル種別を含むモードビット
+///
+/// # 戻り値
+/// `String` - フォーマットされたパーミッション（10文字）
+pub fn format_mode(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+
+    let mut formatted = String::with_capacity(10);
+    formatted.push(file_type);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        formatted.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        formatted.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        formatted.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    formatted
+}
+
+/// パーミッションの指定を解析して、新しいモードビットを返す
+/// 8進数（755）と、chmod と同じ記号形式（u+x,go-w,a=r）の両方に対応する
+///
+/// # 引数
+/// * `spec` - パーミッションの指定
+/// * `current` - 現在のモードビット
+/// * `is_dir` - ディレクトリかどうか（X の判定に使用）
+///
+/// # 戻り値
+/// `fms::Result<u32>` - 新しいモードビット（パーミッション部分のみ）
+pub fn parse_mode(spec: &str, current: u32, is_dir: bool) -> fms::Result<u32> {
+    let spec = spec.trim();
+    let invalid = || FmsError::InvalidMode(spec.to_string());
+
+    if spec.is_empty() {
+        return Err(invalid());
+    }
+    if spec.chars().all(|c| c.is_digit(8)) {
+        return match u32::from_str_radix(spec, 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(mode),
+            _ => Err(invalid()),
+        };
+    }
+
+    let mut mode = current & 0o7777;
+    for clause in spec.split(',') {
+        let mut chars = clause.chars().peekable();
+
+        // 対象（u, g, o, a）
+        let mut who = 0;
+        while let Some(&c) = chars.peek() {
+            who |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => break,
+            };
+            chars.next();
+        }
+        if who == 0 {
+            who = 0o7777;
+        }
+
+        // 演算子と権限の組み合わせを繰り返し適用する（u+x-w など）
+        let mut applied = false;
+        while let Some(op) = chars.next() {
+            if !matches!(op, '+' | '-' | '=') {
+                return Err(invalid());
+            }
+            let mut perms = 0;
+            while let Some(&c) = chars.peek() {
+                perms |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                    'X' => 0,
+                    's' => 0o6000,
+                    't' => 0o1000,
+                    _ => break,
+                };
+                chars.next();
+            }
+            let perms = perms & who;
+            match op {
+                '+' => mode |= perms,
+                '-' => mode &= !perms,
+                _ => mode = (mode & !who) | perms,
+            }
+            applied = true;
+        }
+        if !applied {
+            return Err(invalid());
+        }
+    }
+    Ok(mode)
+}
+
+/// パーミッションを変更する
+///
+/// # 引数
+/// * `path` - 変更するパス
+/// * `spec` - パーミッションの指定（8進数または記号形式）
+/// * `recursive` - ディレクトリの場合に中身も変更するかどうか
+///
+/// # 戻り値
+/// `fms::Result<()>` - 成功した場合は空のタプルを返す
+#[cfg(unix)]
+pub fn change_mode(path: &Path, spec: &str, recursive: bool) -> fms::Result<()> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = fs::symlink_metadata(path)?;
+    // シンボリックリンク自体のパーミッションは変更できないので対象外にする
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    let mode = parse_mode(spec, metadata.permissions().mode(), metadata.is_dir())?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+
+    if recursive && metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            change_mode(&entry?.path(), spec, recursive)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn change_mode(_path: &Path, _spec: &str, _recursive: bool) -> fms::Result<()> {
+    Err(FmsError::ModeUnsupported)
+}
+
+/// 所有者とグループを変更する
+///
+/// # 引数
+/// * `path` - 変更するパス
+/// * `spec` - `所有者`、`所有者:グループ`、`:グループ` のいずれかの形式の指定
+/// * `recursive` - ディレクトリの場合に中身も変更するかどうか
+///
+/// # 戻り値
+/// `fms::Result<()>` - 成功した場合は空のタプルを返す
+#[cfg(unix)]
+pub fn change_owner(path: &Path, spec: &str, recursive: bool) -> fms::Result<()> {
+    use std::fs;
+    use std::os::unix::fs::lchown;
+
+    let (owner, group) = spec.trim().split_once(':').unwrap_or((spec.trim(), ""));
+    let uid = match owner {
+        "" => None,
+        owner => Some(user_id(owner).ok_or_else(|| FmsError::NoSuchUser(owner.to_string()))?),
+    };
+    let gid = match group {
+        "" => None,
+        group => Some(group_id(group).ok_or_else(|| FmsError::NoSuchGroup(group.to_string()))?),
+    };
+
+    lchown(path, uid, gid)?;
+    if recursive && fs::symlink_metadata(path)?.is_dir() {
+        for entry in fs::read_dir(path)? {
+            change_owner(&entry?.path(), spec, recursive)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn change_owner(_path: &Path, _spec: &str, _recursive: bool) -> fms::Result<()> {
+    Err(FmsError::OwnerUnsupported)
+}
+
+#[cfg(unix)]
+pub use unix::{group_id, group_name, user_id, user_name};
+
+/// ユーザー名・グループ名とIDの変換
+/// 一覧表示のたびに問い合わせないよう、結果をキャッシュする
+#[cfg(unix)]
+mod unix {
+    use std::collections::HashMap;
+    use std::ffi::{CStr, CString};
+    use std::sync::Mutex;
+
+    static USER_NAMES: Mutex<Option<HashMap<u32, Option<String>>>> = Mutex::new(None);
+    static GROUP_NAMES: Mutex<Option<HashMap<u32, Option<String>>>> = Mutex::new(None);
+
+    const BUFFER_SIZE: usize = 16 * 1024;
+
+    /// キャッシュから取得し、存在しない場合は問い合わせてキャッシュする
+    fn cached(cache: &Mutex<Option<HashMap<u32, Option<String>>>>, id: u32, lookup: fn(u32) -> Option<String>) -> Option<String> {
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.get_or_insert_with(HashMap::new)
+            .entry(id)
+            .or_insert_with(|| lookup(id))
+            .clone()
+    }
+
+    /// ユーザーIDからユーザー名を取得する
+    pub fn user_name(uid: u32) -> Option<String> {
+        cached(&USER_NAMES, uid, |uid| {
+            let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+            let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+            let mut result = std::ptr::null_mut();
+            let ret = unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+            if ret != 0 || result.is_null() {
+                return None;
+            }
+            Some(unsafe { CStr::from_ptr(passwd.pw_name) }.to_string_lossy().into_owned())
+        })
+    }
+
+    /// グループIDからグループ名を取得する
+    pub fn group_name(gid: u32) -> Option<String> {
+        cached(&GROUP_NAMES, gid, |gid| {
+            let mut group: libc::group = unsafe { std::mem::zeroed() };
+            let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+            let mut result = std::ptr::null_mut();
+            let ret = unsafe { libc::getgrgid_r(gid, &mut group, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+            if ret != 0 || result.is_null() {
+                return None;
+            }
+            Some(unsafe { CStr::from_ptr(group.gr_name) }.to_string_lossy().into_owned())
+        })
+    }
+
+    /// ユーザー名（または数値のID）からユーザーIDを取得する
+    pub fn user_id(name: &str) -> Option<u32> {
+        if let Ok(uid) = name.parse() {
+            return Some(uid);
+        }
+        let name = CString::new(name).ok()?;
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+        let mut result = std::ptr::null_mut();
+        let ret = unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+        (ret == 0 && !result.is_null()).then_some(passwd.pw_uid)
+    }
+
+    /// グループ名（または数値のID）からグループIDを取得する
+    pub fn group_id(name: &str) -> Option<u32> {
+        if let Ok(gid) = name.parse() {
+            return Some(gid);
+        }
+        let name = CString::new(name).ok()?;
+        let mut group: libc::group = unsafe { std::mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; BUFFER_SIZE];
+        let mut result = std::ptr::null_mut();
+        let ret = unsafe { libc::getgrnam_r(name.as_ptr(), &mut group, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+        (ret == 0 && !result.is_null()).then_some(group.gr_gid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_mode_shows_file_type_and_special_bits() {
+        assert_eq!(format_mode(0o040755), "drwxr-xr-x");
+        assert_eq!(format_mode(0o100644), "-rw-r--r--");
+        assert_eq!(format_mode(0o120777), "lrwxrwxrwx");
+        assert_eq!(format_mode(0o104755), "-rwsr-xr-x");
+        assert_eq!(format_mode(0o102745), "-rwxr-Sr-x");
+        assert_eq!(format_mode(0o104644), "-rwSr--r--");
+        assert_eq!(format_mode(0o041777), "drwxrwxrwt");
+        assert_eq!(format_mode(0o041754), "drwxr-xr-T");
+    }
+
+    #[test]
+    fn parse_mode_accepts_octal() {
+        assert_eq!(parse_mode("755", 0o644, false).unwrap(), 0o755);
+        assert_eq!(parse_mode("0644", 0o755, false).unwrap(), 0o644);
+        assert_eq!(parse_mode(" 4755 ", 0, false).unwrap(), 0o4755);
+    }
+
+    #[test]
+    fn parse_mode_applies_symbolic_clauses_in_order() {
+        assert_eq!(parse_mode("u+x,g-w", 0o664, false).unwrap(), 0o744);
+        assert_eq!(parse_mode("go-w", 0o777, false).unwrap(), 0o755);
+        assert_eq!(parse_mode("u+x-w", 0o644, false).unwrap(), 0o544);
+        assert_eq!(parse_mode("o=", 0o755, false).unwrap(), 0o750);
+        // 対象を省略した場合はすべてに適用する
+        assert_eq!(parse_mode("+x", 0o644, false).unwrap(), 0o755);
+    }
+
+    #[test]
+    fn parse_mode_sets_execute_with_capital_x_only_for_directories_or_executables() {
+        assert_eq!(parse_mode("a=rX", 0o644, false).unwrap(), 0o444);
+        assert_eq!(parse_mode("a=rX", 0o644, true).unwrap(), 0o555);
+        assert_eq!(parse_mode("a=rX", 0o744, false).unwrap(), 0o555);
+    }
+
+    #[test]
+    fn parse_mode_sets_special_bits_for_the_chosen_targets() {
+        assert_eq!(parse_mode("+t", 0o755, true).unwrap(), 0o1755);
+        assert_eq!(parse_mode("u+s", 0o755, false).unwrap(), 0o4755);
+        assert_eq!(parse_mode("g+s", 0o755, true).unwrap(), 0o2755);
+        assert_eq!(parse_mode("u-s", 0o6755, false).unwrap(), 0o2755);
+    }
+
+    #[test]
+    fn parse_mode_rejects_invalid_input() {
+        for spec in ["", "   ", "10000", "8", "u", "z+x", "u+q", "u+x,,g+w", "u*x"] {
+            assert!(matches!(parse_mode(spec, 0o644, false), Err(FmsError::InvalidMode(_))), "{:?}", spec);
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use ssh2::{CheckResult, FileStat, HashType, KnownHostFileKind, KnownHosts, Prompt, Session, Sftp};
+
+use crate::fms::{self, FileInformation, FmsError, PathKind};
+use crate::vfs::Vfs;
+
+/// SSHの既定のポート番号
+const DEFAULT_PORT: u16 = 22;
+
+/// 接続を待つ時間
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接続後の操作で応答を待つ時間（ミリ秒）
+const SESSION_TIMEOUT_MS: u32 = 30_000;
+
+/// パスワードを入力できる回数
+const MAX_PASSWORD_ATTEMPTS: u32 = 3;
+
+/// パスワードなしで試す秘密鍵（`~/.ssh` 内）
+const IDENTITY_FILES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+impl From<ssh2::Error> for FmsError {
+    fn from(e: ssh2::Error) -> Self {
+        FmsError::Io(e.into())
+    }
+}
+
+/// `sftp://[user@]host[:port][/path]` 形式のURL
+///
+/// # フィールド
+/// * `user` - ユーザー名（省略時はローカルのユーザー名）
+/// * `host` - ホスト名
+/// * `port` - ポート番号
+/// * `path` - ルートからのリモートのパス（`None` はホームディレクトリ、`~/` で始まる場合はホームディレクトリから）
+#[derive(Clone, Debug)]
+pub struct SftpUrl {
+    pub user: String,
+    pub host: String,
+    pub port: u16,
+    pub path: Option<String>,
+}
+
+impl SftpUrl {
+    /// URLの接頭辞
+    pub const SCHEME: &'static str = "sftp://";
+
+    /// `sftp://` で始まる文字列かどうかを判定する
+    pub fn is_url(text: &str) -> bool {
+        text.get(..SftpUrl::SCHEME.len()).is_some_and(|scheme| scheme.eq_ignore_ascii_case(SftpUrl::SCHEME))
+    }
+
+    /// URLを解析する
+    ///
+    /// # 戻り値
+    /// `Option<SftpUrl>` - 解析したURL（`sftp://` で始まらない場合や、ホスト名・ポート番号が不正な場合は `None`）
+    pub fn parse(text: &str) -> Option<SftpUrl> {
+        if !SftpUrl::is_url(text) {
+            return None;
+        }
+        let rest = &text[SftpUrl::SCHEME.len()..];
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) => (authority, Some(path.trim_end_matches('/').to_string())),
+            None => (rest, None),
+        };
+        let (user, address) = match authority.rsplit_once('@') {
+            Some((user, address)) => (user.to_string(), address),
+            None => (local_user()?, authority),
+        };
+        // IPv6アドレスは [::1]:22 のように括弧で囲む
+        let (host, port) = match address.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed.split_once(']')?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match address.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (address, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse().ok()?,
+            None => DEFAULT_PORT,
+        };
+        if host.is_empty() || user.is_empty() {
+            return None;
+        }
+        Some(SftpUrl { user, host: host.to_string(), port, path })
+    }
+
+    /// 接続先（`sftp://user@host[:port]`）を取得する
+    /// ヘッダーのパスの先頭に表示し、接続を使い回すかどうかの判定に使う
+    pub fn origin(&self) -> String {
+        let host = if self.host.contains(':') { format!("[{}]", self.host) } else { self.host.clone() };
+        match self.port {
+            DEFAULT_PORT => format!("{}{}@{}", SftpUrl::SCHEME, self.user, host),
+            port => format!("{}{}@{}:{}", SftpUrl::SCHEME, self.user, host, port),
+        }
+    }
+
+    /// known_hostsでのホストの名前（既定以外のポートは `[host]:port`）
+    fn known_host_name(&self) -> String {
+        match self.port {
+            DEFAULT_PORT => self.host.clone(),
+            port => format!("[{}]:{}", self.host, port),
+        }
+    }
+}
+
+/// 接続の進行状況
+///
+/// # バリアント
+/// * `Connected` - 接続できた
+/// * `UnknownHost` - known_hostsにないホストで、ホスト鍵を受け入れるかの確認が必要（鍵のフィンガープリント）
+/// * `Password` - パスワード（または秘密鍵のパスフレーズ）の入力が必要
+pub enum ConnectStep {
+    Connected(SftpFs),
+    UnknownHost(Connecting, String),
+    Password(Connecting),
+}
+
+/// 接続途中のセッション
+/// ホスト鍵の確認やパスワードの入力を待つ間、認証前のセッションを保持する
+///
+/// # フィールド
+/// * `url` - 接続先
+/// * `session` - 認証前のセッション
+/// * `attempts` - 入力されたパスワードで認証を試した回数
+pub struct Connecting {
+    url: SftpUrl,
+    session: Session,
+    attempts: u32,
+}
+
+impl Connecting {
+    /// 接続してホスト鍵を確認し、SSHエージェントと `~/.ssh` の秘密鍵で認証を試す
+    ///
+    /// # 引数
+    /// * `url` - 接続先
+    pub fn start(url: SftpUrl) -> fms::Result<ConnectStep> {
+        let addresses = (url.host.as_str(), url.port).to_socket_addrs()?;
+        let mut last_error = io::Error::from(io::ErrorKind::NotFound);
+        let mut stream = None;
+        for address in addresses {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        let stream = stream.ok_or(last_error)?;
+
+        let mut session = Session::new()?;
+        session.set_timeout(SESSION_TIMEOUT_MS);
+        session.set_tcp_stream(stream);
+        session.handshake()?;
+        let connecting = Connecting { url, session, attempts: 0 };
+
+        let (known_hosts, _) = connecting.known_hosts()?;
+        let (key, _) = connecting.session.host_key().ok_or_else(|| FmsError::HostKeyChanged(connecting.url.host.clone()))?;
+        match known_hosts.check_port(&connecting.url.host, connecting.url.port, key) {
+            CheckResult::Match => connecting.authenticate_without_password(),
+            CheckResult::Mismatch => Err(FmsError::HostKeyChanged(connecting.url.host.clone())),
+            // 照合できない場合も、未知のホストとして確認する
+            CheckResult::NotFound | CheckResult::Failure => {
+                let fingerprint = connecting.fingerprint();
+                Ok(ConnectStep::UnknownHost(connecting, fingerprint))
+            }
+        }
+    }
+
+    /// 接続先を取得する
+    pub fn url(&self) -> &SftpUrl {
+        &self.url
+    }
+
+    /// 入力されたパスワードで認証を試した回数を取得する
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// ホスト鍵を受け入れてknown_hostsに追加し、認証を続ける
+    /// known_hostsに書き込めない場合も、今回の接続は続けます
+    pub fn accept_host_key(self) -> fms::Result<ConnectStep> {
+        if let (Ok((mut known_hosts, Some(path))), Some((key, key_type))) = (self.known_hosts(), self.session.host_key()) {
+            let added = known_hosts.add(&self.url.known_host_name(), key, "", key_type.into());
+            if added.is_ok() {
+                if let Some(dir) = path.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                let _ = known_hosts.write_file(&path, KnownHostFileKind::OpenSSH);
+            }
+        }
+        self.authenticate_without_password()
+    }
+
+    /// 入力されたパスワードで認証する
+    /// 暗号化された秘密鍵のパスフレーズとして試してから、パスワード認証・キーボードインタラクティブ認証を試します
+    ///
+    /// # 戻り値
+    /// `fms::Result<ConnectStep>` - 認証できなかった場合は再入力（`Password`）、回数を超えた場合はエラー
+    pub fn authenticate(mut self, password: &str) -> fms::Result<ConnectStep> {
+        let user = self.url.user.clone();
+        for (private, public) in identity_files() {
+            if self.session.userauth_pubkey_file(&user, public.as_deref(), &private, Some(password)).is_ok() {
+                return self.connected();
+            }
+        }
+        let _ = self.session.userauth_password(&user, password);
+        if !self.session.authenticated() {
+            let _ = self.session.userauth_keyboard_interactive(&user, &mut PasswordPrompt(password));
+        }
+        if self.session.authenticated() {
+            return self.connected();
+        }
+        self.attempts += 1;
+        if self.attempts >= MAX_PASSWORD_ATTEMPTS {
+            return Err(FmsError::AuthenticationFailed(self.url.origin()));
+        }
+        Ok(ConnectStep::Password(self))
+    }
+
+    /// SSHエージェントと、パスフレーズのない `~/.ssh` の秘密鍵で認証を試す
+    /// 認証できなかった場合はパスワードの入力に進みます
+    fn authenticate_without_password(self) -> fms::Result<ConnectStep> {
+        let user = &self.url.user;
+        let _ = self.session.userauth_agent(user);
+        for (private, public) in identity_files() {
+            if self.session.authenticated() {
+                break;
+            }
+            let _ = self.session.userauth_pubkey_file(user, public.as_deref(), &private, None);
+        }
+        if self.session.authenticated() {
+            return self.connected();
+        }
+        Ok(ConnectStep::Password(self))
+    }
+
+    /// 認証済みのセッションでSFTPを開始する
+    fn connected(self) -> fms::Result<ConnectStep> {
+        let sftp = self.session.sftp()?;
+        let home = sftp.realpath(Path::new("."))?;
+        Ok(ConnectStep::Connected(SftpFs { url: self.url, _session: self.session, sftp: Arc::new(sftp), home }))
+    }
+
+    /// `~/.ssh/known_hosts` を読み込む
+    ///
+    /// # 戻り値
+    /// `fms::Result<(KnownHosts, Option<PathBuf>)>` - 読み込んだknown_hostsとファイルのパス（ホームディレクトリが不明な場合は `None`）
+    fn known_hosts(&self) -> fms::Result<(KnownHosts, Option<PathBuf>)> {
+        let mut known_hosts = self.session.known_hosts()?;
+        let path = ssh_dir().map(|dir| dir.join("known_hosts"));
+        if let Some(path) = path.as_ref().filter(|path| path.is_file()) {
+            known_hosts.read_file(path, KnownHostFileKind::OpenSSH)?;
+        }
+        Ok((known_hosts, path))
+    }
+
+    /// ホスト鍵のフィンガープリントを、OpenSSHと同じ `SHA256:...` 形式で取得する
+    fn fingerprint(&self) -> String {
+        let hash = self.session.host_key_hash(HashType::Sha256).unwrap_or_default();
+        format!("SHA256:{}", base64_encode(hash))
+    }
+}
+
+/// キーボードインタラクティブ認証のすべての質問に、入力されたパスワードで答える
+struct PasswordPrompt<'a>(&'a str);
+
+impl ssh2::KeyboardInteractivePrompt for PasswordPrompt<'_> {
+    fn prompt<'a>(&mut self, _username: &str, _instructions: &str, prompts: &[Prompt<'a>]) -> Vec<String> {
+        prompts.iter().map(|_| self.0.to_string()).collect()
+    }
+}
+
+/// SFTPで接続したリモートのファイルシステム
+/// パスはルート（/）からの相対パスで、空はルートです
+/// 複製すると同じ接続を使うため、バックグラウンドの処理に渡してコピーなどに使えます
+///
+/// # フィールド
+/// * `url` - 接続先
+/// * `_session` - SFTPのチャンネルを開いているセッション（接続を保つために保持する）
+/// * `sftp` - SFTPのチャンネル
+/// * `home` - ログインしたユーザーのホームディレクトリ（絶対パス）
+#[derive(Clone)]
+pub struct SftpFs {
+    url: SftpUrl,
+    _session: Session,
+    sftp: Arc<Sftp>,
+    home: PathBuf,
+}
+
+impl SftpFs {
+    /// 接続先を取得する
+    pub fn url(&self) -> &SftpUrl {
+        &self.url
+    }
+
+    /// URLのパスを、ファイルシステム内のパス（ルートからの相対パス）にする
+    /// パスがない場合と `~` で始まる場合は、ホームディレクトリからのパスです
+    pub fn resolve(&self, path: Option<&str>) -> PathBuf {
+        let absolute = match path {
+            None => self.home.clone(),
+            Some(path) => match path.strip_prefix('~') {
+                Some(rest) => self.home.join(rest.trim_start_matches('/')),
+                None => Path::new("/").join(path),
+            },
+        };
+        absolute.strip_prefix("/").map(Path::to_path_buf).unwrap_or(absolute)
+    }
+
+    /// ファイルシステム内のパスを、リモートの絶対パスにする
+    fn remote(&self, path: &Path) -> PathBuf {
+        Path::new("/").join(path)
+    }
+
+    /// ディレクトリを中身ごと削除する
+    fn remove_dir_all(&self, path: &Path) -> fms::Result<()> {
+        for (child, stat) in self.sftp.readdir(path)? {
+            if stat.is_dir() {
+                self.remove_dir_all(&child)?;
+            } else {
+                self.sftp.unlink(&child)?;
+            }
+        }
+        Ok(self.sftp.rmdir(path)?)
+    }
+}
+
+impl Vfs for SftpFs {
+    fn list(&self, dir: &Path) -> fms::Result<Vec<PathKind>> {
+        let remote = self.remote(dir);
+        let mut file_list = vec![PathKind::DIR(FileInformation { file_name: "..".to_string(), ..Default::default() })];
+        for (file_no, (path, stat)) in self.sftp.readdir(&remote)?.into_iter().enumerate() {
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            // シンボリックリンクはリンク先がディレクトリならディレクトリとして扱う（ローカルの一覧と同じ）
+            let is_dir = stat.is_dir()
+                || (stat.file_type().is_symlink() && self.sftp.stat(&path).is_ok_and(|target| target.is_dir()));
+            file_list.push(path_kind(file_no + 1, file_name, &stat, is_dir));
+        }
+        fms::sort_file_list(&mut file_list);
+        Ok(file_list)
+    }
+
+    fn stat(&self, path: &Path) -> fms::Result<PathKind> {
+        let stat = self.sftp.stat(&self.remote(path))?;
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        Ok(path_kind(0, &file_name, &stat, stat.is_dir()))
+    }
+
+    fn open_read(&self, path: &Path) -> fms::Result<Box<dyn Read>> {
+        Ok(Box::new(self.sftp.open(self.remote(path))?))
+    }
+
+    fn open_write(&self, path: &Path) -> fms::Result<Box<dyn Write>> {
+        Ok(Box::new(self.sftp.create(&self.remote(path))?))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> fms::Result<()> {
+        Ok(self.sftp.rename(&self.remote(from), &self.remote(to), None)?)
+    }
+
+    fn remove(&self, path: &Path) -> fms::Result<()> {
+        let remote = self.remote(path);
+        if self.sftp.lstat(&remote)?.is_dir() {
+            return self.remove_dir_all(&remote);
+        }
+        Ok(self.sftp.unlink(&remote)?)
+    }
+
+    fn mkdir(&self, path: &Path) -> fms::Result<()> {
+        Ok(self.sftp.mkdir(&self.remote(path), 0o755)?)
+    }
+}
+
+/// リモートのファイルの属性をファイル情報にする
+/// 所有者・グループは名前を取得できないため、IDを表示します
+fn path_kind(file_no: usize, file_name: &str, stat: &FileStat, is_dir: bool) -> PathKind {
+    let datetime = |time: Option<u64>| time
+        .and_then(|time| DateTime::from_timestamp(time as i64, 0))
+        .map(|datetime| datetime.with_timezone(&Local));
+    let info = FileInformation {
+        file_no,
+        file_name: file_name.to_string(),
+        file_size: stat.size.unwrap_or(0),
+        last_modified: datetime(stat.mtime),
+        last_accessed: datetime(stat.atime),
+        mode: stat.perm,
+        owner: stat.uid.map(|uid| uid.to_string()),
+        group: stat.gid.map(|gid| gid.to_string()),
+        ..Default::default()
+    };
+    if is_dir { PathKind::DIR(info) } else { PathKind::FILE(info) }
+}
+
+/// ローカルのユーザー名を取得する
+fn local_user() -> Option<String> {
+    env::var("USER").or_else(|_| env::var("USERNAME")).ok()
+}
+
+/// `~/.ssh` のパスを取得する
+fn ssh_dir() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".ssh"))
+}
+
+/// 存在する `~/.ssh` の秘密鍵と、対応する公開鍵（あれば）の一覧を取得する
+fn identity_files() -> Vec<(PathBuf, Option<PathBuf>)> {
+    let Some(dir) = ssh_dir() else {
+        return Vec::new();
+    };
+    IDENTITY_FILES.iter()
+        .map(|name| dir.join(name))
+        .filter(|private| private.is_file())
+        .map(|private| {
+            let public = private.with_extension("pub");
+            let public = public.is_file().then_some(public);
+            (private, public)
+        })
+        .collect()
+}
+
+/// パディングなしのBase64（OpenSSHのフィンガープリントの表記）に変換する
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_url_parts() {
+        let url = SftpUrl::parse("sftp://alice@example.com:2222/srv/data/").unwrap();
+        assert_eq!((url.user.as_str(), url.host.as_str(), url.port), ("alice", "example.com", 2222));
+        assert_eq!(url.path.as_deref(), Some("srv/data"));
+
+        let url = SftpUrl::parse("SFTP://bob@host").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path), ("host", DEFAULT_PORT, None));
+
+        let url = SftpUrl::parse("sftp://bob@host/~/docs").unwrap();
+        assert_eq!(url.path.as_deref(), Some("~/docs"));
+
+        // ユーザー名に @ を含む場合は、最後の @ で区切る
+        let url = SftpUrl::parse("sftp://bob@corp@host").unwrap();
+        assert_eq!((url.user.as_str(), url.host.as_str()), ("bob@corp", "host"));
+    }
+
+    #[test]
+    fn parse_ipv6_hosts() {
+        let url = SftpUrl::parse("sftp://carol@[::1]:2200/tmp").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path.as_deref()), ("::1", 2200, Some("tmp")));
+
+        let url = SftpUrl::parse("sftp://carol@[fe80::1]").unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("fe80::1", DEFAULT_PORT));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_urls() {
+        assert!(SftpUrl::parse("ftp://dave@host").is_none());
+        assert!(SftpUrl::parse("sftp://dave@").is_none());
+        assert!(SftpUrl::parse("sftp://@host").is_none());
+        assert!(SftpUrl::parse("sftp://dave@host:port").is_none());
+        assert!(SftpUrl::parse("sftp://dave@host:70000").is_none());
+        assert!(SftpUrl::parse("sftp://dave@[::1").is_none());
+    }
+
+    #[test]
+    fn parse_defaults_to_the_local_user() {
+        if let Some(user) = local_user().filter(|user| !user.is_empty()) {
+            assert_eq!(SftpUrl::parse("sftp://host").unwrap().user, user);
+        }
+    }
+
+    #[test]
+    fn origin_omits_the_default_port_and_brackets_ipv6() {
+        let origin = |url: &str| SftpUrl::parse(url).unwrap().origin();
+        assert_eq!(origin("sftp://erin@host/dir"), "sftp://erin@host");
+        assert_eq!(origin("sftp://erin@host:22"), "sftp://erin@host");
+        assert_eq!(origin("sftp://erin@host:2222/dir"), "sftp://erin@host:2222");
+        assert_eq!(origin("sftp://erin@[::1]:2222"), "sftp://erin@[::1]:2222");
+        assert_eq!(origin("sftp://erin@[::1]"), "sftp://erin@[::1]");
+    }
+
+    #[test]
+    fn base64_without_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg");
+        assert_eq!(base64_encode(b"fo"), "Zm8");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "+/8");
+    }
+
+    /// ローカルのSSHサーバーに、SSHエージェントかパスフレーズのない秘密鍵で接続できる環境で実行する
+    /// 接続先は環境変数 `ONE_TEST_SFTP_URL` で変更できます（既定は `sftp://localhost/tmp`）
+    #[test]
+    #[ignore = "needs an SSH server on localhost"]
+    fn round_trip_through_localhost() {
+        let url = env::var("ONE_TEST_SFTP_URL").unwrap_or_else(|_| "sftp://localhost/tmp".to_string());
+        let url = SftpUrl::parse(&url).unwrap();
+        let path = url.path.clone();
+        let remote = match Connecting::start(url).unwrap() {
+            ConnectStep::Connected(remote) => remote,
+            _ => panic!("the host must be known and accept the agent or an unencrypted key"),
+        };
+        let base = remote.resolve(path.as_deref()).join(format!("one-test-{}", std::process::id()));
+        remote.mkdir(&base).unwrap();
+
+        let local = tempfile::tempdir().unwrap();
+        let source = local.path().join("upload");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("a.txt"), b"hello").unwrap();
+        let uploaded = remote.copy_in(&[source], &base);
+        assert!(uploaded.is_empty(), "{:?}", uploaded);
+
+        let names: Vec<String> = remote.list(&base.join("upload")).unwrap().iter()
+            .map(|file| file.info().file_name.clone())
+            .collect();
+        assert_eq!(names, ["..", "a.txt"]);
+
+        let download = local.path().join("download");
+        std::fs::create_dir(&download).unwrap();
+        let downloaded = remote.copy_out(&[base.join("upload")], &download);
+        assert!(downloaded.is_empty(), "{:?}", downloaded);
+        assert_eq!(std::fs::read(download.join("upload").join("a.txt")).unwrap(), b"hello");
+
+        remote.remove(&base).unwrap();
+        assert!(remote.stat(&base).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+
+use crate::config;
+use crate::job::Job;
+
+/// サブシェルの入れ子の深さを表す環境変数
+/// `one` から起動したシェルでは1以上になる（プロンプトの表示などに使える）
+pub const LEVEL_VAR: &str = "ONE_LEVEL";
+
+/// コマンド履歴に保存する最大件数
+const MAX_HISTORY: usize = 100;
+
+/// 端末を一時停止して実行する外部コマンド
+///
+/// # フィールド
+/// * `command_line` - シェルで実行するコマンドライン
+/// * `cwd` - 作業ディレクトリ
+/// * `pause` - 終了後にEnterキーが押されるまで、出力を表示したまま待つかどうか
+/// * `subshell` - コマンドラインの代わりに対話シェル（`$SHELL`）を起動するかどうか
+#[derive(Clone, Debug)]
+pub struct ExternalCommand {
+    pub command_line: String,
+    pub cwd: PathBuf,
+    pub pause: bool,
+    pub subshell: bool,
+}
+
+impl ExternalCommand {
+    /// 対話シェルを起動するExternalCommandインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `cwd` - シェルの作業ディレクトリ
+    pub fn subshell(cwd: PathBuf) -> ExternalCommand {
+        let shell = env::var(if cfg!(windows) { "COMSPEC" } else { "SHELL" }).ok()
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| if cfg!(windows) { "cmd".to_string() } else { "sh".to_string() });
+        ExternalCommand {
+            command_line: shell,
+            cwd,
+            pause: false,
+            subshell: true,
+        }
+    }
+
+    /// 実行するCommandを作成する
+    /// サブシェルには、入れ子の深さを1つ増やした環境変数 `ONE_LEVEL` を設定します
+    pub fn command(&self) -> Command {
+        if !self.subshell {
+            return command(&self.command_line, &self.cwd);
+        }
+        let level = env::var(LEVEL_VAR).ok().and_then(|level| level.parse::<u32>().ok()).unwrap_or(0);
+        let mut command = Command::new(&self.command_line);
+        command.current_dir(&self.cwd).env(LEVEL_VAR, (level + 1).to_string());
+        command
+    }
+}
+
+/// 出力をビューアに表示するために、バックグラウンドで実行中の外部コマンド
+///
+/// # フィールド
+/// * `command_line` - 実行中のコマンドライン
+/// * `child` - 実行中のプロセス（中止する場合は終了させる）
+/// * `output` - 標準出力と標準エラー出力を読み込む処理（終了すると、読み込んだ出力を返す）
+pub struct CapturedCommand {
+    command_line: String,
+    child: Child,
+    output: Option<Job<String>>,
+}
+
+impl CapturedCommand {
+    /// コマンドラインをバックグラウンドで実行して、出力の読み込みを開始する
+    ///
+    /// # 引数
+    /// * `command_line` - コマンドライン
+    /// * `cwd` - 作業ディレクトリ
+    pub fn start(command_line: String, cwd: &Path) -> io::Result<CapturedCommand> {
+        let mut command = command(&command_line, cwd);
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        // シェルから起動された子プロセスもまとめて終了できるように、別のプロセスグループで実行する
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let output = Job::spawn(command_line.clone(), move |_| {
+            // パイプが詰まってコマンドが止まらないように、標準エラー出力は別のスレッドで読み込む
+            let stderr = thread::spawn(move || read_all(stderr));
+            let mut text = read_all(stdout);
+            text.push_str(&stderr.join().unwrap_or_default());
+            text
+        });
+        Ok(CapturedCommand { command_line, child, output: Some(output) })
+    }
+
+    /// 実行中のコマンドラインを取得する
+    pub fn command_line(&self) -> &str {
+        &self.command_line
+    }
+
+    /// コマンドを終了させる（シェルから起動された子プロセスも含む）
+    /// 終了までに出力された内容は、そのまま取得できます
+    pub fn kill(&mut self) {
+        #[cfg(unix)]
+        {
+            // プロセスグループ全体に送らないと、子プロセスがパイプを開いたまま残る
+            if let Ok(pid) = libc::pid_t::try_from(self.child.id()) {
+                unsafe { libc::kill(-pid, libc::SIGKILL) };
+            }
+        }
+        let _ = self.child.kill();
+    }
+
+    /// コマンドが終了した場合に、出力と終了ステータスを取得する
+    ///
+    /// # 戻り値
+    /// `Option<(String, Option<ExitStatus>)>` - 標準出力・標準エラー出力の順につなげた出力と、終了ステータス（実行中の場合は `None`）
+    pub fn take_output(&mut self) -> Option<(String, Option<ExitStatus>)> {
+        if !self.output.as_ref().is_some_and(Job::is_finished) {
+            return None;
+        }
+        let status = match self.child.try_wait() {
+            Ok(None) => return None,
+            Ok(status) => status,
+            Err(_) => None,
+        };
+        let text = self.output.take().and_then(Job::result).unwrap_or_default();
+        Some((text, status))
+    }
+}
+
+impl Drop for CapturedCommand {
+    /// 終了を待たずに閉じる場合（アプリの終了時など）は、コマンドを終了させる
+    fn drop(&mut self) {
+        if self.output.is_some() {
+            self.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// パイプから最後まで読み込んで、文字列にする（UTF-8として読めないバイトは置き換える）
+fn read_all(pipe: Option<impl Read>) -> String {
+    let mut bytes = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut bytes);
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// 実行したコマンドラインの履歴
+/// 設定ディレクトリの `history` ファイルに1行1件で保存し、次回の起動時に読み込む
+///
+/// # フィールド
+/// * `entries` - コマンドライン（古い順）
+/// * `path` - 保存先のファイルのパス
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// 履歴ファイルを読み込んだHistoryインスタンスを返します。
+    /// ファイルが存在しない場合や読み込めない場合は空の履歴です
+    pub fn load() -> History {
+        let path = config::history_path();
+        let entries = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().filter(|line| !line.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        History { entries, path }
+    }
+
+    /// 履歴を取得する（古い順）
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// コマンドラインを履歴の末尾に追加して保存する
+    /// 同じコマンドラインは古い方を削除し、最大件数を超えた分は古い順に削除します
+    pub fn add(&mut self, command_line: &str) {
+        if command_line.trim().is_empty() || command_line.contains('\n') {
+            return;
+        }
+        self.entries.retain(|entry| entry != command_line);
+        self.entries.push(command_line.to_string());
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY);
+        self.entries.drain(..excess);
+
+        // 保存に失敗しても、起動中は履歴を使えるので無視する
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let _ = fs::write(path, self.entries.join("\n") + "\n");
+        }
+    }
+}
+
+/// コマンドラインのプレースホルダーに埋め込む値
+///
+/// # フィールド
+/// * `focused` - フォーカスされているファイル名（`%f`）
+/// * `marked` - マークされているファイル名、ない場合はフォーカスされているファイル名（`%m`）
+/// * `dir` - 現在のディレクトリ（`%d`）
+/// * `other_dir` - もう1つのディレクトリ、1つ前にいたディレクトリ（`%o`）
+pub struct Placeholders {
+    pub focused: String,
+    pub marked: Vec<String>,
+    pub dir: String,
+    pub other_dir: String,
+}
+
+/// コマンドラインのプレースホルダーを、クォートした値に置き換える
+/// `%%` は `%` に置き換え、それ以外の `%` はそのまま残します
+///
+/// # 引数
+/// * `template` - 入力されたコマンドライン
+/// * `values` - 埋め込む値
+pub fn expand(template: &str, values: &Placeholders) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        let value = match chars.peek() {
+            Some('f') => quote(&values.focused),
+            Some('m') => values.marked.iter().map(|name| quote(name)).collect::<Vec<_>>().join(" "),
+            Some('d') => quote(&values.dir),
+            Some('o') => quote(&values.other_dir),
+            Some('%') => "%".to_string(),
+            _ => {
+                expanded.push('%');
+                continue;
+            }
+        };
+        chars.next();
+        expanded.push_str(&value);
+    }
+    expanded
+}
+
+/// 文字列をシェルの1つの引数として扱われるようにクォートする
+/// Unixでは単一引用符、Windowsでは二重引用符で囲みます
+pub fn quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// コマンドラインをシェル（Unixでは `sh -c`、Windowsでは `cmd /C`）で実行するCommandを作成する
+///
+/// # 引数
+/// * `command_line` - コマンドライン
+/// * `cwd` - 作業ディレクトリ
+pub fn command(command_line: &str, cwd: &Path) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(command_line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    };
+    command.current_dir(cwd);
+    command
+}
+
+/// コマンドラインを端末から切り離して実行する（GUIのプログラム向け）
+/// 終了を待たず、標準入出力は使いません
+///
+/// # 引数
+/// * `command_line` - コマンドライン
+/// * `cwd` - 作業ディレクトリ
+pub fn spawn_detached(command_line: &str, cwd: &Path) -> io::Result<()> {
+    let mut command = command(command_line, cwd);
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // 端末のシグナル（Ctrl+C など）が届かないように、別のプロセスグループで実行する
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command.spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// コマンドが終了するまで出力の取得を繰り返す
+    fn wait_output(command: &mut CapturedCommand) -> (String, Option<ExitStatus>) {
+        let started = Instant::now();
+        loop {
+            if let Some(output) = command.take_output() {
+                return output;
+            }
+            assert!(started.elapsed() < Duration::from_secs(10), "the command did not finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn captured_command_collects_stdout_then_stderr() {
+        let cwd = env::temp_dir();
+        let mut command = CapturedCommand::start("echo out; echo err >&2; exit 3".to_string(), &cwd).unwrap();
+        let (text, status) = wait_output(&mut command);
+        assert_eq!(text, "out\nerr\n");
+        assert_eq!(status.and_then(|status| status.code()), Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn captured_command_can_be_killed() {
+        let cwd = env::temp_dir();
+        let mut command = CapturedCommand::start("sleep 30; echo done".to_string(), &cwd).unwrap();
+        command.kill();
+        let (text, status) = wait_output(&mut command);
+        assert_eq!(text, "");
+        assert!(!status.unwrap().success());
+    }
+
+    /// テスト用のプレースホルダーの値
+    fn placeholders(focused: &str, marked: &[&str]) -> Placeholders {
+        Placeholders {
+            focused: focused.to_string(),
+            marked: marked.iter().map(|name| name.to_string()).collect(),
+            dir: "/home/user/my dir".to_string(),
+            other_dir: "/tmp".to_string(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quote_keeps_special_characters_literal() {
+        assert_eq!(quote("plain"), "'plain'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        // シェルで展開して、元の文字列のまま1つの引数になることを確かめる
+        for name in ["it's", "two  spaces", "$HOME", "`date`", "a\"b", "semi;colon", "*", "", "'"] {
+            let output = command(&format!("printf '%s|' {}", quote(name)), &env::temp_dir()).output().unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{}|", name), "{}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn expand_replaces_placeholders_with_quoted_values() {
+        let values = placeholders("it's a file", &["a b", "$x", "c'd"]);
+        assert_eq!(expand("less %f", &values), r"less 'it'\''s a file'");
+        assert_eq!(expand("tar cf out.tar %m", &values), r"tar cf out.tar 'a b' '$x' 'c'\''d'");
+        assert_eq!(expand("cp %f %o", &values), r"cp 'it'\''s a file' '/tmp'");
+        assert_eq!(expand("ls %d", &values), "ls '/home/user/my dir'");
+    }
+
+    #[test]
+    fn expand_keeps_literal_percent_signs() {
+        let values = placeholders("f", &["f"]);
+        assert_eq!(expand("date +%%Y-%%m", &values), "date +%Y-%m");
+        assert_eq!(expand("echo 100%", &values), "echo 100%");
+        assert_eq!(expand("printf %x %s", &values), "printf %x %s");
+        assert_eq!(expand("%%f", &values), "%f");
+        assert_eq!(expand("", &values), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn expand_marked_names_with_a_single_name() {
+        let values = placeholders("only", &["only"]);
+        assert_eq!(expand("rm -- %m", &values), "rm -- 'only'");
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::config::{theme_path, ThemeConfig};
+use crate::dialog::DialogStyle;
+use crate::fms::PathKind;
+
+/// 画面の配色
+/// 組み込みのテーマ（dark、light、high-contrast）か、テーマファイルから読み込む
+///
+/// # フィールド
+/// * `title` - ヘッダーのタイトル
+/// * `border` - ヘッダーの枠線
+/// * `label` - ヘッダーの項目名（PATH=、PAGE=）
+/// * `value` - ヘッダーの値
+/// * `file` - 通常のファイル
+/// * `directory` - ディレクトリ
+/// * `symlink` - シンボリックリンク
+/// * `executable` - 実行可能なファイル
+/// * `focus` - フォーカスされている行（ファイルの配色に重ねる）
+/// * `marked` - マークされているファイル名（ファイルの配色に重ねる）
+/// * `key` - フッターのキー
+/// * `key_label` - フッターの操作の名前
+/// * `modifier` - フッターの押されている修飾キー
+/// * `viewer_border` - ビューアの枠線
+/// * `dialog_border` - ダイアログの枠線
+/// * `dialog_label` - ダイアログの項目名
+/// * `dialog_hint` - ダイアログの操作の説明
+/// * `dialog_selected` - ダイアログで選択している項目
+/// * `dialog_newer` - 競合ダイアログで更新日時が新しい方の印
+/// * `warning` - 警告メッセージ
+/// * `ls_colors` - `LS_COLORS` によるファイルの配色（無効な場合は `None`）
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub title: Style,
+    pub border: Style,
+    pub label: Style,
+    pub value: Style,
+    pub file: Style,
+    pub directory: Style,
+    pub symlink: Style,
+    pub executable: Style,
+    pub focus: Style,
+    pub marked: Style,
+    pub key: Style,
+    pub key_label: Style,
+    pub modifier: Style,
+    pub viewer_border: Style,
+    pub dialog_border: Style,
+    pub dialog_label: Style,
+    pub dialog_hint: Style,
+    pub dialog_selected: Style,
+    pub dialog_newer: Style,
+    pub warning: Style,
+    ls_colors: Option<LsColors>,
+}
+
+impl Theme {
+    /// 暗い背景向けのテーマ（既定）
+    pub fn dark() -> Theme {
+        Theme {
+            title: Style::default().fg(Color::Blue),
+            border: Style::default().fg(Color::Blue),
+            label: Style::default().fg(Color::LightCyan),
+            value: Style::default().fg(Color::Blue),
+            file: Style::default(),
+            directory: Style::default().fg(Color::Yellow),
+            symlink: Style::default().fg(Color::Cyan),
+            executable: Style::default().fg(Color::LightGreen),
+            focus: Style::default().bg(Color::LightBlue),
+            marked: Style::default().fg(Color::LightMagenta),
+            key: Style::default().fg(Color::Yellow),
+            key_label: Style::default().fg(Color::Black).bg(Color::Gray),
+            modifier: Style::default().fg(Color::Black).bg(Color::Yellow),
+            viewer_border: Style::default().fg(Color::Blue),
+            dialog_border: Style::default().fg(Color::Cyan),
+            dialog_label: Style::default().fg(Color::LightCyan),
+            dialog_hint: Style::default().fg(Color::DarkGray),
+            dialog_selected: Style::default().fg(Color::Black).bg(Color::LightBlue),
+            dialog_newer: Style::default().fg(Color::LightGreen),
+            warning: Style::default().fg(Color::Red),
+            ls_colors: None,
+        }
+    }
+
+    /// 明るい背景向けのテーマ
+    pub fn light() -> Theme {
+        Theme {
+            title: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            border: Style::default().fg(Color::Blue),
+            label: Style::default().fg(Color::Magenta),
+            value: Style::default().fg(Color::Blue),
+            file: Style::default(),
+            directory: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            symlink: Style::default().fg(Color::Cyan),
+            executable: Style::default().fg(Color::Green),
+            focus: Style::default().bg(Color::LightCyan),
+            marked: Style::default().fg(Color::Red),
+            key: Style::default().fg(Color::Red),
+            key_label: Style::default().fg(Color::White).bg(Color::Blue),
+            modifier: Style::default().fg(Color::White).bg(Color::Red),
+            viewer_border: Style::default().fg(Color::Blue),
+            dialog_border: Style::default().fg(Color::Blue),
+            dialog_label: Style::default().fg(Color::Magenta),
+            dialog_hint: Style::default().fg(Color::DarkGray),
+            dialog_selected: Style::default().fg(Color::White).bg(Color::Blue),
+            dialog_newer: Style::default().fg(Color::Green),
+            warning: Style::default().fg(Color::Red),
+            ls_colors: None,
+        }
+    }
+
+    /// 高コントラストのテーマ
+    pub fn high_contrast() -> Theme {
+        Theme {
+            title: Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            border: Style::default().fg(Color::White),
+            label: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            value: Style::default().fg(Color::White),
+            file: Style::default().fg(Color::White),
+            directory: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            symlink: Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            executable: Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            focus: Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD),
+            marked: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            key: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            key_label: Style::default().fg(Color::Black).bg(Color::White),
+            modifier: Style::default().fg(Color::Black).bg(Color::Yellow),
+            viewer_border: Style::default().fg(Color::White),
+            dialog_border: Style::default().fg(Color::White),
+            dialog_label: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            dialog_hint: Style::default().fg(Color::White),
+            dialog_selected: Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD),
+            dialog_newer: Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            warning: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            ls_colors: None,
+        }
+    }
+
+    /// 設定に従ってテーマを読み込む
+    /// 組み込みのテーマ以外の名前は、設定ディレクトリの `themes/<名前>.toml` から読み込みます
+    /// 設定ファイルの `[theme.styles]` の配色はテーマの配色に上書きします
+    ///
+    /// # 引数
+    /// * `config` - 設定ファイルの `[theme]` の内容
+    ///
+    /// # 戻り値
+    /// `io::Result<Theme>` - テーマ（テーマファイルが見つからない・不正な場合はエラー）
+    pub fn load(config: &ThemeConfig) -> io::Result<Theme> {
+        let mut theme = Theme::named(&config.name, 0)?;
+        theme.apply(&config.styles).map_err(|e| invalid_data(format!("[theme.styles] {}", e)))?;
+        if config.ls_colors {
+            theme.ls_colors = env::var("LS_COLORS").ok().map(|value| LsColors::parse(&value));
+        }
+        Ok(theme)
+    }
+
+    /// 名前からテーマを取得する
+    /// テーマファイルは `base` で別のテーマを元にできるため、循環しないように深さを制限します
+    fn named(name: &str, depth: usize) -> io::Result<Theme> {
+        match name {
+            "dark" => return Ok(Theme::dark()),
+            "light" => return Ok(Theme::light()),
+            "high-contrast" => return Ok(Theme::high_contrast()),
+            _ => {}
+        }
+
+        const MAX_DEPTH: usize = 8;
+        let path = theme_path(name)
+            .filter(|_| depth < MAX_DEPTH)
+            .ok_or_else(|| invalid_data(format!("unknown theme `{}`", name)))?;
+        let text = fs::read_to_string(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        let file: ThemeFile = toml::from_str(&text)
+            .map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+
+        let mut theme = Theme::named(file.base.as_deref().unwrap_or("dark"), depth + 1)?;
+        theme.apply(&file.styles).map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+        Ok(theme)
+    }
+
+    /// 名前で指定された配色で上書きする
+    fn apply(&mut self, styles: &HashMap<String, StyleSpec>) -> Result<(), String> {
+        for (name, spec) in styles {
+            let style = match name.as_str() {
+                "title" => &mut self.title,
+                "border" => &mut self.border,
+                "label" => &mut self.label,
+                "value" => &mut self.value,
+                "file" => &mut self.file,
+                "directory" => &mut self.directory,
+                "symlink" => &mut self.symlink,
+                "executable" => &mut self.executable,
+                "focus" => &mut self.focus,
+                "marked" => &mut self.marked,
+                "key" => &mut self.key,
+                "key_label" => &mut self.key_label,
+                "modifier" => &mut self.modifier,
+                "viewer_border" => &mut self.viewer_border,
+                "dialog_border" => &mut self.dialog_border,
+                "dialog_label" => &mut self.dialog_label,
+                "dialog_hint" => &mut self.dialog_hint,
+                "dialog_selected" => &mut self.dialog_selected,
+                "dialog_newer" => &mut self.dialog_newer,
+                "warning" => &mut self.warning,
+                _ => return Err(format!("unknown style `{}`", name)),
+            };
+            *style = spec.to_style()?;
+        }
+        Ok(())
+    }
+
+    /// ダイアログの配色を取得する
+    pub fn dialog_style(&self) -> DialogStyle {
+        DialogStyle {
+            border: self.dialog_border,
+            label: self.dialog_label,
+            hint: self.dialog_hint,
+            selected: self.dialog_selected,
+            newer: self.dialog_newer,
+        }
+    }
+
+    /// ファイルの配色を取得する
+    /// `LS_COLORS` が有効な場合はそれを優先し、該当しない場合はテーマの配色を使います
+    pub fn file_style(&self, file: &PathKind) -> Style {
+        let kind = FileKind::of(file);
+        if let Some(style) = self.ls_colors.as_ref().and_then(|ls_colors| ls_colors.style(file, kind)) {
+            return style;
+        }
+        match kind {
+            FileKind::Directory => self.directory,
+            FileKind::Symlink => self.symlink,
+            FileKind::Executable => self.executable,
+            _ => self.file,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+/// テーマファイルの内容
+///
+/// ```toml
+/// base = "dark"
+///
+/// [styles]
+/// focus = { fg = "black", bg = "#87afd7" }
+/// directory = { fg = "yellow", modifiers = ["bold"] }
+/// ```
+///
+/// # フィールド
+/// * `base` - 元にするテーマの名前（省略時は dark）
+/// * `styles` - 上書きする配色
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    styles: HashMap<String, StyleSpec>,
+}
+
+/// 設定ファイルでの配色の指定
+///
+/// # フィールド
+/// * `fg` - 文字色（`yellow`、`lightblue`、`#rrggbb`、0～255の番号など）
+/// * `bg` - 背景色
+/// * `modifiers` - 文字の装飾（bold、dim、italic、underlined、reversed など）
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleSpec {
+    fg: Option<String>,
+    bg: Option<String>,
+    modifiers: Vec<String>,
+}
+
+impl StyleSpec {
+    /// ratatuiのStyleに変換する
+    fn to_style(&self) -> Result<Style, String> {
+        let color = |name: &str| Color::from_str(name).map_err(|_| format!("invalid color `{}`", name));
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for name in &self.modifiers {
+            style = style.add_modifier(match name.to_ascii_lowercase().as_str() {
+                "bold" => Modifier::BOLD,
+                "dim" => Modifier::DIM,
+                "italic" => Modifier::ITALIC,
+                "underlined" => Modifier::UNDERLINED,
+                "blink" => Modifier::SLOW_BLINK,
+                "reversed" => Modifier::REVERSED,
+                "hidden" => Modifier::HIDDEN,
+                "crossed_out" => Modifier::CROSSED_OUT,
+                _ => return Err(format!("invalid modifier `{}`", name)),
+            });
+        }
+        Ok(style)
+    }
+}
+
+/// 配色を決めるためのファイルの種別
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileKind {
+    File,
+    Directory,
+    Symlink,
+    Executable,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl FileKind {
+    /// ファイル情報から種別を判定する
+    /// モードビットが取得できない場合はファイルとディレクトリのみ区別します
+    fn of(file: &PathKind) -> FileKind {
+        let mode = file.info().mode.unwrap_or(0);
+        match mode & 0o170000 {
+            0o120000 => FileKind::Symlink,
+            0o010000 => FileKind::Fifo,
+            0o140000 => FileKind::Socket,
+            0o060000 => FileKind::BlockDevice,
+            0o020000 => FileKind::CharDevice,
+            _ => match file {
+                PathKind::DIR(_) => FileKind::Directory,
+                PathKind::FILE(_) if mode & 0o111 != 0 => FileKind::Executable,
+                PathKind::FILE(_) => FileKind::File,
+            },
+        }
+    }
+
+    /// `LS_COLORS` での種別の名前
+    fn ls_key(&self) -> &'static str {
+        match self {
+            FileKind::File => "fi",
+            FileKind::Directory => "di",
+            FileKind::Symlink => "ln",
+            FileKind::Executable => "ex",
+            FileKind::Fifo => "pi",
+            FileKind::Socket => "so",
+            FileKind::BlockDevice => "bd",
+            FileKind::CharDevice => "cd",
+        }
+    }
+}
+
+/// `LS_COLORS` 環境変数の配色
+/// `di=01;34:ln=01;36:*.tar=01;31` のような、種別・拡張子とSGRコードの組を解釈する
+///
+/// # フィールド
+/// * `kinds` - 種別（di、ln、ex など）ごとの配色
+/// * `suffixes` - ファイル名の末尾（小文字）ごとの配色（指定順）
+#[derive(Clone, Debug, Default)]
+struct LsColors {
+    kinds: HashMap<String, Style>,
+    suffixes: Vec<(String, Style)>,
+}
+
+impl LsColors {
+    /// `LS_COLORS` の値を解析する
+    /// 解釈できない項目は無視します
+    fn parse(value: &str) -> LsColors {
+        let mut ls_colors = LsColors::default();
+        for entry in value.split(':') {
+            let Some((key, codes)) = entry.split_once('=') else {
+                continue;
+            };
+            let Some(style) = parse_sgr(codes) else {
+                continue;
+            };
+            match key.strip_prefix('*') {
+                Some(suffix) => ls_colors.suffixes.push((suffix.to_lowercase(), style)),
+                None => { ls_colors.kinds.insert(key.to_string(), style); }
+            }
+        }
+        ls_colors
+    }
+
+    /// ファイルの配色を取得する
+    /// 通常のファイル・実行可能なファイルは、ファイル名の末尾の指定を種別の指定より優先します
+    fn style(&self, file: &PathKind, kind: FileKind) -> Option<Style> {
+        if matches!(kind, FileKind::File | FileKind::Executable) {
+            let name = file.info().file_name.to_lowercase();
+            if let Some((_, style)) = self.suffixes.iter().find(|(suffix, _)| name.ends_with(suffix.as_str())) {
+                return Some(*style);
+            }
+        }
+        self.kinds.get(kind.ls_key()).copied()
+    }
+}
+
+/// SGRコード（`01;34`、`38;5;208` など）をStyleに変換する
+fn parse_sgr(codes: &str) -> Option<Style> {
+    let mut style = Style::default();
+    let mut codes = codes.split(';').map(|code| if code.is_empty() { Some(0) } else { code.parse::<u8>().ok() });
+    while let Some(code) = codes.next() {
+        match code? {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            8 => style = style.add_modifier(Modifier::HIDDEN),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            code @ 30..=37 => style = style.fg(Color::Indexed(code - 30)),
+            38 => style = style.fg(extended_color(&mut codes)?),
+            39 => style = style.fg(Color::Reset),
+            code @ 40..=47 => style = style.bg(Color::Indexed(code - 40)),
+            48 => style = style.bg(extended_color(&mut codes)?),
+            49 => style = style.bg(Color::Reset),
+            code @ 90..=97 => style = style.fg(Color::Indexed(code - 90 + 8)),
+            code @ 100..=107 => style = style.bg(Color::Indexed(code - 100 + 8)),
+            _ => {}
+        }
+    }
+    Some(style)
+}
+
+/// 拡張色の指定（`5;n` または `2;r;g;b`）を読み取る
+fn extended_color(codes: &mut impl Iterator<Item = Option<u8>>) -> Option<Color> {
+    match codes.next()?? {
+        5 => Some(Color::Indexed(codes.next()??)),
+        2 => Some(Color::Rgb(codes.next()??, codes.next()??, codes.next()??)),
+        _ => None,
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fms::FileInformation;
+
+    /// テスト用のファイル情報
+    fn file(name: &str, mode: u32) -> PathKind {
+        PathKind::FILE(FileInformation { file_name: name.to_string(), mode: Some(mode), ..Default::default() })
+    }
+
+    /// テスト用のディレクトリ情報
+    fn dir(name: &str) -> PathKind {
+        PathKind::DIR(FileInformation { file_name: name.to_string(), mode: Some(0o040755), ..Default::default() })
+    }
+
+    #[test]
+    fn parse_sgr_reads_attributes_and_colors() {
+        let cases = [
+            ("0", Some(Style::default())),
+            ("", Some(Style::default())),
+            ("01;34", Some(Style::default().add_modifier(Modifier::BOLD).fg(Color::Indexed(4)))),
+            ("01;0;32", Some(Style::default().fg(Color::Indexed(2)))),
+            ("4;41", Some(Style::default().add_modifier(Modifier::UNDERLINED).bg(Color::Indexed(1)))),
+            ("38;5;208", Some(Style::default().fg(Color::Indexed(208)))),
+            ("48;5;17", Some(Style::default().bg(Color::Indexed(17)))),
+            ("38;2;255;128;0", Some(Style::default().fg(Color::Rgb(255, 128, 0)))),
+            ("90", Some(Style::default().fg(Color::Indexed(8)))),
+            ("97", Some(Style::default().fg(Color::Indexed(15)))),
+            ("100", Some(Style::default().bg(Color::Indexed(8)))),
+            ("107", Some(Style::default().bg(Color::Indexed(15)))),
+            ("39;49", Some(Style::default().fg(Color::Reset).bg(Color::Reset))),
+            ("38;5", None),
+            ("38;2;1;2", None),
+            ("38;9;1", None),
+            ("01;x", None),
+            ("300", None),
+        ];
+        for (codes, expected) in cases {
+            assert_eq!(parse_sgr(codes), expected, "{:?}", codes);
+        }
+    }
+
+    #[test]
+    fn ls_colors_parse_reads_kinds_and_suffixes() {
+        let ls_colors = LsColors::parse("di=01;34:ln=01;36:*.tar=01;31");
+        assert_eq!(ls_colors.kinds["di"], Style::default().add_modifier(Modifier::BOLD).fg(Color::Indexed(4)));
+        assert_eq!(ls_colors.kinds["ln"], Style::default().add_modifier(Modifier::BOLD).fg(Color::Indexed(6)));
+        assert_eq!(ls_colors.suffixes, vec![(".tar".to_string(), Style::default().add_modifier(Modifier::BOLD).fg(Color::Indexed(1)))]);
+    }
+
+    #[test]
+    fn ls_colors_parse_skips_malformed_entries() {
+        let ls_colors = LsColors::parse("di=01;34:bogus::ln=x:*.gz=38;5:*.ZIP=31:");
+        assert_eq!(ls_colors.kinds.len(), 1);
+        assert!(ls_colors.kinds.contains_key("di"));
+        assert_eq!(ls_colors.suffixes, vec![(".zip".to_string(), Style::default().fg(Color::Indexed(1)))]);
+    }
+
+    #[test]
+    fn ls_colors_prefers_suffixes_for_files_only() {
+        let ls_colors = LsColors::parse("di=34:ex=32:fi=37:*.sh=33");
+        let style = |file: &PathKind| ls_colors.style(file, FileKind::of(file));
+        let fg = |index: u8| Some(Style::default().fg(Color::Indexed(index)));
+
+        assert_eq!(style(&file("run.sh", 0o100755)), fg(3));
+        assert_eq!(style(&file("RUN.SH", 0o100644)), fg(3));
+        assert_eq!(style(&file("run", 0o100755)), fg(2));
+        assert_eq!(style(&file("notes.txt", 0o100644)), fg(7));
+        assert_eq!(style(&dir("scripts.sh")), fg(4));
+        // 指定のない種別は、テーマの配色を使う
+        assert_eq!(style(&file("link.sh", 0o120777)), None);
+    }
+
+    #[test]
+    fn file_kind_of_reads_the_mode_bits() {
+        let cases = [
+            (file("a", 0o100644), FileKind::File),
+            (file("a", 0o100755), FileKind::Executable),
+            (file("a", 0o100744), FileKind::Executable),
+            (file("a", 0o120777), FileKind::Symlink),
+            (file("a", 0o010644), FileKind::Fifo),
+            (file("a", 0o140755), FileKind::Socket),
+            (file("a", 0o060660), FileKind::BlockDevice),
+            (file("a", 0o020666), FileKind::CharDevice),
+            (dir("a"), FileKind::Directory),
+            // モードビットが取得できない場合（Windows、アーカイブなど）
+            (PathKind::FILE(FileInformation::default()), FileKind::File),
+            (PathKind::DIR(FileInformation::default()), FileKind::Directory),
+        ];
+        for (file, expected) in cases {
+            assert_eq!(FileKind::of(&file), expected, "{:o}", file.info().mode.unwrap_or(0));
+        }
+    }
+}
@@ -0,0 +1,497 @@
+#[cfg(unix)]
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::fms::{self, FmsError};
+use crate::format;
+use crate::i18n::{tr, Msg};
+use crate::theme::Theme;
+
+use ratatui::{
+    buffer::Buffer, crossterm::event::{KeyCode, KeyEvent}, layout::Rect, style::Style,
+    symbols::border, text::{Line, Span}, widgets::{Block, Paragraph, Widget}
+};
+
+/// 割合を表す棒の幅
+const BAR_WIDTH: usize = 20;
+
+/// ディレクトリツリーの1つのファイル・ディレクトリの使用量
+///
+/// # フィールド
+/// * `name` - ファイル名（UTF-8でない名前もそのまま保持し、削除するパスに使う）
+/// * `size` - サイズ（ディレクトリは中のすべてのファイルの合計）
+/// * `files` - ファイル数（ディレクトリは中のすべてのファイルの数）
+/// * `dir` - ディレクトリかどうか
+/// * `children` - ディレクトリの中のファイル（サイズの大きい順）
+pub struct UsageEntry {
+    name: OsString,
+    size: u64,
+    files: u64,
+    dir: bool,
+    children: Vec<UsageEntry>,
+}
+
+/// スキャンの進行状況（スキャンのスレッドと共有する）
+///
+/// # フィールド
+/// * `files` - スキャンしたファイル数
+/// * `bytes` - スキャンしたファイルの合計サイズ
+/// * `current` - スキャン中のディレクトリ
+#[derive(Default)]
+struct ScanProgress {
+    files: AtomicU64,
+    bytes: AtomicU64,
+    current: Mutex<PathBuf>,
+}
+
+/// ディレクトリツリーの、バックグラウンドでの使用量のスキャン
+/// シンボリックリンクはたどらずにリンク自体を数え、複数のハードリンクがあるファイルは1回だけ数えます
+///
+/// # フィールド
+/// * `dir` - スキャンするディレクトリ
+/// * `receiver` - スキャンの結果
+/// * `cancelled` - スキャンを中止するかどうか（スレッドと共有する）
+/// * `progress` - 進行状況（スレッドと共有する）
+pub struct UsageScan {
+    dir: PathBuf,
+    receiver: Receiver<UsageEntry>,
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<ScanProgress>,
+}
+
+impl UsageScan {
+    /// ディレクトリのスキャンを開始する
+    ///
+    /// # 引数
+    /// * `dir` - スキャンするディレクトリ
+    pub fn start(dir: &Path) -> fms::Result<UsageScan> {
+        if !dir.is_dir() {
+            return Err(FmsError::NotADirectory(dir.to_path_buf()));
+        }
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(ScanProgress::default());
+        let (shared_cancelled, shared_progress) = (Arc::clone(&cancelled), Arc::clone(&progress));
+        let root = dir.to_path_buf();
+        thread::spawn(move || {
+            let mut scanner = Scanner {
+                cancelled: &shared_cancelled,
+                progress: &shared_progress,
+                #[cfg(unix)]
+                linked: HashSet::new(),
+            };
+            if let Some(entry) = scanner.scan_dir(&root, root.clone().into_os_string()) {
+                let _ = sender.send(entry);
+            }
+        });
+        Ok(UsageScan { dir: dir.to_path_buf(), receiver, cancelled, progress })
+    }
+
+    /// スキャンするディレクトリを取得する
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 進行状況を取得する
+    ///
+    /// # 戻り値
+    /// `(u64, u64, PathBuf)` - スキャンしたファイル数、合計サイズ、スキャン中のディレクトリ
+    pub fn progress(&self) -> (u64, u64, PathBuf) {
+        let current = self.progress.current.lock().map(|current| current.clone()).unwrap_or_default();
+        (self.progress.files.load(Ordering::Relaxed), self.progress.bytes.load(Ordering::Relaxed), current)
+    }
+
+    /// スキャンが終わった場合に、結果を受け取る
+    ///
+    /// # 戻り値
+    /// `Option<UsageEntry>` - スキャンしたディレクトリの使用量（スキャン中の場合は `None`）
+    pub fn take_result(&self) -> Option<UsageEntry> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for UsageScan {
+    /// スキャンを中止した場合や、別のディレクトリに移動した場合に、スキャンのスレッドを止める
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// スキャンのスレッドの状態
+///
+/// # フィールド
+/// * `cancelled` - スキャンを中止するかどうか
+/// * `progress` - 進行状況
+/// * `linked` - 数えたハードリンクのファイル（デバイス番号とiノード番号）
+struct Scanner<'a> {
+    cancelled: &'a AtomicBool,
+    progress: &'a ScanProgress,
+    #[cfg(unix)]
+    linked: HashSet<(u64, u64)>,
+}
+
+impl Scanner<'_> {
+    /// ディレクトリの中を再帰的にスキャンする
+    /// 読み込めないディレクトリは空として扱います
+    ///
+    /// # 戻り値
+    /// `Option<UsageEntry>` - ディレクトリの使用量（中止された場合は `None`）
+    fn scan_dir(&mut self, path: &Path, name: OsString) -> Option<UsageEntry> {
+        if let Ok(mut current) = self.progress.current.lock() {
+            *current = path.to_path_buf();
+        }
+        let mut children = Vec::new();
+        for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            // DirEntryのメタデータはシンボリックリンクをたどらない
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let name = entry.file_name();
+            if metadata.is_dir() {
+                children.push(self.scan_dir(&entry.path(), name)?);
+                continue;
+            }
+            // 2つ目以降のハードリンクは一覧に表示するが、サイズとファイル数には数えない
+            #[cfg(unix)]
+            let counted = metadata.nlink() <= 1 || self.linked.insert((metadata.dev(), metadata.ino()));
+            #[cfg(not(unix))]
+            let counted = true;
+            let (size, files) = if counted { (metadata.len(), 1) } else { (0, 0) };
+            self.progress.files.fetch_add(files, Ordering::Relaxed);
+            self.progress.bytes.fetch_add(size, Ordering::Relaxed);
+            children.push(UsageEntry { name, size, files, dir: false, children: Vec::new() });
+        }
+        sort_entries(&mut children);
+        Some(UsageEntry {
+            name,
+            size: children.iter().map(|child| child.size).sum(),
+            files: children.iter().map(|child| child.files).sum(),
+            dir: true,
+            children,
+        })
+    }
+}
+
+/// ファイルをサイズの大きい順（同じサイズは名前順）に並べる
+fn sort_entries(entries: &mut [UsageEntry]) {
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// 使用量ビューのキー操作の結果
+///
+/// # バリアント
+/// * `Close` - ビューを閉じる
+/// * `Delete` - 選択しているファイルを削除する（削除するパス）
+/// * `Pending` - 操作中
+pub enum UsageResult {
+    Close,
+    Delete(PathBuf),
+    Pending,
+}
+
+/// ディレクトリツリーの使用量を、サイズの大きい順に割合の棒と一緒に表示するビュー
+///
+/// # フィールド
+/// * `root_path` - スキャンしたディレクトリのパス
+/// * `root` - スキャンしたディレクトリの使用量
+/// * `trail` - 表示しているディレクトリまでの、各階層で開いたディレクトリのインデックス
+/// * `selected` - 選択しているファイルのインデックス
+/// * `scroll` - 先頭に表示しているファイルのインデックス
+/// * `height` - 描画エリアの高さ（1画面分の移動量）
+/// * `border_style` - 枠線の配色
+/// * `focus_style` - 選択している行の配色
+pub struct UsageView {
+    root_path: PathBuf,
+    root: UsageEntry,
+    trail: Vec<usize>,
+    selected: usize,
+    scroll: usize,
+    height: usize,
+    border_style: Style,
+    focus_style: Style,
+}
+
+impl UsageView {
+    /// フッターに表示する、ファンクションキーの番号と操作の名前
+    pub const FUNCTION_KEYS: [(u8, Msg); 2] = [(3, Msg::Close), (8, Msg::Delete)];
+
+    /// スキャンの結果を表示するUsageViewインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `root_path` - スキャンしたディレクトリのパス
+    /// * `root` - スキャンしたディレクトリの使用量
+    pub fn new(root_path: &Path, root: UsageEntry) -> UsageView {
+        // 既定のテーマの配色（`border_style`・`focus_style` で変更する）
+        let theme = Theme::default();
+        UsageView {
+            root_path: root_path.to_path_buf(),
+            root,
+            trail: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            height: 0,
+            border_style: theme.viewer_border,
+            focus_style: theme.focus,
+        }
+    }
+
+    /// 枠線の配色を設定する
+    pub fn border_style(mut self, style: Style) -> UsageView {
+        self.border_style = style;
+        self
+    }
+
+    /// 選択している行の配色を設定する
+    pub fn focus_style(mut self, style: Style) -> UsageView {
+        self.focus_style = style;
+        self
+    }
+
+    /// 表示しているディレクトリの使用量を取得する
+    fn current(&self) -> &UsageEntry {
+        self.trail.iter().fold(&self.root, |entry, &index| &entry.children[index])
+    }
+
+    /// 表示しているディレクトリのパスを取得する
+    fn current_path(&self) -> PathBuf {
+        let mut path = self.root_path.clone();
+        let mut entry = &self.root;
+        for &index in &self.trail {
+            entry = &entry.children[index];
+            path.push(&entry.name);
+        }
+        path
+    }
+
+    /// 選択しているファイルを指定した数だけ移動する
+    ///
+    /// # 引数
+    /// * `delta` - 移動する数（負の場合は上に移動）
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.current().children.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// キー入力を処理する
+    /// Enter・→で選択しているディレクトリを開き、←・Backspaceで親ディレクトリに戻ります
+    pub fn handle_key(&mut self, key: KeyEvent) -> UsageResult {
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Esc | KeyCode::F(3) | KeyCode::Char('q') => return UsageResult::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(page),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l')
+                if self.current().children.get(self.selected).is_some_and(|entry| entry.dir) => {
+                self.trail.push(self.selected);
+                self.selected = 0;
+                self.scroll = 0;
+            }
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => self.leave_dir(),
+            KeyCode::F(8) | KeyCode::Delete | KeyCode::Char('d') => {
+                if let Some(entry) = self.current().children.get(self.selected) {
+                    return UsageResult::Delete(self.current_path().join(&entry.name));
+                }
+            }
+            _ => {}
+        }
+        UsageResult::Pending
+    }
+
+    /// 親ディレクトリに戻り、開いていたディレクトリを選択する
+    /// 削除で順番が変わっている場合があるため、戻った階層を並べ直します
+    fn leave_dir(&mut self) {
+        let Some(index) = self.trail.pop() else {
+            return;
+        };
+        let mut entry = &mut self.root;
+        for &index in &self.trail {
+            entry = &mut entry.children[index];
+        }
+        let name = entry.children[index].name.clone();
+        sort_entries(&mut entry.children);
+        self.selected = entry.children.iter().position(|child| child.name == name).unwrap_or(0);
+    }
+
+    /// 削除した、選択しているファイルを一覧から取り除き、親ディレクトリの使用量から差し引く
+    pub fn remove_selected(&mut self) {
+        let Some(removed) = self.current().children.get(self.selected) else {
+            return;
+        };
+        let (size, files) = (removed.size, removed.files);
+        let mut entry = &mut self.root;
+        for &index in &self.trail {
+            entry.size -= size;
+            entry.files -= files;
+            entry = &mut entry.children[index];
+        }
+        entry.size -= size;
+        entry.files -= files;
+        entry.children.remove(self.selected);
+        self.move_by(0);
+    }
+}
+
+impl Widget for &mut UsageView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let current = self.current();
+        let total = tr(Msg::UsageTotal(format::file_size(current.size).trim_start().to_string(), format::number(current.files)));
+        let block = Block::bordered()
+            .title(Line::from(format!(" {} ", self.current_path().display())))
+            .title(Line::from(format!(" {} ", total)).right_aligned())
+            .border_set(border::ROUNDED)
+            .border_style(self.border_style);
+        let inner = block.inner(area);
+        self.height = inner.height as usize;
+
+        // 選択しているファイルが見えるようにスクロールする
+        let height = self.height.max(1);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+
+        // 棒の長さは一番大きいファイルを基準に、割合は表示しているディレクトリの合計を基準にする
+        let current = self.current();
+        let largest = current.children.first().map_or(1, |entry| entry.size.max(1));
+        let lines: Vec<Line> = current.children.iter().enumerate()
+            .skip(self.scroll)
+            .take(self.height)
+            .map(|(i, entry)| {
+                let percent = entry.size as f64 * 100.0 / current.size.max(1) as f64;
+                let filled = ((entry.size as f64 * BAR_WIDTH as f64 / largest as f64).round() as usize).min(BAR_WIDTH);
+                let name = entry.name.to_string_lossy();
+                let name = if entry.dir { format!("{}/", name) } else { name.into_owned() };
+                let style = if i == self.selected { self.focus_style } else { Style::default() };
+                Line::from(vec![
+                    Span::raw(format!("{} {:>5.1}% [", format::file_size(entry.size), percent)),
+                    Span::styled("#".repeat(filled), self.border_style),
+                    Span::raw(format!("{}] ", " ".repeat(BAR_WIDTH - filled))),
+                    Span::raw(name),
+                ]).style(style)
+            })
+            .collect();
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    /// テスト用のファイル
+    fn file(name: &str, size: u64) -> UsageEntry {
+        UsageEntry { name: name.into(), size, files: 1, dir: false, children: Vec::new() }
+    }
+
+    /// テスト用のディレクトリ（サイズとファイル数は中のファイルの合計）
+    fn dir(name: &str, mut children: Vec<UsageEntry>) -> UsageEntry {
+        sort_entries(&mut children);
+        UsageEntry {
+            name: name.into(),
+            size: children.iter().map(|child| child.size).sum(),
+            files: children.iter().map(|child| child.files).sum(),
+            dir: true,
+            children,
+        }
+    }
+
+    /// /data
+    /// ├── big/ (100)
+    /// │   ├── a (60)
+    /// │   ├── b (30)
+    /// │   └── c (10)
+    /// ├── mid (50)
+    /// └── small (5)
+    fn view() -> UsageView {
+        let root = dir("/data", vec![
+            file("small", 5),
+            dir("big", vec![file("a", 60), file("b", 30), file("c", 10)]),
+            file("mid", 50),
+        ]);
+        UsageView::new(Path::new("/data"), root)
+    }
+
+    /// キーを押す
+    fn press(view: &mut UsageView, code: KeyCode) -> UsageResult {
+        view.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    /// 表示しているディレクトリのファイル名
+    fn names(view: &UsageView) -> Vec<String> {
+        view.current().children.iter().map(|child| child.name.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn remove_selected_subtracts_from_every_parent() {
+        let mut view = view();
+        assert_eq!(names(&view), ["big", "mid", "small"]);
+        press(&mut view, KeyCode::Enter);
+        assert_eq!(view.current_path(), Path::new("/data/big"));
+
+        view.remove_selected();
+        assert_eq!(names(&view), ["b", "c"]);
+        assert_eq!(view.selected, 0);
+        assert_eq!((view.current().size, view.current().files), (40, 2));
+        assert_eq!((view.root.size, view.root.files), (95, 4));
+
+        // 最後のファイルを削除すると、1つ上を選択する
+        view.move_by(1);
+        view.remove_selected();
+        assert_eq!(names(&view), ["b"]);
+        assert_eq!(view.selected, 0);
+        assert_eq!((view.root.size, view.root.files), (85, 3));
+    }
+
+    #[test]
+    fn leave_dir_sorts_again_and_selects_the_dir() {
+        let mut view = view();
+        press(&mut view, KeyCode::Enter);
+        view.remove_selected();
+        view.remove_selected();
+
+        press(&mut view, KeyCode::Left);
+        assert!(view.trail.is_empty());
+        assert_eq!(names(&view), ["mid", "big", "small"]);
+        assert_eq!(view.selected, 1);
+
+        // 最上位では何もしない
+        press(&mut view, KeyCode::Left);
+        assert_eq!(view.selected, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn delete_keeps_names_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"caf\xe9");
+        let mut entry = file("", 1);
+        entry.name = name.to_os_string();
+        let mut view = UsageView::new(Path::new("/data"), dir("/data", vec![dir("sub", vec![entry])]));
+        press(&mut view, KeyCode::Enter);
+
+        match press(&mut view, KeyCode::Delete) {
+            UsageResult::Delete(path) => assert_eq!(path, Path::new("/data/sub").join(name)),
+            _ => panic!("expected a path to delete"),
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use std::ops::{Div, Sub};
-
 use num::Integer;
 
 /// 割り算の結果を切り上げる関数
@@ -0,0 +1,435 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fms::{self, FmsError, PathKind};
+
+/// ファイルシステムの操作
+/// ローカルのディレクトリ・アーカイブ・SFTPの接続先などを、一覧の表示やファイルの読み書きで同じように扱う
+/// パスはそれぞれのファイルシステム内のパス（ローカルは絶対パス、アーカイブ・SFTPはルートからの相対パスで空はルート）
+pub trait Vfs {
+    /// ディレクトリのファイル一覧を取得する
+    /// 先頭に親ディレクトリ（..）を追加し、ディレクトリが先に来るように名前順で並べます
+    ///
+    /// # 引数
+    /// * `dir` - ディレクトリのパス
+    fn list(&self, dir: &Path) -> fms::Result<Vec<PathKind>>;
+
+    /// ファイル・ディレクトリの情報を取得する
+    ///
+    /// # 引数
+    /// * `path` - ファイル・ディレクトリのパス
+    fn stat(&self, path: &Path) -> fms::Result<PathKind>;
+
+    /// ファイルを読み込み用に開く
+    ///
+    /// # 引数
+    /// * `path` - ファイルのパス
+    fn open_read(&self, path: &Path) -> fms::Result<Box<dyn Read>>;
+
+    /// ファイルを書き込み用に開く（既に存在する場合は空にする）
+    ///
+    /// # 引数
+    /// * `path` - ファイルのパス
+    fn open_write(&self, path: &Path) -> fms::Result<Box<dyn Write>>;
+
+    /// ファイル・ディレクトリの名前を変更する（移動する）
+    ///
+    /// # 引数
+    /// * `from` - 変更前のパス
+    /// * `to` - 変更後のパス
+    fn rename(&self, from: &Path, to: &Path) -> fms::Result<()>;
+
+    /// ファイル・ディレクトリを削除する（ディレクトリは中身も削除する）
+    ///
+    /// # 引数
+    /// * `path` - 削除するパス
+    fn remove(&self, path: &Path) -> fms::Result<()>;
+
+    /// ディレクトリを作成する
+    ///
+    /// # 引数
+    /// * `path` - 作成するディレクトリのパス
+    fn mkdir(&self, path: &Path) -> fms::Result<()>;
+
+    /// ファイル・ディレクトリをローカルのディレクトリにコピーする
+    /// ディレクトリはその中身もすべてコピーし、コピー先に既に存在するファイルは上書きします
+    /// 既定の実装は `list` と `open_read` でたどってコピーします
+    ///
+    /// # 引数
+    /// * `targets` - コピーするパス（それぞれの名前でコピー先に作成する）
+    /// * `dest` - コピー先のローカルのディレクトリ
+    ///
+    /// # 戻り値
+    /// `Vec<(PathBuf, FmsError)>` - コピーできなかったパスとエラー
+    fn copy_out(&self, targets: &[PathBuf], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        let mut errors = Vec::new();
+        for target in targets {
+            if let Some(name) = target.file_name() {
+                copy_tree(self, target, &dest.join(name), &mut errors);
+            }
+        }
+        errors
+    }
+
+    /// ローカルのファイル・ディレクトリをこのファイルシステムのディレクトリにコピーする
+    /// ディレクトリはその中身もすべてコピーし、コピー先に既に存在するファイルは上書きします
+    /// 既定の実装は `mkdir` と `open_write` でコピーします
+    ///
+    /// # 引数
+    /// * `sources` - コピーするローカルのパス（それぞれの名前でコピー先に作成する）
+    /// * `dest` - コピー先のディレクトリ
+    ///
+    /// # 戻り値
+    /// `Vec<(PathBuf, FmsError)>` - コピーできなかったパスとエラー
+    fn copy_in(&self, sources: &[PathBuf], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        let mut errors = Vec::new();
+        for source in sources {
+            if let Some(name) = source.file_name() {
+                copy_tree_in(self, source, &dest.join(name), &mut errors);
+            }
+        }
+        errors
+    }
+}
+
+/// ローカルのファイル・ディレクトリをファイルシステムにコピーする
+/// ディレクトリは中身をたどってコピーし、エラーが発生したパスは飛ばします
+fn copy_tree_in<V: Vfs + ?Sized>(vfs: &V, source: &Path, dest: &Path, errors: &mut Vec<(PathBuf, FmsError)>) {
+    let result = match fs::metadata(source) {
+        Ok(metadata) if metadata.is_dir() => {
+            // 既に存在するディレクトリには、そのままコピーする
+            let created = match vfs.stat(dest) {
+                Ok(PathKind::DIR(_)) => Ok(()),
+                _ => vfs.mkdir(dest),
+            };
+            created.and_then(|_| Ok(fs::read_dir(source)?.collect::<io::Result<Vec<_>>>()?))
+        }
+        Ok(_) => {
+            let result = vfs.open_write(dest)
+                .and_then(|mut writer| Ok(io::copy(&mut File::open(source)?, &mut writer)?));
+            if let Err(e) = result {
+                errors.push((source.to_path_buf(), e));
+            }
+            return;
+        }
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(entries) => {
+            for entry in entries {
+                copy_tree_in(vfs, &entry.path(), &dest.join(entry.file_name()), errors);
+            }
+        }
+        Err(e) => errors.push((source.to_path_buf(), e)),
+    }
+}
+
+/// ファイル・ディレクトリをローカルにコピーする
+/// ディレクトリは中身をたどってコピーし、エラーが発生したパスは飛ばします
+fn copy_tree<V: Vfs + ?Sized>(vfs: &V, path: &Path, dest: &Path, errors: &mut Vec<(PathBuf, FmsError)>) {
+    let result = match vfs.stat(path) {
+        Ok(PathKind::DIR(_)) => fs::create_dir_all(dest).map_err(FmsError::from).and_then(|_| vfs.list(path)),
+        Ok(PathKind::FILE(_)) => {
+            let result = vfs.open_read(path)
+                .and_then(|mut reader| Ok(io::copy(&mut reader, &mut File::create(dest)?)?));
+            if let Err(e) = result {
+                errors.push((path.to_path_buf(), e));
+            }
+            return;
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(children) => {
+            for child in children.iter().map(|child| &child.info().file_name).filter(|name| *name != "..") {
+                copy_tree(vfs, &path.join(child), &dest.join(child), errors);
+            }
+        }
+        Err(e) => errors.push((path.to_path_buf(), e)),
+    }
+}
+
+/// ローカルのファイルシステム
+pub struct LocalFs;
+
+impl Vfs for LocalFs {
+    fn list(&self, dir: &Path) -> fms::Result<Vec<PathKind>> {
+        let dir = dir.to_str().ok_or_else(|| FmsError::InvalidFileName(dir.as_os_str().to_os_string()))?;
+        fms::list_files_in_directory(dir)
+    }
+
+    fn stat(&self, path: &Path) -> fms::Result<PathKind> {
+        fms::path_information(path)
+    }
+
+    fn open_read(&self, path: &Path) -> fms::Result<Box<dyn Read>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn open_write(&self, path: &Path) -> fms::Result<Box<dyn Write>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> fms::Result<()> {
+        Ok(fs::rename(from, to)?)
+    }
+
+    fn remove(&self, path: &Path) -> fms::Result<()> {
+        fms::remove_path(path)
+    }
+
+    fn mkdir(&self, path: &Path) -> fms::Result<()> {
+        Ok(fs::create_dir(path)?)
+    }
+
+    fn copy_out(&self, targets: &[PathBuf], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        targets.iter()
+            .filter_map(|target| {
+                let name = target.file_name()?;
+                fms::copy_path(target, &dest.join(name)).err().map(|e| (target.clone(), e))
+            })
+            .collect()
+    }
+
+    fn copy_in(&self, sources: &[PathBuf], dest: &Path) -> Vec<(PathBuf, FmsError)> {
+        self.copy_out(sources, dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use crate::fms::FileInformation;
+
+    /// テスト用の、メモリ上のファイルシステム
+    /// パスはルートからの相対パスで、値はディレクトリが `None`、ファイルが内容です（ルートは常に存在する）
+    #[derive(Default)]
+    struct MemoryFs {
+        entries: Rc<RefCell<BTreeMap<PathBuf, Option<Vec<u8>>>>>,
+    }
+
+    impl MemoryFs {
+        /// ディレクトリとファイルを作成したMemoryFsインスタンスを返します。
+        /// 内容が `None` のパスはディレクトリです（親ディレクトリは先に指定する）
+        fn with(entries: &[(&str, Option<&str>)]) -> MemoryFs {
+            let vfs = MemoryFs::default();
+            for (path, content) in entries {
+                match content {
+                    Some(content) => vfs.open_write(Path::new(path)).unwrap().write_all(content.as_bytes()).unwrap(),
+                    None => vfs.mkdir(Path::new(path)).unwrap(),
+                }
+            }
+            vfs
+        }
+
+        /// ファイルの内容を取得する
+        fn read(&self, path: &str) -> Option<String> {
+            let entries = self.entries.borrow();
+            let content = entries.get(Path::new(path))?.as_ref()?;
+            Some(String::from_utf8_lossy(content).into_owned())
+        }
+
+        /// ディレクトリとして存在するかどうかを判定する
+        fn is_dir(&self, path: &Path) -> bool {
+            path.as_os_str().is_empty() || matches!(self.entries.borrow().get(path), Some(None))
+        }
+
+        /// 親ディレクトリが存在しない場合のエラー
+        fn check_parent(&self, path: &Path) -> fms::Result<()> {
+            match path.parent() {
+                Some(parent) if self.is_dir(parent) => Ok(()),
+                _ => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            }
+        }
+    }
+
+    /// MemoryFsのファイルへの書き込み（書き込んだ分をすぐに内容に追加する）
+    struct MemoryWriter {
+        entries: Rc<RefCell<BTreeMap<PathBuf, Option<Vec<u8>>>>>,
+        path: PathBuf,
+    }
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(Some(content)) = self.entries.borrow_mut().get_mut(&self.path) {
+                content.extend_from_slice(buf);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Vfs for MemoryFs {
+        fn list(&self, dir: &Path) -> fms::Result<Vec<PathKind>> {
+            if !self.is_dir(dir) {
+                return Err(FmsError::NotADirectory(dir.to_path_buf()));
+            }
+            let mut file_list = vec![fms::parent_dir_entry()];
+            let entries = self.entries.borrow();
+            let children = entries.iter().filter(|(path, _)| path.parent() == Some(dir));
+            for (file_no, (path, content)) in children.enumerate() {
+                let info = FileInformation {
+                    file_no: file_no + 1,
+                    file_name: path.file_name().unwrap().to_string_lossy().into_owned(),
+                    file_size: content.as_ref().map_or(0, |content| content.len() as u64),
+                    ..Default::default()
+                };
+                file_list.push(if content.is_some() { PathKind::FILE(info) } else { PathKind::DIR(info) });
+            }
+            fms::sort_file_list(&mut file_list);
+            Ok(file_list)
+        }
+
+        fn stat(&self, path: &Path) -> fms::Result<PathKind> {
+            let info = |file_size| FileInformation {
+                file_name: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+                file_size,
+                ..Default::default()
+            };
+            if path.as_os_str().is_empty() {
+                return Ok(PathKind::DIR(info(0)));
+            }
+            match self.entries.borrow().get(path) {
+                Some(Some(content)) => Ok(PathKind::FILE(info(content.len() as u64))),
+                Some(None) => Ok(PathKind::DIR(info(0))),
+                None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            }
+        }
+
+        fn open_read(&self, path: &Path) -> fms::Result<Box<dyn Read>> {
+            match self.entries.borrow().get(path) {
+                Some(Some(content)) => Ok(Box::new(Cursor::new(content.clone()))),
+                Some(None) => Err(io::Error::from(io::ErrorKind::IsADirectory).into()),
+                None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            }
+        }
+
+        fn open_write(&self, path: &Path) -> fms::Result<Box<dyn Write>> {
+            self.check_parent(path)?;
+            if self.is_dir(path) {
+                return Err(io::Error::from(io::ErrorKind::IsADirectory).into());
+            }
+            self.entries.borrow_mut().insert(path.to_path_buf(), Some(Vec::new()));
+            Ok(Box::new(MemoryWriter { entries: Rc::clone(&self.entries), path: path.to_path_buf() }))
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> fms::Result<()> {
+            self.check_parent(to)?;
+            let mut entries = self.entries.borrow_mut();
+            let moved: Vec<PathBuf> = entries.keys().filter(|path| path.starts_with(from)).cloned().collect();
+            if moved.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::NotFound).into());
+            }
+            for path in moved {
+                let content = entries.remove(&path).unwrap();
+                entries.insert(to.join(path.strip_prefix(from).unwrap()), content);
+            }
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> fms::Result<()> {
+            let mut entries = self.entries.borrow_mut();
+            let count = entries.len();
+            entries.retain(|entry, _| !entry.starts_with(path));
+            match entries.len() < count {
+                true => Ok(()),
+                false => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+            }
+        }
+
+        fn mkdir(&self, path: &Path) -> fms::Result<()> {
+            self.check_parent(path)?;
+            if self.stat(path).is_ok() {
+                return Err(FmsError::AlreadyExists(path.to_path_buf()));
+            }
+            self.entries.borrow_mut().insert(path.to_path_buf(), None);
+            Ok(())
+        }
+    }
+
+    /// ファイル一覧の名前を取得する
+    fn names(files: &[PathKind]) -> Vec<String> {
+        files.iter().map(|file| file.info().file_name.clone()).collect()
+    }
+
+    #[test]
+    fn list_puts_the_parent_first_and_directories_before_files() {
+        let vfs = MemoryFs::with(&[
+            ("b", None),
+            ("-a", None),
+            ("b/inner.txt", Some("x")),
+            ("a.txt", Some("")),
+            ("C.txt", Some("")),
+        ]);
+        assert_eq!(names(&vfs.list(Path::new("")).unwrap()), ["..", "-a", "b", "C.txt", "a.txt"]);
+        assert_eq!(names(&vfs.list(Path::new("b")).unwrap()), ["..", "inner.txt"]);
+        assert!(matches!(vfs.list(Path::new("a.txt")), Err(FmsError::NotADirectory(_))));
+    }
+
+    #[test]
+    fn stat_reports_kind_and_size() {
+        let vfs = MemoryFs::with(&[("dir", None), ("dir/file", Some("12345"))]);
+        assert!(matches!(vfs.stat(Path::new("")), Ok(PathKind::DIR(_))));
+        assert!(matches!(vfs.stat(Path::new("dir")), Ok(PathKind::DIR(_))));
+        match vfs.stat(Path::new("dir/file")) {
+            Ok(PathKind::FILE(info)) => assert_eq!((info.file_name.as_str(), info.file_size), ("file", 5)),
+            _ => panic!("dir/file should be a file"),
+        }
+        assert!(vfs.stat(Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn default_copy_out_copies_trees_and_reports_failures() {
+        let vfs = MemoryFs::with(&[
+            ("docs", None),
+            ("docs/a.txt", Some("a")),
+            ("docs/sub", None),
+            ("docs/sub/b.txt", Some("b")),
+            ("top.txt", Some("top")),
+        ]);
+        let dest = tempfile::tempdir().unwrap();
+        fs::write(dest.path().join("top.txt"), "old contents").unwrap();
+
+        let targets = [PathBuf::from("docs"), PathBuf::from("top.txt"), PathBuf::from("missing")];
+        let errors = vfs.copy_out(&targets, dest.path());
+
+        assert_eq!(fs::read_to_string(dest.path().join("docs/a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dest.path().join("docs/sub/b.txt")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dest.path().join("top.txt")).unwrap(), "top");
+        let failed: Vec<&PathBuf> = errors.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [Path::new("missing")]);
+    }
+
+    #[test]
+    fn default_copy_in_merges_into_existing_directories() {
+        let source = tempfile::tempdir().unwrap();
+        let docs = source.path().join("docs");
+        fs::create_dir_all(docs.join("sub")).unwrap();
+        fs::write(docs.join("a.txt"), "new a").unwrap();
+        fs::write(docs.join("sub/b.txt"), "b").unwrap();
+        let vfs = MemoryFs::with(&[
+            ("in", None),
+            ("in/docs", None),
+            ("in/docs/a.txt", Some("old a")),
+            ("in/docs/keep.txt", Some("keep")),
+        ]);
+
+        let missing = source.path().join("missing");
+        let errors = vfs.copy_in(&[docs, missing.clone()], Path::new("in"));
+
+        assert_eq!(vfs.read("in/docs/a.txt").as_deref(), Some("new a"));
+        assert_eq!(vfs.read("in/docs/sub/b.txt").as_deref(), Some("b"));
+        assert_eq!(vfs.read("in/docs/keep.txt").as_deref(), Some("keep"));
+        let failed: Vec<&PathBuf> = errors.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [&missing]);
+    }
+}
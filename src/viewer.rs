@@ -0,0 +1,145 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::fms;
+use crate::i18n::Msg;
+use crate::theme::Theme;
+use crate::vfs::Vfs;
+
+use ratatui::{
+    buffer::Buffer, crossterm::event::{KeyCode, KeyEvent}, layout::Rect, style::Style,
+    symbols::border, text::Line, widgets::{Block, Paragraph, Widget, Wrap}
+};
+
+/// ビューアで読み込む最大バイト数
+pub const MAX_VIEW_BYTES: u64 = 4 * 1024 * 1024;
+
+/// タブを展開する幅
+const TAB_WIDTH: usize = 4;
+
+/// テキストビューア
+///
+/// # フィールド
+/// * `title` - タイトル（ファイル名など）
+/// * `lines` - 表示する行
+/// * `scroll` - 先頭に表示している行番号
+/// * `wrap` - 長い行を折り返すかどうか
+/// * `height` - 描画エリアの高さ（1画面分のスクロール量）
+/// * `border_style` - 枠線の配色
+pub struct Viewer {
+    title: String,
+    lines: Vec<String>,
+    scroll: usize,
+    wrap: bool,
+    height: usize,
+    border_style: Style,
+}
+
+impl Viewer {
+    /// フッターに表示する、ファンクションキーの番号と操作の名前
+    pub const FUNCTION_KEYS: [(u8, Msg); 2] = [(3, Msg::Close), (4, Msg::Wrap)];
+
+    /// 文字列を表示するViewerインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `title` - タイトル
+    /// * `text` - 表示する文字列
+    pub fn new(title: &str, text: &str) -> Viewer {
+        let lines = text.lines()
+            .map(|line| line.replace('\t', &" ".repeat(TAB_WIDTH)).replace(|c: char| c.is_control(), "."))
+            .collect();
+        Viewer {
+            title: title.to_string(),
+            lines,
+            scroll: 0,
+            wrap: false,
+            height: 0,
+            // 既定のテーマの配色（`border_style` で変更する）
+            border_style: Theme::default().viewer_border,
+        }
+    }
+
+    /// 枠線の配色を設定する
+    pub fn border_style(mut self, style: Style) -> Viewer {
+        self.border_style = style;
+        self
+    }
+
+    /// ファイルの内容を表示するViewerインスタンスを返します。
+    /// 大きなファイルは先頭のみ読み込み、UTF-8として解釈できないバイトは置き換えて表示します
+    ///
+    /// # 引数
+    /// * `vfs` - ファイルのあるファイルシステム
+    /// * `path` - ファイルシステム内のファイルのパス
+    /// * `title` - タイトル
+    ///
+    /// # 戻り値
+    /// `fms::Result<Viewer>` - ビューア
+    pub fn open(vfs: &dyn Vfs, path: &Path, title: &str) -> fms::Result<Viewer> {
+        let mut bytes = Vec::new();
+        vfs.open_read(path)?.take(MAX_VIEW_BYTES).read_to_end(&mut bytes)?;
+        Ok(Viewer::from_bytes(title, &bytes))
+    }
+
+    /// バイト列を表示するViewerインスタンスを返します。
+    ///
+    /// # 引数
+    /// * `title` - タイトル
+    /// * `bytes` - 表示するバイト列
+    pub fn from_bytes(title: &str, bytes: &[u8]) -> Viewer {
+        Viewer::new(title, &String::from_utf8_lossy(bytes))
+    }
+
+    /// 指定した行数だけスクロールする
+    ///
+    /// # 引数
+    /// * `delta` - スクロールする行数（負の場合は上にスクロール）
+    pub fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.lines.len().saturating_sub(self.height.max(1));
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+
+    /// キー入力を処理する
+    ///
+    /// # 戻り値
+    /// `bool` - ビューアを閉じる場合は `true`
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Esc | KeyCode::F(3) | KeyCode::Char('q') => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_by(page),
+            KeyCode::PageUp | KeyCode::Char('b') => self.scroll_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => self.scroll_by(isize::MAX),
+            KeyCode::F(4) | KeyCode::Char('w') => self.wrap = !self.wrap,
+            _ => {}
+        }
+        false
+    }
+}
+
+impl Widget for &mut Viewer {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let position = format!(" {}/{} ", (self.scroll + 1).min(self.lines.len()), self.lines.len());
+        let block = Block::bordered()
+            .title(Line::from(format!(" {} ", self.title)))
+            .title(Line::from(position).right_aligned())
+            .border_set(border::ROUNDED)
+            .border_style(self.border_style);
+        let inner = block.inner(area);
+        self.height = inner.height as usize;
+
+        let lines: Vec<Line> = self.lines.iter()
+            .skip(self.scroll)
+            .take(self.height)
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        let mut paragraph = Paragraph::new(lines).block(block);
+        if self.wrap {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        }
+        paragraph.render(area, buf);
+    }
+}
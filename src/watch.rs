@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use notify::event::ModifyKind;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// OSの変更通知を使えない場合に、ディレクトリを読み直して変更を調べる間隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// ディレクトリ内で変更されたファイル
+///
+/// # フィールド
+/// * `names` - 作成・変更・削除されたファイルの名前
+/// * `rescan` - 変更を取りこぼした可能性があり、ディレクトリ全体を読み直す必要があるかどうか
+#[derive(Default)]
+pub struct Changes {
+    pub names: HashSet<OsString>,
+    pub rescan: bool,
+}
+
+/// ディレクトリの変更の監視
+/// OSの変更通知（Linuxではinotify）を使い、使えない場合は一定の間隔でディレクトリを読み直します
+/// 変更は監視のスレッドで記録し、`take_changes` で受け取る
+///
+/// # フィールド
+/// * `_watcher` - 監視（破棄すると監視を終了する）
+/// * `changes` - 前回受け取ってからの変更
+pub struct DirWatcher {
+    _watcher: Box<dyn Watcher>,
+    changes: Arc<Mutex<Changes>>,
+}
+
+impl DirWatcher {
+    /// ディレクトリの監視を開始する
+    /// サブディレクトリの中は監視しません
+    ///
+    /// # 引数
+    /// * `dir` - 監視するディレクトリ
+    ///
+    /// # 戻り値
+    /// `Option<DirWatcher>` - 監視（OSの変更通知も読み直しも使えない場合は `None`）
+    pub fn new(dir: &Path) -> Option<DirWatcher> {
+        let changes = Arc::new(Mutex::new(Changes::default()));
+        let watcher = RecommendedWatcher::new(handler(dir, &changes), Config::default())
+            .map(|watcher| Box::new(watcher) as Box<dyn Watcher>)
+            .and_then(|mut watcher| watcher.watch(dir, RecursiveMode::NonRecursive).map(|_| watcher))
+            .or_else(|_| {
+                // 監視できる数の上限に達した場合や、ネットワークファイルシステムなど
+                let config = Config::default().with_poll_interval(POLL_INTERVAL);
+                let mut watcher: Box<dyn Watcher> = Box::new(PollWatcher::new(handler(dir, &changes), config)?);
+                watcher.watch(dir, RecursiveMode::NonRecursive).map(|_| watcher)
+            })
+            .ok()?;
+        Some(DirWatcher { _watcher: watcher, changes })
+    }
+
+    /// 前回受け取ってからの変更を受け取る
+    ///
+    /// # 戻り値
+    /// `Option<Changes>` - 変更（変更がない場合は `None`）
+    pub fn take_changes(&self) -> Option<Changes> {
+        let mut changes = self.changes.lock().ok()?;
+        if changes.names.is_empty() && !changes.rescan {
+            return None;
+        }
+        Some(std::mem::take(&mut *changes))
+    }
+}
+
+/// 変更の通知を受け取って、ディレクトリ直下のファイルの名前を記録する処理を作成する
+/// ファイルを開いた・読んだだけの通知と、ディレクトリ自体の更新日時などの変更の通知は無視します
+fn handler(dir: &Path, changes: &Arc<Mutex<Changes>>) -> impl Fn(notify::Result<Event>) + Send + 'static {
+    let dir: PathBuf = dir.to_path_buf();
+    let changes = Arc::clone(changes);
+    move |event: notify::Result<Event>| {
+        let Ok(mut changes) = changes.lock() else {
+            return;
+        };
+        let event = match event {
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => return,
+            Ok(event) if !event.need_rescan() => event,
+            _ => {
+                changes.rescan = true;
+                return;
+            }
+        };
+        let modified = matches!(event.kind, EventKind::Modify(ModifyKind::Any | ModifyKind::Data(_) | ModifyKind::Metadata(_)));
+        for path in event.paths {
+            match (path.parent(), path.file_name()) {
+                _ if modified && path == dir => {}
+                (Some(parent), Some(name)) if parent == dir => { changes.names.insert(name.to_os_string()); }
+                // ディレクトリ自体の変更（削除・名前の変更など）
+                _ => changes.rescan = true,
+            }
+        }
+    }
+}